@@ -9,25 +9,32 @@
 //! Note: 使用 ureq（纯同步 HTTP 客户端）替代 reqwest::blocking
 //! 避免 Windows 上 file picker 后 tokio runtime 问题
 
-use anyhow::Result;
 use bevy::prelude::Resource;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::io::Read;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
-const BASE_URL: &str = "http://192.168.2.31:5060/api";
-pub const WS_BASE_URL: &str = "ws://192.168.2.31:5060/ws";
+use crate::audio_format::{self, AudioFormat};
+use crate::audio_stream::AudioStream;
+use crate::auth::{AuthState, AuthToken};
+use crate::config::{AppConfig, ServerProfile};
+use crate::tls::{self, TlsSettings};
+use crate::upload_state::{self, ChunkedUploadState, UploadStateStore};
 
 // ============================================================================
 // 编码检测和转换
 // ============================================================================
 
 /// 检测文件编码并转换为 UTF-8
-fn convert_to_utf8(bytes: &[u8]) -> Result<String> {
+pub(crate) fn convert_to_utf8(bytes: &[u8]) -> String {
     // 首先尝试直接解析为 UTF-8
     if let Ok(text) = std::str::from_utf8(bytes) {
         tracing::info!("File is already UTF-8");
-        return Ok(text.to_string());
+        return text.to_string();
     }
     
     // 使用 chardetng 检测编码
@@ -43,13 +50,278 @@ fn convert_to_utf8(bytes: &[u8]) -> Result<String> {
         tracing::warn!("Encoding conversion had some errors, but continuing");
     }
     
-    Ok(text.into_owned())
+    text.into_owned()
+}
+
+// ============================================================================
+// 错误类型
+// ============================================================================
+
+/// API 错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// 网络传输错误（连接失败、DNS 解析失败、连接被重置等）
+    Transport(String),
+    /// 请求超时
+    Timeout,
+    /// 非 2xx HTTP 状态码
+    Http { status: u16, message: String },
+    /// 服务端返回的业务错误（errno != 0）
+    Server { errno: i32, message: String },
+    /// 响应解析失败
+    Decode(String),
+    /// 资源尚未就绪（如音频仍在推理中）
+    NotReady,
+    /// 本地文件读取失败
+    Io(String),
+    /// 用户取消
+    Cancelled,
+    /// 未登录或 token 已失效（HTTP 401）
+    Unauthorized(String),
+    /// 当前后端不支持该操作
+    Unsupported(String),
+    /// 请求参数无效（在客户端校验时发现）
+    Invalid(String),
+}
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+impl ApiError {
+    /// 是否为可忽略的"未就绪"错误
+    pub fn is_not_ready(&self) -> bool {
+        matches!(self, ApiError::NotReady)
+    }
+
+    /// 是否需要重新登录
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self, ApiError::Unauthorized(_))
+    }
+
+    /// 是否值得重试（网络问题、超时、服务端 5xx）
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::Transport(_) | ApiError::Timeout => true,
+            ApiError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// 读取响应体时的 IO 错误
+    pub(crate) fn from_body_error(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::InvalidData => ApiError::Decode(e.to_string()),
+            std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => ApiError::Timeout,
+            _ => ApiError::Transport(e.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Transport(msg) => write!(f, "网络错误: {}", msg),
+            ApiError::Timeout => write!(f, "请求超时"),
+            ApiError::Http { status, message } if message.is_empty() => write!(f, "HTTP {}", status),
+            ApiError::Http { status, message } => write!(f, "HTTP {}: {}", status, message),
+            ApiError::Server { errno, message } => write!(f, "服务端错误 ({}): {}", errno, message),
+            ApiError::Decode(msg) => write!(f, "响应解析失败: {}", msg),
+            ApiError::NotReady => write!(f, "资源尚未就绪"),
+            ApiError::Io(msg) => write!(f, "文件读取失败: {}", msg),
+            ApiError::Cancelled => write!(f, "已取消"),
+            ApiError::Unauthorized(msg) if msg.is_empty() => write!(f, "未登录或登录已过期"),
+            ApiError::Unauthorized(msg) => write!(f, "未登录或登录已过期: {}", msg),
+            ApiError::Unsupported(msg) => write!(f, "当前后端不支持{}", msg),
+            ApiError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<ureq::Error> for ApiError {
+    fn from(e: ureq::Error) -> Self {
+        match e {
+            ureq::Error::Status(status, resp) => {
+                let body = resp.into_string().unwrap_or_default();
+                if status == 401 {
+                    let message = serde_json::from_str::<ApiResponse<EmptyData>>(&body)
+                        .map(|r| r.error)
+                        .unwrap_or(body);
+                    return ApiError::Unauthorized(message);
+                }
+                // 服务端可能在非 2xx 响应中也返回统一格式的错误
+                if let Ok(api_resp) = serde_json::from_str::<ApiResponse<EmptyData>>(&body) {
+                    if api_resp.errno != 0 {
+                        return ApiError::Server { errno: api_resp.errno, message: api_resp.error };
+                    }
+                }
+                ApiError::Http { status, message: body }
+            }
+            ureq::Error::Transport(t) => {
+                use std::error::Error as _;
+                let timed_out = t
+                    .source()
+                    .and_then(|s| s.downcast_ref::<std::io::Error>())
+                    .map(|e| matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock))
+                    .unwrap_or(false);
+                if timed_out {
+                    ApiError::Timeout
+                } else {
+                    ApiError::Transport(t.to_string())
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// 重试策略
+// ============================================================================
+
+/// 幂等请求的重试策略（指数退避 + 随机抖动）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 最大尝试次数（含首次请求），1 表示不重试
+    pub max_attempts: u32,
+    /// 首次重试前的基础等待时间（毫秒）
+    pub base_delay_ms: u64,
+    /// 单次等待时间上限（毫秒）
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 4000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次失败后的等待时间：`base * 2^(attempt-1)`，取上限后在 [50%, 100%] 之间抖动
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay_ms);
+        let half = exp / 2;
+        Duration::from_millis(half + (random_u64() % (half + 1)))
+    }
+}
+
+/// 随机数（仅用于退避抖动，不需要密码学强度）
+fn random_u64() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish()
+}
+
+/// 一次重试的信息（通知给 UI）
+#[derive(Debug, Clone)]
+pub struct RetryAttempt {
+    /// 即将进行的尝试次数（从 2 开始）
+    pub attempt: u32,
+    pub max_attempts: u32,
+    /// 上一次尝试的错误
+    pub error: ApiError,
+}
+
+/// 重试通知回调
+pub type RetryObserver = Arc<dyn Fn(&RetryAttempt) + Send + Sync>;
+
+// ============================================================================
+// 连接池与超时
+// ============================================================================
+
+/// 按操作类型区分的 HTTP 超时（秒）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpTimeouts {
+    /// 建立 TCP 连接
+    pub connect_secs: u64,
+    /// 列表、查询、会话控制等元数据请求
+    pub metadata_secs: u64,
+    /// 小说 / 音色上传
+    pub upload_secs: u64,
+    /// 音频下载
+    pub audio_secs: u64,
+}
+
+impl Default for HttpTimeouts {
+    fn default() -> Self {
+        Self {
+            connect_secs: 5,
+            metadata_secs: 15,
+            upload_secs: 300,
+            audio_secs: 60,
+        }
+    }
+}
+
+/// 请求类别，决定使用哪个超时
+#[derive(Debug, Clone, Copy)]
+enum RequestClass {
+    Metadata,
+    Upload,
+    Audio,
+}
+
+impl HttpTimeouts {
+    fn for_class(&self, class: RequestClass) -> Duration {
+        let secs = match class {
+            RequestClass::Metadata => self.metadata_secs,
+            RequestClass::Upload => self.upload_secs,
+            RequestClass::Audio => self.audio_secs,
+        };
+        Duration::from_secs(secs.max(1))
+    }
+}
+
+/// 流式读取音频响应体时每次读取的字节数
+const AUDIO_STREAM_READ_BYTES: usize = 16 * 1024;
+
+/// 每个主机保留的空闲 keep-alive 连接数
+const MAX_IDLE_CONNECTIONS_PER_HOST: usize = 8;
+
+// ============================================================================
+// 分块上传
+// ============================================================================
+
+/// 大文件分块上传策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkedUploadPolicy {
+    /// 超过该大小（UTF-8 转换后）的小说使用分块上传
+    pub threshold_bytes: u64,
+    /// 建议的分块大小，服务端可在 init 响应中调整
+    pub chunk_bytes: u64,
+}
+
+impl Default for ChunkedUploadPolicy {
+    fn default() -> Self {
+        Self {
+            threshold_bytes: 8 * 1024 * 1024,
+            chunk_bytes: 2 * 1024 * 1024,
+        }
+    }
 }
 
 // ============================================================================
 // 统一响应格式
 // ============================================================================
 
+/// 服务端 errno：资源不存在（包括已过期或未知的分块上传）
+pub const ERRNO_NOT_FOUND: i32 = 40401;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ApiResponse<T> {
     pub errno: i32,
@@ -58,11 +330,11 @@ pub struct ApiResponse<T> {
 }
 
 impl<T> ApiResponse<T> {
-    pub fn into_result(self) -> Result<T> {
+    pub fn into_result(self) -> ApiResult<T> {
         if self.errno == 0 {
-            self.data.ok_or_else(|| anyhow::anyhow!("No data in response"))
+            self.data.ok_or_else(|| ApiError::Decode("No data in response".to_string()))
         } else {
-            Err(anyhow::anyhow!("API error ({}): {}", self.errno, self.error))
+            Err(ApiError::Server { errno: self.errno, message: self.error })
         }
     }
 }
@@ -204,6 +476,53 @@ pub struct QueryTaskStatusResponse {
     pub tasks: Vec<TaskStatusInfo>,
 }
 
+/// 一段已下载的完整音频（批量获取的结果）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentAudio {
+    pub segment_index: u32,
+    pub format: Option<AudioFormat>,
+    pub data: Vec<u8>,
+}
+
+impl SegmentAudio {
+    /// 转换为可直接播放的音频流
+    pub fn to_stream(&self) -> AudioStream {
+        AudioStream::from_bytes(self.data.clone()).with_format(self.format.or(AudioFormat::sniff(&self.data)))
+    }
+}
+
+// ============================================================================
+// Auth DTOs
+// ============================================================================
+
+/// 登录 / 刷新 token 响应
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoginResponse {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+// ============================================================================
+// Chunked Upload DTOs
+// ============================================================================
+
+/// 分块上传初始化响应
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadInitResponse {
+    pub upload_id: String,
+    /// 服务端确定的分块大小
+    pub chunk_size: u64,
+}
+
+/// 分块上传状态查询响应
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadStatusResponse {
+    /// 服务端已收到的分块序号
+    #[serde(default)]
+    pub received_parts: Vec<u32>,
+}
+
 // ============================================================================
 // WebSocket Event DTOs (V2)
 // ============================================================================
@@ -318,12 +637,243 @@ struct QueryTaskStatusRequest {
     task_ids: Vec<String>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+struct EmptyRequest {}
+
+#[derive(Debug, Clone, Serialize)]
+struct LoginRequest<'a> {
+    username: &'a str,
+    password: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RefreshTokenRequest<'a> {
+    refresh_token: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UploadInitRequest<'a> {
+    title: &'a str,
+    file_name: &'a str,
+    total_size: u64,
+    chunk_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UploadIdRequest<'a> {
+    upload_id: &'a str,
+}
+
 /// V2 Get Audio Request
 #[derive(Debug, Clone, Serialize)]
-struct GetAudioRequest {
+struct GetAudioRequest<'a> {
     novel_id: Uuid,
     segment_index: u32,
     voice_id: Uuid,
+    /// 客户端能解码的格式，按优先级排列
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    formats: &'a [AudioFormat],
+}
+
+/// V2 Get Audio Batch Request
+#[derive(Debug, Clone, Serialize)]
+struct GetAudioBatchRequest<'a> {
+    novel_id: Uuid,
+    voice_id: Uuid,
+    segment_indices: &'a [u32],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    formats: &'a [AudioFormat],
+}
+
+/// 批量音频响应中每段音频的段落序号头
+const SEGMENT_INDEX_HEADER: &str = "x-segment-index";
+
+/// multipart 的一个部分：（小写）头部和内容
+type MultipartPart = (HashMap<String, String>, Vec<u8>);
+
+/// 解析 `multipart/mixed` 响应体
+fn parse_multipart_mixed(content_type: &str, body: &[u8]) -> ApiResult<Vec<MultipartPart>> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))
+        .map(|b| b.trim_matches('"'))
+        .ok_or_else(|| ApiError::Decode(format!("missing multipart boundary in {:?}", content_type)))?;
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+    let find = |haystack: &[u8], needle: &[u8]| haystack.windows(needle.len()).position(|w| w == needle);
+    let truncated = || ApiError::Decode("truncated multipart body".to_string());
+
+    let start = find(body, delimiter).ok_or_else(truncated)?;
+    let mut rest = &body[start + delimiter.len()..];
+    let mut parts = Vec::new();
+    // 结束分隔符为 `--boundary--`
+    while !rest.starts_with(b"--") {
+        let line_end = find(rest, b"\r\n").ok_or_else(truncated)?;
+        rest = &rest[line_end + 2..];
+        let header_end = find(rest, b"\r\n\r\n").ok_or_else(truncated)?;
+        let headers = String::from_utf8_lossy(&rest[..header_end])
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        rest = &rest[header_end + 4..];
+
+        let mut next = b"\r\n".to_vec();
+        next.extend_from_slice(delimiter);
+        let content_end = find(rest, &next).ok_or_else(truncated)?;
+        parts.push((headers, rest[..content_end].to_vec()));
+        rest = &rest[content_end + next.len()..];
+    }
+    Ok(parts)
+}
+
+// ============================================================================
+// 流式 multipart 上传
+// ============================================================================
+
+/// 进度回调的最小间隔字节数
+const PROGRESS_REPORT_BYTES: u64 = 64 * 1024;
+
+/// 等待上传线程时检查取消标记的间隔
+const UPLOAD_CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// multipart/form-data 请求体：文本字段 + 单个文件字段
+///
+/// 文件内容不放入内存中的请求体，而是在发送时从 reader 流式读取。
+struct MultipartForm {
+    boundary: String,
+    /// 文本字段 + 文件字段头部
+    head: Vec<u8>,
+}
+
+impl MultipartForm {
+    fn new() -> Self {
+        Self {
+            boundary: format!("----WebKitFormBoundary{}", Uuid::new_v4().simple()),
+            head: Vec::new(),
+        }
+    }
+
+    fn text_field(&mut self, name: &str, value: &str) {
+        self.head.extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+        self.head.extend_from_slice(
+            format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes(),
+        );
+        self.head.extend_from_slice(value.as_bytes());
+        self.head.extend_from_slice(b"\r\n");
+    }
+
+    /// 文件字段头部，必须在所有文本字段之后调用
+    fn file_header(&mut self, name: &str, file_name: &str, content_type: &str) {
+        self.head.extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+        self.head.extend_from_slice(
+            format!(
+                "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n",
+                name, file_name
+            )
+            .as_bytes(),
+        );
+        self.head.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+    }
+
+    /// 发送请求体：头部 + 文件内容 + 结束边界
+    fn send(
+        self,
+        request: ureq::Request,
+        content: impl Read + Send + 'static,
+        content_len: u64,
+        cancel: &AtomicBool,
+        mut on_progress: impl FnMut(u64, u64),
+    ) -> ApiResult<ureq::Response> {
+        let tail = format!("\r\n--{}--\r\n", self.boundary).into_bytes();
+        let total = self.head.len() as u64 + content_len + tail.len() as u64;
+        let body = Cursor::new(self.head).chain(content).chain(Cursor::new(tail));
+        let request = request
+            .set("Content-Type", &format!("multipart/form-data; boundary={}", self.boundary))
+            .set("Content-Length", &total.to_string());
+        send_upload(request, body, total, cancel, &mut on_progress)
+    }
+}
+
+/// 上传线程发回调用线程的消息
+enum UploadMessage {
+    /// 已发送的字节数
+    Progress(u64),
+    /// 收到响应（或发送失败）
+    Done(Box<Result<ureq::Response, ureq::Error>>),
+}
+
+/// 统计已读取字节数并检查中止标记的 reader（在上传线程中读取）
+struct ProgressReader<R> {
+    inner: R,
+    sent: u64,
+    last_reported: u64,
+    abort: Arc<AtomicBool>,
+    progress: std::sync::mpsc::Sender<UploadMessage>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.abort.load(Ordering::Relaxed) {
+            // 不能用 Interrupted，否则 std::io::copy 会一直重试
+            return Err(std::io::Error::other("upload cancelled"));
+        }
+        let n = self.inner.read(buf)?;
+        self.sent += n as u64;
+        if self.sent - self.last_reported >= PROGRESS_REPORT_BYTES || (n == 0 && self.sent != self.last_reported) {
+            self.last_reported = self.sent;
+            let _ = self.progress.send(UploadMessage::Progress(self.sent));
+        }
+        Ok(n)
+    }
+}
+
+/// 在上传线程中发送请求，调用线程报告进度并轮询取消标记
+///
+/// 请求体发完后 ureq 会阻塞等待响应，reader 里的取消检查不再起作用；
+/// 由调用线程等待就能随时放弃请求，上传线程收到响应或超时后自行退出。
+fn send_upload(
+    request: ureq::Request,
+    body: impl Read + Send + 'static,
+    total: u64,
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(u64, u64),
+) -> ApiResult<ureq::Response> {
+    let abort = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = std::sync::mpsc::channel();
+    let reader = ProgressReader {
+        inner: body,
+        sent: 0,
+        last_reported: 0,
+        abort: abort.clone(),
+        progress: tx.clone(),
+    };
+    std::thread::spawn(move || {
+        let _ = tx.send(UploadMessage::Done(Box::new(request.send(reader))));
+    });
+
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            abort.store(true, Ordering::Relaxed);
+            return Err(ApiError::Cancelled);
+        }
+        match rx.recv_timeout(UPLOAD_CANCEL_POLL) {
+            Ok(UploadMessage::Progress(sent)) => on_progress(sent, total),
+            Ok(UploadMessage::Done(result)) => {
+                return (*result).map_err(|e| {
+                    if cancel.load(Ordering::Relaxed) {
+                        ApiError::Cancelled
+                    } else {
+                        ApiError::from(e)
+                    }
+                });
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(ApiError::Transport("upload thread exited".to_string()));
+            }
+        }
+    }
 }
 
 // ============================================================================
@@ -367,114 +917,346 @@ fn ensure_winsock_initialized() {
 #[derive(Clone, Resource)]
 pub struct ApiClient {
     base_url: String,
+    /// 共享的 ureq agent，克隆后共用同一个 keep-alive 连接池
+    agent: ureq::Agent,
+    timeouts: HttpTimeouts,
+    retry_policy: RetryPolicy,
+    retry_observer: Option<RetryObserver>,
+    chunked_upload: ChunkedUploadPolicy,
+    /// 分块上传状态存储，`None` 时不持久化（无法确定配置目录）
+    upload_store: Option<UploadStateStore>,
+    /// 当前 profile 的登录 token（所有克隆共享）
+    auth: AuthState,
+    /// TLS 配置（与 WebSocket 连接共用）
+    tls: Arc<rustls::ClientConfig>,
 }
 
 impl Default for ApiClient {
+    /// 使用配置文件 / 环境变量 / 命令行中的活动 profile
     fn default() -> Self {
-        Self::new(BASE_URL.to_string())
+        Self::from_config(&AppConfig::load())
     }
 }
 
 impl ApiClient {
     pub fn new(base_url: String) -> Self {
+        let timeouts = HttpTimeouts::default();
+        let tls = tls::client_config(&TlsSettings::default()).expect("default TLS config");
         Self {
             base_url,
+            agent: Self::build_agent(&timeouts, &tls),
+            timeouts,
+            retry_policy: RetryPolicy::default(),
+            retry_observer: None,
+            chunked_upload: ChunkedUploadPolicy::default(),
+            upload_store: UploadStateStore::default_dir().map(UploadStateStore::new),
+            auth: AuthState::default(),
+            tls,
+        }
+    }
+
+    /// 根据服务器 profile 创建客户端（使用该 profile 保存的 token 和 TLS 设置）
+    pub fn from_profile(profile: &ServerProfile) -> Self {
+        Self::new(profile.api_base_url())
+            .with_tls(&profile.tls)
+            .with_auth_state(AuthState::new(profile.auth.clone()))
+    }
+
+    /// 根据应用配置创建客户端（活动 profile + 重试策略）
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self::from_profile(config.active())
+            .with_timeouts(config.timeouts.clone())
+            .with_retry_policy(config.retry.clone())
+            .with_chunked_upload_policy(config.chunked_upload.clone())
+    }
+
+    /// 设置超时并重建连接池
+    pub fn with_timeouts(mut self, timeouts: HttpTimeouts) -> Self {
+        self.agent = Self::build_agent(&timeouts, &self.tls);
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// 设置 TLS 信任（自定义 CA / 证书指纹）并重建连接池
+    ///
+    /// 配置无效时记录错误并继续使用默认根证书，连接时会因证书校验失败而报错。
+    pub fn with_tls(mut self, settings: &TlsSettings) -> Self {
+        if settings.is_empty() {
+            return self;
+        }
+        match tls::client_config(settings) {
+            Ok(config) => {
+                self.tls = config;
+                self.agent = Self::build_agent(&self.timeouts, &self.tls);
+            }
+            Err(e) => tracing::error!("Invalid TLS settings for {}: {:#}", self.base_url, e),
+        }
+        self
+    }
+
+    /// TLS 配置（WebSocket 握手使用同一份）
+    pub fn tls_config(&self) -> Arc<rustls::ClientConfig> {
+        self.tls.clone()
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn with_auth_state(mut self, auth: AuthState) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// 登录状态（与 WebSocket 线程共享）
+    pub fn auth(&self) -> &AuthState {
+        &self.auth
+    }
+
+    pub fn with_chunked_upload_policy(mut self, policy: ChunkedUploadPolicy) -> Self {
+        self.chunked_upload = policy;
+        self
+    }
+
+    /// 设置重试通知回调（每次重试前调用）
+    pub fn with_retry_observer(mut self, observer: impl Fn(&RetryAttempt) + Send + Sync + 'static) -> Self {
+        self.retry_observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// 按重试策略执行幂等请求，只重试网络错误、超时和 5xx
+    fn with_retry<T>(&self, op: &str, f: impl Fn() -> ApiResult<T>) -> ApiResult<T> {
+        let max_attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(error) if error.is_retryable() && attempt < max_attempts => {
+                    let delay = self.retry_policy.backoff(attempt);
+                    attempt += 1;
+                    tracing::warn!(
+                        "{} failed: {}, retrying in {:?} (attempt {}/{})",
+                        op, error, delay, attempt, max_attempts
+                    );
+                    if let Some(observer) = &self.retry_observer {
+                        observer(&RetryAttempt { attempt, max_attempts, error });
+                    }
+                    std::thread::sleep(delay);
+                }
+                result => return result,
+            }
         }
     }
     
-    /// 创建新的 ureq agent（纯同步，无 tokio 依赖）
-    fn new_agent() -> ureq::Agent {
-        // 确保 Windows Winsock 已初始化
+    /// 执行请求，401 时刷新 token 后重试一次
+    fn with_reauth<T>(&self, f: impl Fn() -> ApiResult<T>) -> ApiResult<T> {
+        let sent_token = self.auth.access_token();
+        match f() {
+            Err(ApiError::Unauthorized(_)) if self.refresh_after_unauthorized(sent_token.as_deref()) => f(),
+            result => result,
+        }
+    }
+
+    /// 请求携带的 token 被拒绝（401）后刷新 token，返回是否可以用新 token 重试
+    ///
+    /// `rejected` 为被拒绝的 access token。若其他线程已经刷新过则直接返回。
+    /// 无法刷新（没有 refresh token 或 refresh token 也已失效）时清除 token，需要重新登录。
+    pub fn refresh_after_unauthorized(&self, rejected: Option<&str>) -> bool {
+        let _guard = self.auth.refresh_guard();
+        let current = self.auth.token();
+        if current.as_ref().map(|t| t.access_token.as_str()) != rejected {
+            return current.is_some();
+        }
+        let Some(token) = current else { return false };
+        let Some(refresh_token) = token.refresh_token.as_deref() else {
+            tracing::info!("Access token rejected and no refresh token, login required");
+            self.auth.set(None);
+            return false;
+        };
+
+        let refreshed: ApiResult<LoginResponse> =
+            self.send_post("/auth/refresh", &RefreshTokenRequest { refresh_token });
+        match refreshed {
+            Ok(resp) => {
+                tracing::info!("Access token refreshed for {}", token.username);
+                self.auth.set(Some(AuthToken {
+                    username: token.username.clone(),
+                    access_token: resp.access_token,
+                    // 服务端未轮换 refresh token 时继续使用旧的
+                    refresh_token: resp.refresh_token.or(token.refresh_token.clone()),
+                }));
+                true
+            }
+            // 网络问题时保留 token，下次请求再尝试
+            Err(error) if error.is_retryable() => {
+                tracing::warn!("Token refresh failed: {}", error);
+                false
+            }
+            Err(error) => {
+                tracing::info!("Refresh token rejected ({}), login required", error);
+                self.auth.set(None);
+                false
+            }
+        }
+    }
+
+    /// 创建长期存在的 ureq agent（纯同步，无 tokio 依赖）
+    ///
+    /// 整体超时按请求设置，这里只设置连接超时和连接池大小。
+    pub(crate) fn build_agent(timeouts: &HttpTimeouts, tls: &Arc<rustls::ClientConfig>) -> ureq::Agent {
         ensure_winsock_initialized();
-        
+
         ureq::AgentBuilder::new()
-            .timeout(std::time::Duration::from_secs(60))
+            .timeout_connect(Duration::from_secs(timeouts.connect_secs.max(1)))
+            .max_idle_connections_per_host(MAX_IDLE_CONNECTIONS_PER_HOST)
+            .tls_config(tls.clone())
             .build()
     }
 
+    /// 创建请求（复用连接池），按类别设置超时，已登录时携带 Bearer token
+    fn request(&self, method: &str, url: &str, class: RequestClass) -> ureq::Request {
+        // 确保 Windows Winsock 已初始化
+        ensure_winsock_initialized();
+
+        let request = self
+            .agent
+            .request(method, url)
+            .timeout(self.timeouts.for_class(class));
+        match self.auth.bearer() {
+            Some(bearer) => request.set("Authorization", &bearer),
+            None => request,
+        }
+    }
+
     /// 通用 GET 请求
-    fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+    fn get<T: DeserializeOwned>(&self, endpoint: &str) -> ApiResult<T> {
+        self.with_reauth(|| self.send_get(endpoint))
+    }
+
+    /// 通用 POST 请求
+    fn post<R: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &R) -> ApiResult<T> {
+        self.with_reauth(|| self.send_post(endpoint, body))
+    }
+
+    /// POST 请求（无返回数据）
+    fn post_empty<R: Serialize>(&self, endpoint: &str, body: &R) -> ApiResult<()> {
+        self.with_reauth(|| self.send_post_empty(endpoint, body))
+    }
+
+    fn send_get<T: DeserializeOwned>(&self, endpoint: &str) -> ApiResult<T> {
         let url = format!("{}{}", self.base_url, endpoint);
         tracing::debug!("API GET: {}", url);
         
-        let agent = Self::new_agent();
-        let resp = agent.get(&url).call().map_err(|e| {
+        let resp = self.request("GET", &url, RequestClass::Metadata).call().map_err(|e| {
             tracing::error!("GET {} error: {}", url, e);
-            anyhow::anyhow!("HTTP GET error: {}", e)
+            ApiError::from(e)
         })?;
         
         let api_resp: ApiResponse<T> = resp.into_json().map_err(|e| {
             tracing::error!("GET {} json parse error: {}", url, e);
-            anyhow::anyhow!("JSON parse error: {}", e)
+            ApiError::from_body_error(e)
         })?;
         api_resp.into_result()
     }
 
-    /// 通用 POST 请求
-    fn post<R: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &R) -> Result<T> {
+    fn send_post<R: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &R) -> ApiResult<T> {
         let url = format!("{}{}", self.base_url, endpoint);
         tracing::debug!("API POST: {}", url);
         
-        let agent = Self::new_agent();
-        let resp = agent.post(&url)
+        let resp = self.request("POST", &url, RequestClass::Metadata)
             .set("Content-Type", "application/json")
             .send_json(body)
             .map_err(|e| {
                 tracing::error!("POST {} error: {}", url, e);
-                anyhow::anyhow!("HTTP POST error: {}", e)
+                ApiError::from(e)
             })?;
         
         let api_resp: ApiResponse<T> = resp.into_json().map_err(|e| {
             tracing::error!("POST {} json parse error: {}", url, e);
-            anyhow::anyhow!("JSON parse error: {}", e)
+            ApiError::from_body_error(e)
         })?;
         api_resp.into_result()
     }
 
-    /// POST 请求（无返回数据）
-    fn post_empty<R: Serialize>(&self, endpoint: &str, body: &R) -> Result<()> {
+    fn send_post_empty<R: Serialize>(&self, endpoint: &str, body: &R) -> ApiResult<()> {
         let url = format!("{}{}", self.base_url, endpoint);
         tracing::debug!("API POST (empty): {}", url);
         
-        let agent = Self::new_agent();
-        let resp = agent.post(&url)
+        let resp = self.request("POST", &url, RequestClass::Metadata)
             .set("Content-Type", "application/json")
             .send_json(body)
             .map_err(|e| {
                 tracing::error!("POST {} error: {}", url, e);
-                anyhow::anyhow!("HTTP POST error: {}", e)
+                ApiError::from(e)
             })?;
         
         let api_resp: ApiResponse<EmptyData> = resp.into_json().map_err(|e| {
             tracing::error!("POST {} json parse error: {}", url, e);
-            anyhow::anyhow!("JSON parse error: {}", e)
+            ApiError::from_body_error(e)
         })?;
         
         if api_resp.errno == 0 {
             Ok(())
         } else {
-            Err(anyhow::anyhow!("API error ({}): {}", api_resp.errno, api_resp.error))
+            Err(ApiError::Server { errno: api_resp.errno, message: api_resp.error })
         }
     }
 
+    // ========================================================================
+    // Auth APIs
+    // ========================================================================
+
+    /// 用户名/密码登录，成功后后续请求都携带新 token
+    pub fn login(&self, username: &str, password: &str) -> ApiResult<AuthToken> {
+        let resp: LoginResponse = self.send_post("/auth/login", &LoginRequest { username, password })?;
+        let token = AuthToken {
+            username: username.to_string(),
+            access_token: resp.access_token,
+            refresh_token: resp.refresh_token,
+        };
+        self.auth.set(Some(token.clone()));
+        Ok(token)
+    }
+
+    /// 退出登录：通知服务端注销 token（失败不影响本地退出）
+    pub fn logout(&self) -> ApiResult<()> {
+        if self.auth.token().is_none() {
+            return Ok(());
+        }
+        if let Err(e) = self.send_post_empty("/auth/logout", &EmptyRequest {}) {
+            tracing::warn!("Logout request failed: {}", e);
+        }
+        self.auth.set(None);
+        Ok(())
+    }
+
     // ========================================================================
     // Novel APIs
     // ========================================================================
 
-    pub fn list_novels(&self) -> Result<Vec<NovelResponse>> {
-        self.get("/novel/list")
+    pub fn list_novels(&self) -> ApiResult<Vec<NovelResponse>> {
+        self.with_retry("list_novels", || self.get("/novel/list"))
     }
 
-    pub fn get_novel(&self, id: Uuid) -> Result<NovelResponse> {
-        self.post("/novel/get", &IdRequest { id })
+    pub fn get_novel(&self, id: Uuid) -> ApiResult<NovelResponse> {
+        self.with_retry("get_novel", || self.post("/novel/get", &IdRequest { id }))
     }
 
     /// 获取小说段落 (V2: 通过 novel_id 直接查询，不需要 session)
-    pub fn get_novel_segments(&self, novel_id: Uuid, start: Option<usize>, limit: Option<usize>) -> Result<SegmentsResponse> {
-        self.post("/novel/segments", &GetNovelSegmentsRequest { novel_id, start, limit })
+    pub fn get_novel_segments(&self, novel_id: Uuid, start: Option<usize>, limit: Option<usize>) -> ApiResult<SegmentsResponse> {
+        self.with_retry("get_novel_segments", || {
+            self.post("/novel/segments", &GetNovelSegmentsRequest { novel_id, start, limit })
+        })
     }
 
-    pub fn upload_novel(&self, title: &str, file_path: &std::path::Path) -> Result<NovelResponse> {
+    /// 上传小说（流式发送，`on_progress(sent, total)` 报告已发送字节数）
+    pub fn upload_novel(
+        &self,
+        title: &str,
+        file_path: &std::path::Path,
+        cancel: &AtomicBool,
+        on_progress: impl FnMut(u64, u64),
+    ) -> ApiResult<NovelResponse> {
         let url = format!("{}/novel/upload", self.base_url);
         tracing::info!("API upload_novel: url={}, title={}, path={:?}", url, title, file_path);
         
@@ -484,57 +1266,224 @@ impl ApiClient {
             .unwrap_or("novel.txt");
         let file_bytes = std::fs::read(file_path).map_err(|e| {
             tracing::error!("Failed to read file {:?}: {}", file_path, e);
-            anyhow::anyhow!("Failed to read file: {}", e)
+            ApiError::Io(e.to_string())
         })?;
         tracing::info!("File read: {} bytes", file_bytes.len());
 
-        // 检测并转换编码为 UTF-8
-        let file_content = convert_to_utf8(&file_bytes)?;
+        // 检测并转换编码为 UTF-8（需要完整内容，转换后再流式发送）
+        let file_content: Arc<[u8]> = convert_to_utf8(&file_bytes).into_bytes().into();
+        drop(file_bytes);
         tracing::info!("File converted to UTF-8: {} bytes", file_content.len());
 
-        // 构建 multipart form
-        let boundary = format!("----WebKitFormBoundary{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
-        let mut body = Vec::new();
-        
-        // title 字段
-        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-        body.extend_from_slice(b"Content-Disposition: form-data; name=\"title\"\r\n\r\n");
-        body.extend_from_slice(title.as_bytes());
-        body.extend_from_slice(b"\r\n");
-        
-        // file 字段
-        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-        body.extend_from_slice(format!(
-            "Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n",
-            file_name
-        ).as_bytes());
-        body.extend_from_slice(b"Content-Type: text/plain; charset=utf-8\r\n\r\n");
-        body.extend_from_slice(file_content.as_bytes());
-        body.extend_from_slice(b"\r\n");
-        
-        // 结束边界
-        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        if file_content.len() as u64 > self.chunked_upload.threshold_bytes {
+            return self.upload_novel_chunked(title, file_path, file_name, &file_content, cancel, on_progress);
+        }
 
-        let agent = Self::new_agent();
         tracing::info!("Sending upload request...");
-        let resp = agent.post(&url)
-            .set("Content-Type", &format!("multipart/form-data; boundary={}", boundary))
-            .send_bytes(&body)
-            .map_err(|e| {
-                tracing::error!("upload_novel error: {}", e);
-                anyhow::anyhow!("Upload error: {}", e)
-            })?;
+        let content_len = file_content.len() as u64;
+        let on_progress = std::cell::RefCell::new(on_progress);
+        let resp = self
+            .with_reauth(|| {
+                let mut form = MultipartForm::new();
+                form.text_field("title", title);
+                form.file_header("file", file_name, "text/plain; charset=utf-8");
+                let request = self.request("POST", &url, RequestClass::Upload);
+                form.send(request, Cursor::new(file_content.clone()), content_len, cancel, |sent, total| {
+                    (on_progress.borrow_mut())(sent, total)
+                })
+            })
+            .inspect_err(|e| tracing::error!("upload_novel error: {}", e))?;
         
         tracing::info!("Upload response status: {}", resp.status());
         
         let api_resp: ApiResponse<NovelResponse> = resp.into_json().map_err(|e| {
             tracing::error!("upload_novel json parse error: {}", e);
-            anyhow::anyhow!("JSON parse error: {}", e)
+            ApiError::from_body_error(e)
         })?;
         api_resp.into_result()
     }
 
-    pub fn delete_novel(&self, id: Uuid) -> Result<()> {
+    /// 分块上传小说（init / part / complete）
+    ///
+    /// 每完成一个分块就把进度写入磁盘；同一服务器上同一文件再次上传时，
+    /// 先向服务端查询已收到的分块，只上传缺失部分。
+    fn upload_novel_chunked(
+        &self,
+        title: &str,
+        file_path: &std::path::Path,
+        file_name: &str,
+        content: &[u8],
+        cancel: &AtomicBool,
+        on_progress: impl FnMut(u64, u64),
+    ) -> ApiResult<NovelResponse> {
+        let mut state = self.resume_or_init_upload(title, file_path, file_name, content)?;
+        let total = state.total_size;
+        tracing::info!(
+            "Chunked upload {}: {} parts of {} bytes, {} already uploaded",
+            state.upload_id,
+            state.part_count(),
+            state.chunk_size,
+            state.completed_parts.len()
+        );
+
+        let on_progress = std::cell::RefCell::new(on_progress);
+        (on_progress.borrow_mut())(state.uploaded_bytes(), total);
+
+        for index in 0..state.part_count() {
+            if state.completed_parts.contains(&index) {
+                continue;
+            }
+            if cancel.load(Ordering::Relaxed) {
+                self.discard_upload_state(&state);
+                return Err(ApiError::Cancelled);
+            }
+
+            let chunk = &content[state.part_range(index)];
+            let uploaded = state.uploaded_bytes();
+            // 服务端按分块序号覆盖写入，重复上传同一分块是幂等的
+            let result = self.with_retry("upload_part", || {
+                self.with_reauth(|| {
+                    self.upload_part(&state.upload_id, index, chunk, cancel, |sent, _| {
+                        (on_progress.borrow_mut())(uploaded + sent, total)
+                    })
+                })
+            });
+            if let Err(error) = result {
+                if error == ApiError::Cancelled {
+                    self.discard_upload_state(&state);
+                }
+                return Err(error);
+            }
+
+            state.completed_parts.insert(index);
+            if let Some(store) = &self.upload_store {
+                if let Err(e) = store.save(&state) {
+                    tracing::warn!("Failed to persist upload state: {}", e);
+                }
+            }
+        }
+
+        // complete 不是幂等的，不自动重试；失败时保留状态，下次先查询服务端状态
+        let novel: NovelResponse = self.post(
+            "/novel/upload/complete",
+            &UploadIdRequest { upload_id: &state.upload_id },
+        )?;
+        self.discard_upload_state(&state);
+        Ok(novel)
+    }
+
+    /// 恢复磁盘上的未完成上传，恢复不了时初始化新的上传
+    fn resume_or_init_upload(
+        &self,
+        title: &str,
+        file_path: &std::path::Path,
+        file_name: &str,
+        content: &[u8],
+    ) -> ApiResult<ChunkedUploadState> {
+        let fingerprint = upload_state::fingerprint(content);
+        let saved = self
+            .upload_store
+            .as_ref()
+            .and_then(|store| store.find(&self.base_url, file_path));
+
+        if let Some(mut state) = saved {
+            if state.fingerprint == fingerprint && state.total_size == content.len() as u64 {
+                let status = self.with_retry("upload_status", || {
+                    self.post::<_, UploadStatusResponse>(
+                        "/novel/upload/status",
+                        &UploadIdRequest { upload_id: &state.upload_id },
+                    )
+                });
+                match status {
+                    // 以服务端实际收到的分块为准
+                    Ok(status) => {
+                        state.completed_parts = status.received_parts.into_iter().collect();
+                        state.title = title.to_string();
+                        return Ok(state);
+                    }
+                    // 服务端已过期或不认识该上传，重新开始
+                    Err(ApiError::Server { errno: ERRNO_NOT_FOUND, .. }) | Err(ApiError::Http { status: 404, .. }) => {
+                        tracing::info!("Upload {} expired on server, restarting", state.upload_id);
+                    }
+                    // 其他错误（网络、服务端故障）保留断点，下次再续传
+                    Err(error) => return Err(error),
+                }
+            } else {
+                tracing::info!("File {:?} changed since last upload, restarting", file_path);
+            }
+            self.discard_upload_state(&state);
+        }
+
+        let init: UploadInitResponse = self.with_retry("upload_init", || {
+            self.post(
+                "/novel/upload/init",
+                &UploadInitRequest {
+                    title,
+                    file_name,
+                    total_size: content.len() as u64,
+                    chunk_size: self.chunked_upload.chunk_bytes,
+                },
+            )
+        })?;
+        let state = ChunkedUploadState {
+            server_url: self.base_url.clone(),
+            upload_id: init.upload_id,
+            title: title.to_string(),
+            file_path: file_path.to_path_buf(),
+            total_size: content.len() as u64,
+            chunk_size: init.chunk_size.max(1),
+            fingerprint,
+            completed_parts: Default::default(),
+        };
+        if let Some(store) = &self.upload_store {
+            if let Err(e) = store.save(&state) {
+                tracing::warn!("Failed to persist upload state: {}", e);
+            }
+        }
+        Ok(state)
+    }
+
+    /// 上传单个分块（原始字节，分块序号放在查询参数中）
+    fn upload_part(
+        &self,
+        upload_id: &str,
+        index: u32,
+        chunk: &[u8],
+        cancel: &AtomicBool,
+        mut on_progress: impl FnMut(u64, u64),
+    ) -> ApiResult<()> {
+        let url = format!("{}/novel/upload/part", self.base_url);
+        let request = self
+            .request("POST", &url, RequestClass::Upload)
+            .query("upload_id", upload_id)
+            .query("index", &index.to_string())
+            .set("Content-Type", "application/octet-stream")
+            .set("Content-Length", &chunk.len().to_string());
+        let resp = send_upload(request, Cursor::new(chunk.to_vec()), chunk.len() as u64, cancel, &mut on_progress)?;
+
+        let api_resp: ApiResponse<EmptyData> = resp.into_json().map_err(ApiError::from_body_error)?;
+        if api_resp.errno == 0 {
+            Ok(())
+        } else {
+            Err(ApiError::Server { errno: api_resp.errno, message: api_resp.error })
+        }
+    }
+
+    fn discard_upload_state(&self, state: &ChunkedUploadState) {
+        if let Some(store) = &self.upload_store {
+            store.remove(state);
+        }
+    }
+
+    /// 当前服务器上未完成的分块上传（应用重启后继续）
+    pub fn pending_chunked_uploads(&self) -> Vec<ChunkedUploadState> {
+        self.upload_store
+            .as_ref()
+            .map(|store| store.pending(&self.base_url))
+            .unwrap_or_default()
+    }
+
+    pub fn delete_novel(&self, id: Uuid) -> ApiResult<()> {
         self.post_empty("/novel/delete", &IdRequest { id })
     }
 
@@ -542,16 +1491,19 @@ impl ApiClient {
     // Voice APIs
     // ========================================================================
 
-    pub fn list_voices(&self) -> Result<Vec<VoiceResponse>> {
-        self.get("/voice/list")
+    pub fn list_voices(&self) -> ApiResult<Vec<VoiceResponse>> {
+        self.with_retry("list_voices", || self.get("/voice/list"))
     }
 
+    /// 上传音色（直接从文件流式发送，`on_progress(sent, total)` 报告已发送字节数）
     pub fn upload_voice(
         &self,
         name: &str,
         description: Option<&str>,
         file_path: &std::path::Path,
-    ) -> Result<VoiceResponse> {
+        cancel: &AtomicBool,
+        on_progress: impl FnMut(u64, u64),
+    ) -> ApiResult<VoiceResponse> {
         let url = format!("{}/voice/upload", self.base_url);
         tracing::info!("API upload_voice: url={}, name={}, path={:?}", url, name, file_path);
         
@@ -559,7 +1511,7 @@ impl ApiClient {
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("voice.wav");
-        let file_content = std::fs::read(file_path)?;
+        let content_len = std::fs::metadata(file_path).map_err(|e| ApiError::Io(e.to_string()))?.len();
 
         let mime_type = match file_path.extension().and_then(|e| e.to_str()) {
             Some("wav") => "audio/wav",
@@ -569,51 +1521,29 @@ impl ApiClient {
             _ => "audio/wav",
         };
 
-        // 构建 multipart form
-        let boundary = format!("----WebKitFormBoundary{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
-        let mut body = Vec::new();
-        
-        // name 字段
-        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-        body.extend_from_slice(b"Content-Disposition: form-data; name=\"name\"\r\n\r\n");
-        body.extend_from_slice(name.as_bytes());
-        body.extend_from_slice(b"\r\n");
-        
-        // description 字段（可选）
-        if let Some(desc) = description {
-            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-            body.extend_from_slice(b"Content-Disposition: form-data; name=\"description\"\r\n\r\n");
-            body.extend_from_slice(desc.as_bytes());
-            body.extend_from_slice(b"\r\n");
-        }
-        
-        // file 字段
-        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-        body.extend_from_slice(format!(
-            "Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n",
-            file_name
-        ).as_bytes());
-        body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", mime_type).as_bytes());
-        body.extend_from_slice(&file_content);
-        body.extend_from_slice(b"\r\n");
-        
-        // 结束边界
-        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
-
-        let agent = Self::new_agent();
-        let resp = agent.post(&url)
-            .set("Content-Type", &format!("multipart/form-data; boundary={}", boundary))
-            .send_bytes(&body)
-            .map_err(|e| {
-                tracing::error!("upload_voice error: {}", e);
-                anyhow::anyhow!("Upload error: {}", e)
-            })?;
+        let on_progress = std::cell::RefCell::new(on_progress);
+        let resp = self
+            .with_reauth(|| {
+                // 401 重试时需要重新从头读取文件
+                let file = std::fs::File::open(file_path).map_err(|e| ApiError::Io(e.to_string()))?;
+                let mut form = MultipartForm::new();
+                form.text_field("name", name);
+                if let Some(desc) = description {
+                    form.text_field("description", desc);
+                }
+                form.file_header("file", file_name, mime_type);
+                let request = self.request("POST", &url, RequestClass::Upload);
+                form.send(request, std::io::BufReader::new(file), content_len, cancel, |sent, total| {
+                    (on_progress.borrow_mut())(sent, total)
+                })
+            })
+            .inspect_err(|e| tracing::error!("upload_voice error: {}", e))?;
         
-        let api_resp: ApiResponse<VoiceResponse> = resp.into_json()?;
+        let api_resp: ApiResponse<VoiceResponse> = resp.into_json().map_err(ApiError::from_body_error)?;
         api_resp.into_result()
     }
 
-    pub fn delete_voice(&self, id: Uuid) -> Result<()> {
+    pub fn delete_voice(&self, id: Uuid) -> ApiResult<()> {
         self.post_empty("/voice/delete", &IdRequest { id })
     }
 
@@ -622,12 +1552,12 @@ impl ApiClient {
     // ========================================================================
 
     /// V2: 开始播放，按需创建 Session
-    pub fn play(&self, novel_id: Uuid, voice_id: Uuid, start_index: u32) -> Result<PlayResponse> {
+    pub fn play(&self, novel_id: Uuid, voice_id: Uuid, start_index: u32) -> ApiResult<PlayResponse> {
         self.post("/session/play", &PlayRequest { novel_id, voice_id, start_index })
     }
 
     /// V2: Seek 到指定位置，自动取消旧任务
-    pub fn seek(&self, session_id: &str, segment_index: u32) -> Result<SeekResponse> {
+    pub fn seek(&self, session_id: &str, segment_index: u32) -> ApiResult<SeekResponse> {
         self.post("/session/seek", &SeekRequest { 
             session_id: session_id.to_string(), 
             segment_index 
@@ -635,7 +1565,7 @@ impl ApiClient {
     }
 
     /// V2: 切换音色，自动取消旧任务
-    pub fn change_voice(&self, session_id: &str, voice_id: Uuid) -> Result<ChangeVoiceResponse> {
+    pub fn change_voice(&self, session_id: &str, voice_id: Uuid) -> ApiResult<ChangeVoiceResponse> {
         self.post("/session/change_voice", &ChangeVoiceRequest { 
             session_id: session_id.to_string(), 
             voice_id 
@@ -643,7 +1573,7 @@ impl ApiClient {
     }
 
     /// V2: 关闭 Session
-    pub fn close_session(&self, session_id: &str) -> Result<CloseSessionResponse> {
+    pub fn close_session(&self, session_id: &str) -> ApiResult<CloseSessionResponse> {
         self.post("/session/close", &CloseSessionRequest { 
             session_id: session_id.to_string() 
         })
@@ -653,17 +1583,19 @@ impl ApiClient {
     // Inference APIs (V2)
     // ========================================================================
 
-    /// V2: 提交推理任务
-    pub fn submit_infer(&self, session_id: &str, segment_indices: Vec<u32>) -> Result<SubmitInferResponse> {
-        self.post("/infer/submit", &SubmitInferRequest { 
+    /// V2: 提交推理任务（服务端按段落去重，可安全重试）
+    pub fn submit_infer(&self, session_id: &str, segment_indices: Vec<u32>) -> ApiResult<SubmitInferResponse> {
+        let body = SubmitInferRequest { 
             session_id: session_id.to_string(), 
             segment_indices 
-        })
+        };
+        self.with_retry("submit_infer", || self.post("/infer/submit", &body))
     }
 
     /// V2: 查询任务状态
-    pub fn query_task_status(&self, task_ids: Vec<String>) -> Result<QueryTaskStatusResponse> {
-        self.post("/infer/status", &QueryTaskStatusRequest { task_ids })
+    pub fn query_task_status(&self, task_ids: Vec<String>) -> ApiResult<QueryTaskStatusResponse> {
+        let body = QueryTaskStatusRequest { task_ids };
+        self.with_retry("query_task_status", || self.post("/infer/status", &body))
     }
 
     // ========================================================================
@@ -671,35 +1603,418 @@ impl ApiClient {
     // ========================================================================
 
     /// V2: 获取音频 (通过 novel_id + segment_index + voice_id)
-    pub fn get_audio(&self, novel_id: Uuid, segment_index: u32, voice_id: Uuid) -> Result<Option<Vec<u8>>> {
+    ///
+    /// `formats` 为客户端能解码的格式（按优先级），为空时由服务器决定。
+    /// 音频尚未生成时返回 `ApiError::NotReady`
+    pub fn get_audio(&self, novel_id: Uuid, segment_index: u32, voice_id: Uuid, formats: &[AudioFormat]) -> ApiResult<Vec<u8>> {
+        self.with_retry("get_audio", || {
+            self.with_reauth(|| self.fetch_audio(novel_id, segment_index, voice_id, formats))
+        })
+    }
+
+    /// V2: 以流的方式获取音频
+    ///
+    /// 收到响应头后立即返回，响应体（chunked transfer）在后台线程中写入 `AudioStream`，
+    /// 调用方可以边下载边解码播放；`AudioStream` 被取消后停止下载。
+    /// 返回的 `AudioStream` 记录服务器实际选择的格式。
+    /// 音频尚未生成时返回 `ApiError::NotReady`
+    pub fn open_audio_stream(
+        &self,
+        novel_id: Uuid,
+        segment_index: u32,
+        voice_id: Uuid,
+        formats: &[AudioFormat],
+    ) -> ApiResult<AudioStream> {
+        let started = std::time::Instant::now();
+        let resp = self.with_retry("get_audio", || {
+            self.with_reauth(|| self.request_audio(novel_id, segment_index, voice_id, formats))
+        })?;
+
+        let format = AudioFormat::from_content_type(resp.header("content-type").unwrap_or(""));
+        match format {
+            Some(format) => tracing::debug!(
+                "get_audio: segment {} negotiated {} (codec {})",
+                segment_index,
+                format.label(),
+                format.codec()
+            ),
+            None => tracing::debug!(
+                "get_audio: segment {} has unrecognized content type {:?}",
+                segment_index,
+                resp.header("content-type")
+            ),
+        }
+        let stream = AudioStream::new().with_format(format);
+        let writer = stream.clone();
+        std::thread::spawn(move || {
+            let mut reader = resp.into_reader();
+            let mut buf = [0u8; AUDIO_STREAM_READ_BYTES];
+            loop {
+                if writer.is_cancelled() {
+                    tracing::debug!("get_audio: segment {} stream cancelled", segment_index);
+                    return;
+                }
+                match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => writer.push(&buf[..n]),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        tracing::warn!("get_audio: segment {} stream failed: {}", segment_index, e);
+                        writer.fail(e.to_string());
+                        return;
+                    }
+                }
+            }
+            writer.finish();
+            tracing::info!(
+                "get_audio: segment {} streamed, {} bytes in {:?}",
+                segment_index,
+                writer.received(),
+                started.elapsed()
+            );
+        });
+        Ok(stream)
+    }
+
+    fn fetch_audio(&self, novel_id: Uuid, segment_index: u32, voice_id: Uuid, formats: &[AudioFormat]) -> ApiResult<Vec<u8>> {
+        let started = std::time::Instant::now();
+        let resp = self.request_audio(novel_id, segment_index, voice_id, formats)?;
+
+        // 二进制音频数据
+        let mut reader = resp.into_reader();
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(ApiError::from_body_error)?;
+        // 往返耗时，用于观察连接复用的效果
+        tracing::info!(
+            "get_audio: segment {} loaded, {} bytes in {:?}",
+            segment_index,
+            bytes.len(),
+            started.elapsed()
+        );
+        Ok(bytes)
+    }
+
+    /// V2: 一次请求批量获取多段音频
+    ///
+    /// 服务器以 `multipart/mixed` 返回已生成的段落，每部分带 `X-Segment-Index` 头，
+    /// 尚未生成的段落不包含在结果中。服务器不支持批量接口（404）时逐段获取。
+    pub fn get_audio_batch(
+        &self,
+        novel_id: Uuid,
+        voice_id: Uuid,
+        segment_indices: &[u32],
+        formats: &[AudioFormat],
+    ) -> ApiResult<Vec<SegmentAudio>> {
+        let result = self.with_retry("get_audio_batch", || {
+            self.with_reauth(|| self.fetch_audio_batch(novel_id, voice_id, segment_indices, formats))
+        });
+        match result {
+            Err(ApiError::Http { status: 404 | 405, .. }) => {
+                tracing::info!("Server has no batch audio endpoint, fetching {} segments one by one", segment_indices.len());
+                crate::backend::get_audio_each(self, novel_id, voice_id, segment_indices, formats)
+            }
+            result => result,
+        }
+    }
+
+    fn fetch_audio_batch(
+        &self,
+        novel_id: Uuid,
+        voice_id: Uuid,
+        segment_indices: &[u32],
+        formats: &[AudioFormat],
+    ) -> ApiResult<Vec<SegmentAudio>> {
+        let url = format!("{}/audio/batch", self.base_url);
+        let started = std::time::Instant::now();
+
+        let mut request = self.request("POST", &url, RequestClass::Audio)
+            .set("Content-Type", "application/json");
+        if !formats.is_empty() {
+            request = request.set("Accept", &format!("multipart/mixed, {}", audio_format::accept_header(formats)));
+        }
+        let resp = request
+            .send_json(&GetAudioBatchRequest {
+                novel_id,
+                voice_id,
+                segment_indices,
+                formats,
+            })
+            .map_err(ApiError::from)?;
+
+        let content_type = resp.header("content-type").unwrap_or("").to_string();
+        if content_type.contains("application/json") {
+            // 没有任何段落就绪时服务器可能返回 JSON
+            let api_resp: ApiResponse<EmptyData> = resp.into_json().map_err(ApiError::from_body_error)?;
+            return match api_resp.errno {
+                0 => Ok(Vec::new()),
+                errno => Err(ApiError::Server { errno, message: api_resp.error }),
+            };
+        }
+
+        let mut body = Vec::new();
+        resp.into_reader().read_to_end(&mut body).map_err(ApiError::from_body_error)?;
+        let mut segments = Vec::new();
+        for (headers, data) in parse_multipart_mixed(&content_type, &body)? {
+            let Some(segment_index) = headers.get(SEGMENT_INDEX_HEADER).and_then(|v| v.parse().ok()) else {
+                tracing::warn!("get_audio_batch: part without {} header skipped", SEGMENT_INDEX_HEADER);
+                continue;
+            };
+            let format = headers
+                .get("content-type")
+                .and_then(|ct| AudioFormat::from_content_type(ct))
+                .or_else(|| AudioFormat::sniff(&data));
+            segments.push(SegmentAudio { segment_index, format, data });
+        }
+        tracing::info!(
+            "get_audio_batch: {}/{} segments, {} bytes in {:?}",
+            segments.len(),
+            segment_indices.len(),
+            body.len(),
+            started.elapsed()
+        );
+        Ok(segments)
+    }
+
+    /// 请求音频，返回尚未读取响应体的二进制音频响应
+    fn request_audio(
+        &self,
+        novel_id: Uuid,
+        segment_index: u32,
+        voice_id: Uuid,
+        formats: &[AudioFormat],
+    ) -> ApiResult<ureq::Response> {
         let url = format!("{}/audio", self.base_url);
-        let agent = Self::new_agent();
-        
-        let resp = agent.post(&url)
-            .set("Content-Type", "application/json")
+
+        let mut request = self.request("POST", &url, RequestClass::Audio)
+            .set("Content-Type", "application/json");
+        if !formats.is_empty() {
+            request = request.set("Accept", &audio_format::accept_header(formats));
+        }
+        let resp = request
             .send_json(&GetAudioRequest {
                 novel_id,
                 segment_index,
                 voice_id,
+                formats,
             })
-            .map_err(|e| anyhow::anyhow!("Audio request error: {}", e))?;
+            .map_err(ApiError::from)?;
 
         // 检查 Content-Type
         let content_type = resp.header("content-type").unwrap_or("");
         
         if content_type.contains("application/json") {
             // JSON 响应 - 可能是错误或音频未准备好
-            let api_resp: ApiResponse<EmptyData> = resp.into_json()?;
+            let api_resp: ApiResponse<EmptyData> = resp.into_json().map_err(ApiError::from_body_error)?;
             if api_resp.errno != 0 {
-                return Ok(None); // 音频未准备好
+                tracing::debug!("Audio not ready ({}): {}", api_resp.errno, api_resp.error);
             }
-            Ok(None)
+            Err(ApiError::NotReady)
         } else {
-            // 二进制音频数据
-            let mut reader = resp.into_reader();
-            let mut bytes = Vec::new();
-            reader.read_to_end(&mut bytes)?;
-            Ok(Some(bytes))
+            Ok(resp)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_util::TempDir;
+    use crate::mock_server::MockRovelServer;
+    use std::path::PathBuf;
+
+    /// 模拟一次应用启动：新的客户端，共用同一个状态目录
+    fn test_client(server: &MockRovelServer, state_dir: &std::path::Path) -> ApiClient {
+        let mut client = ApiClient::new(server.base_url())
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..Default::default() })
+            .with_chunked_upload_policy(ChunkedUploadPolicy { threshold_bytes: 1024, chunk_bytes: 1024 });
+        client.upload_store = Some(UploadStateStore::new(state_dir.to_path_buf()));
+        client
+    }
+
+    fn write_novel(dir: &std::path::Path, len: usize) -> (PathBuf, Vec<u8>) {
+        let content: Vec<u8> = (0..len).map(|i| b'a' + (i % 26) as u8).collect();
+        let path = dir.join("novel.txt");
+        std::fs::write(&path, &content).unwrap();
+        (path, content)
+    }
+
+    #[test]
+    fn chunked_upload_resumes_after_network_drop() {
+        let server = MockRovelServer::start();
+        server.state.lock().unwrap().drop_part = Some((4, 1));
+        let dir = TempDir::new("upload");
+        let (path, content) = write_novel(&dir, 10 * 1024 + 100);
+        let state_dir = dir.join("uploads");
+        let no_cancel = AtomicBool::new(false);
+
+        let first = test_client(&server, &state_dir).upload_novel("长篇", &path, &no_cancel, |_, _| {});
+        assert!(matches!(first, Err(ApiError::Transport(_))), "unexpected result: {:?}", first);
+
+        // 中断后磁盘上保留了已完成的分块
+        let client = test_client(&server, &state_dir);
+        let pending = client.pending_chunked_uploads();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].completed_parts, (0..4).collect());
+        assert_eq!(pending[0].uploaded_bytes(), 4 * 1024);
+
+        let mut last_progress = (0, 0);
+        let novel = client
+            .upload_novel("长篇", &path, &no_cancel, |sent, total| last_progress = (sent, total))
+            .unwrap();
+        assert_eq!(novel.title, "长篇");
+        assert_eq!(last_progress, (content.len() as u64, content.len() as u64));
+
+        let state = server.state.lock().unwrap();
+        // 只有中断的分块被重新发送
+        assert_eq!(state.part_requests, vec![0, 1, 2, 3, 4, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(state.completed.as_deref(), Some(content.as_slice()));
+        assert!(client.pending_chunked_uploads().is_empty());
+    }
+
+    #[test]
+    fn chunked_upload_restarts_when_server_forgot_upload() {
+        let server = MockRovelServer::start();
+        let dir = TempDir::new("upload");
+        let (path, content) = write_novel(&dir, 3000);
+        let state_dir = dir.join("uploads");
+        let client = test_client(&server, &state_dir);
+
+        // 上次的上传在服务端已经过期
+        UploadStateStore::new(state_dir.clone())
+            .save(&ChunkedUploadState {
+                server_url: server.base_url(),
+                upload_id: "expired".to_string(),
+                title: "过期".to_string(),
+                file_path: path.clone(),
+                total_size: content.len() as u64,
+                chunk_size: 1024,
+                fingerprint: upload_state::fingerprint(&content),
+                completed_parts: (0..2).collect(),
+            })
+            .unwrap();
+
+        client.upload_novel("过期", &path, &AtomicBool::new(false), |_, _| {}).unwrap();
+
+        let state = server.state.lock().unwrap();
+        assert_eq!(state.part_requests, vec![0, 1, 2]);
+        assert_eq!(state.completed.as_deref(), Some(content.as_slice()));
+    }
+
+    #[test]
+    fn chunked_upload_keeps_state_when_status_check_fails() {
+        let server = MockRovelServer::start();
+        server.state.lock().unwrap().drop_part = Some((1, 1));
+        let dir = TempDir::new("upload");
+        let (path, _) = write_novel(&dir, 3000);
+        let state_dir = dir.join("uploads");
+        let no_cancel = AtomicBool::new(false);
+        assert!(test_client(&server, &state_dir).upload_novel("长篇", &path, &no_cancel, |_, _| {}).is_err());
+
+        // 服务端临时故障不等于上传已过期：返回错误并保留断点
+        server.state.lock().unwrap().upload_status_errno = Some(50001);
+        let client = test_client(&server, &state_dir);
+        let result = client.upload_novel("长篇", &path, &no_cancel, |_, _| {});
+        assert!(matches!(result, Err(ApiError::Server { errno: 50001, .. })), "unexpected result: {:?}", result);
+        let pending = client.pending_chunked_uploads();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].completed_parts, (0..1).collect());
+    }
+
+    #[test]
+    fn cancelled_chunked_upload_discards_state() {
+        let server = MockRovelServer::start();
+        let dir = TempDir::new("upload");
+        let (path, _) = write_novel(&dir, 5000);
+        let state_dir = dir.join("uploads");
+        let client = test_client(&server, &state_dir);
+
+        let cancel = AtomicBool::new(false);
+        let result = client.upload_novel("取消", &path, &cancel, |sent, _| {
+            if sent >= 2048 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        });
+        assert_eq!(result.unwrap_err(), ApiError::Cancelled);
+        assert!(client.pending_chunked_uploads().is_empty());
+        assert!(server.state.lock().unwrap().completed.is_none());
+    }
+
+    #[test]
+    fn upload_cancel_stops_waiting_for_response() {
+        let server = MockRovelServer::start();
+        server.state.lock().unwrap().upload_response_delay = Duration::from_secs(5);
+        let dir = TempDir::new("upload");
+        let (path, content) = write_novel(&dir, 500);
+        let client = test_client(&server, &dir);
+
+        // 请求体发完后才取消，此时服务端还没有响应
+        let cancel = AtomicBool::new(false);
+        let started = std::time::Instant::now();
+        let result = client.upload_novel("取消", &path, &cancel, |sent, total| {
+            if sent == total {
+                assert!(total > content.len() as u64);
+                cancel.store(true, Ordering::Relaxed);
+            }
+        });
+        assert_eq!(result.unwrap_err(), ApiError::Cancelled);
+        assert!(started.elapsed() < Duration::from_secs(2), "cancel waited {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn audio_stream_is_readable_before_download_completes() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["一"]);
+        let voice = server.add_voice("默认音色");
+        let client = ApiClient::new(server.base_url()).with_retry_policy(RetryPolicy { max_attempts: 1, ..Default::default() });
+        let formats = [AudioFormat::Flac, AudioFormat::Wav];
+        assert!(matches!(client.open_audio_stream(novel.id, 0, voice.id, &formats), Err(ApiError::NotReady)));
+
+        {
+            let mut state = server.state.lock().unwrap();
+            state.ready_audio.insert((novel.id, voice.id, 0));
+            state.audio_chunk_delay = Duration::from_millis(300);
+        }
+        let stream = client.open_audio_stream(novel.id, 0, voice.id, &formats).unwrap();
+
+        // 第一个分块到达即可读取音频头部，此时后续分块仍在传输
+        let mut header = [0u8; 4];
+        stream.reader().read_exact(&mut header).unwrap();
+        assert_eq!(&header, b"RIFF");
+        let expected = crate::mock_server::wav_tone(200, 220.0);
+        assert!(stream.received() < expected.len());
+
+        let mut data = Vec::new();
+        stream.reader().read_to_end(&mut data).unwrap();
+        assert_eq!(data, expected);
+
+        // mock 服务器只能生成 WAV，从声明的格式中选择了 WAV
+        assert_eq!(stream.format(), Some(AudioFormat::Wav));
+        assert_eq!(server.state.lock().unwrap().audio_formats[1], serde_json::json!(["flac", "wav"]));
+    }
+
+    #[test]
+    fn batch_audio_returns_only_ready_segments() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["一", "二", "三", "四"]);
+        let voice = server.add_voice("默认音色");
+        {
+            let mut state = server.state.lock().unwrap();
+            state.ready_audio.insert((novel.id, voice.id, 1));
+            state.ready_audio.insert((novel.id, voice.id, 3));
+        }
+        let client = ApiClient::new(server.base_url()).with_retry_policy(RetryPolicy { max_attempts: 1, ..Default::default() });
+
+        let segments = client.get_audio_batch(novel.id, voice.id, &[1, 2, 3], &[AudioFormat::Wav]).unwrap();
+
+        let expected: Vec<_> = [1, 3]
+            .into_iter()
+            .map(|idx| SegmentAudio {
+                segment_index: idx,
+                format: Some(AudioFormat::Wav),
+                data: crate::mock_server::wav_tone(200, 220.0 + 20.0 * idx as f32),
+            })
+            .collect();
+        assert_eq!(segments, expected);
+        assert_eq!(server.state.lock().unwrap().batch_audio_requests, [vec![1, 2, 3]]);
+    }
+}
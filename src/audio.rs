@@ -1,23 +1,134 @@
 //! Audio playback system using rodio
 //!
 //! 由于 rodio 的 OutputStream 不是 Send+Sync，我们使用一个专用线程来处理音频播放
+//!
+//! 音频边下载边播放：每段音频由一个解码线程从 `AudioStream` 解码到样本队列，
+//! `StreamingSource` 从队列取样本输出；队列中积累到最小缓冲后才开始出声，
+//! 下载跟不上播放（欠载）时输出静音并重新缓冲。
+//!
+//! 之后的段落可以提前排队（`AudioPlayer::enqueue`）：`PlaylistSource` 在同一个 sink 里
+//! 依次播放队列中的段落，按 `SegmentTransition` 无缝衔接、插入停顿或交叉淡入淡出，
+//! 开始播放新段落时通过 channel 通知段落索引。队列播完才报告 `Finished`。
+//!
+//! 音量和播放速度通过原子变量与音频线程共享：音量包含主音量和睡眠定时淡出，
+//! 速度由 `TimeStretch` 在保持音调的前提下伸缩时间。解码线程同时测量每段的响度，
+//! 开启响度均衡时 `PlaylistSource` 对每段应用各自的均衡增益。
+//!
+//! 已播放的样本保留在队列中，段落内可以前后跳转（`AudioPlayer::seek`），也可以在段内循环
+//! （`AudioPlayer::set_loop`，单段重复和 A-B 循环）；音频线程把当前段的播放位置写入
+//! `PlaybackPosition` 供界面显示。
 
 use bevy::prelude::*;
-use std::io::Cursor;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread;
+use std::time::Duration;
+
+use crate::audio_format::AudioFormat;
+use crate::audio_stream::{AudioStream, AudioStreamReader};
+use crate::config::AppConfig;
+use crate::loudness::LoudnessMeter;
+use crate::state::{
+    AppState, AudioFinishedEvent, PauseAudioEvent, PlayAudioEvent, QueueAudioEvent, RepeatMode, ResumeAudioEvent,
+    SeekAudioEvent, SegmentStartedEvent, StopAudioEvent,
+};
+use crate::time_stretch::{clamp_speed, TimeStretch};
+
+/// 开始播放前至少缓冲的音频时长，欠载后同样缓冲到该时长再继续
+const MIN_BUFFER: Duration = Duration::from_millis(500);
+
+/// 解码线程每次写入样本队列的帧数
+const DECODE_BATCH_FRAMES: usize = 1024;
+
+/// `PlaylistSource` 每隔这么多帧更新一次均衡增益并检查是否开始交叉淡化
+const CONTROL_INTERVAL_FRAMES: usize = 256;
+
+/// 段落内响度均衡增益每次更新最多变化的分贝数，避免测量更新时音量跳变
+const GAIN_RAMP_DB: f32 = 0.1;
+
+/// 段落之间的衔接方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SegmentTransition {
+    /// 紧接着播放，没有间隙
+    #[default]
+    Gapless,
+    /// 段落之间插入固定时长的停顿
+    Pause { ms: u32 },
+    /// 上一段结尾与下一段开头交叉淡入淡出
+    Crossfade { ms: u32 },
+}
 
-use crate::state::{AudioFinishedEvent, PauseAudioEvent, PlayAudioEvent, ResumeAudioEvent, StopAudioEvent};
+impl SegmentTransition {
+    pub fn label(self) -> &'static str {
+        match self {
+            SegmentTransition::Gapless => "无缝衔接",
+            SegmentTransition::Pause { .. } => "段间停顿",
+            SegmentTransition::Crossfade { .. } => "交叉淡化",
+        }
+    }
+
+    /// 停顿或交叉淡化的时长
+    pub fn duration_ms(self) -> Option<u32> {
+        match self {
+            SegmentTransition::Gapless => None,
+            SegmentTransition::Pause { ms } | SegmentTransition::Crossfade { ms } => Some(ms),
+        }
+    }
+}
 
 /// 音频命令
 enum AudioCommand {
-    Play(Vec<u8>),
+    /// 停止当前播放，从这一段开始新的播放队列
+    Play { segment_index: u32, stream: AudioStream },
+    /// 排在队列末尾，前一段播完后无缝播放
+    Enqueue { segment_index: u32, stream: AudioStream },
+    SetTransition(SegmentTransition),
+    /// 跳转到当前段内的位置
+    Seek(Duration),
+    SetLoop(Option<LoopRegion>),
+    /// 丢弃排队中尚未开始的段落
+    ClearQueue,
     Stop,
     Pause,
     Resume,
 }
 
+/// 当前段的播放位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaybackPosition {
+    pub segment_index: u32,
+    /// 段内已播放的时长（音频本身的时间，不随播放速度变化）
+    pub elapsed: Duration,
+    /// 段落总时长，解码完成前未知
+    pub duration: Option<Duration>,
+    /// 段内循环已回到起点的次数
+    pub loop_passes: u32,
+    /// 之后还会回到循环起点（这一遍播完不会进入下一段）
+    pub looping: bool,
+}
+
+/// 段内循环区间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopRegion {
+    /// 循环的段落，`None` 表示每一段都循环
+    pub segment_index: Option<u32>,
+    pub start: Duration,
+    /// 播放到这里回到 `start`，`None` 为段落结尾
+    pub end: Option<Duration>,
+    /// 回到起点的次数，`None` 为一直循环
+    pub repeats: Option<u32>,
+}
+
+impl LoopRegion {
+    fn applies_to(&self, segment_index: u32) -> bool {
+        self.segment_index.is_none_or(|index| index == segment_index)
+    }
+}
+
 /// 音频状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AudioStatus {
@@ -32,30 +143,103 @@ pub enum AudioStatus {
 pub struct AudioPlayer {
     command_tx: Sender<AudioCommand>,
     status_rx: Mutex<Receiver<AudioStatus>>,
+    /// 开始播放的段落索引（包括 `play` 的第一段和排队的段落）
+    segment_rx: Mutex<Receiver<u32>>,
     current_status: Mutex<AudioStatus>,
     /// 标记是否已通知播放完成，防止重复触发 AudioFinishedEvent
     has_notified_finished: Mutex<bool>,
+    /// 音量（`f32` 的位表示），音频线程每次循环时应用到 sink
+    volume: Arc<AtomicU32>,
+    /// 是否按测得的响度均衡各段音量
+    normalize: Arc<AtomicBool>,
+    /// 播放速度（`f32` 的位表示），由 `TimeStretch` 读取
+    speed: Arc<AtomicU32>,
+    /// 当前段的播放位置，没有播放时为 `None`
+    position: Arc<Mutex<Option<PlaybackPosition>>>,
 }
 
 impl AudioPlayer {
     pub fn new() -> Self {
         let (command_tx, command_rx) = mpsc::channel::<AudioCommand>();
         let (status_tx, status_rx) = mpsc::channel::<AudioStatus>();
+        let (segment_tx, segment_rx) = mpsc::channel::<u32>();
+        let volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let normalize = Arc::new(AtomicBool::new(true));
+        let speed = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let position = Arc::new(Mutex::new(None));
 
         // 启动音频线程
+        let shared = SharedControls {
+            volume: volume.clone(),
+            normalize: normalize.clone(),
+            speed: speed.clone(),
+            position: position.clone(),
+        };
         thread::spawn(move || {
-            audio_thread(command_rx, status_tx);
+            audio_thread(command_rx, status_tx, segment_tx, shared);
         });
 
         Self {
             command_tx,
             status_rx: Mutex::new(status_rx),
+            segment_rx: Mutex::new(segment_rx),
             current_status: Mutex::new(AudioStatus::Idle),
             has_notified_finished: Mutex::new(false),
+            volume,
+            normalize,
+            speed,
+            position,
         }
     }
 
-    pub fn play(&self, data: Vec<u8>) {
+    /// 设置音量（0.0 - 1.0），对当前和之后的段落都生效
+    pub fn set_volume(&self, volume: f32) {
+        self.volume.store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// 开启或关闭响度均衡
+    pub fn set_normalize(&self, normalize: bool) {
+        self.normalize.store(normalize, Ordering::Relaxed);
+    }
+
+    /// 设置播放速度（0.5 - 3.0 倍），保持音调，对当前和之后的段落都生效
+    pub fn set_speed(&self, speed: f32) {
+        self.speed.store(clamp_speed(speed).to_bits(), Ordering::Relaxed);
+    }
+
+    /// 设置段落之间的衔接方式
+    pub fn set_transition(&self, transition: SegmentTransition) {
+        let _ = self.command_tx.send(AudioCommand::SetTransition(transition));
+    }
+
+    /// 当前段的播放位置
+    pub fn position(&self) -> Option<PlaybackPosition> {
+        self.position.lock().ok().and_then(|position| *position)
+    }
+
+    /// 当前段按当前速度的剩余播放时长，解码尚未完成或还会循环时返回 `None`
+    pub fn remaining(&self) -> Option<Duration> {
+        let position = self.position().filter(|position| !position.looping)?;
+        let speed = f32::from_bits(self.speed.load(Ordering::Relaxed));
+        Some(position.duration?.saturating_sub(position.elapsed).div_f32(speed))
+    }
+
+    /// 跳转到当前段内的位置，超过已解码的部分时停在已解码的末尾
+    pub fn seek(&self, position: Duration) {
+        let _ = self.command_tx.send(AudioCommand::Seek(position));
+    }
+
+    /// 设置段内循环，对当前段（循环次数重新计算）和之后开始的段落生效
+    pub fn set_loop(&self, region: Option<LoopRegion>) {
+        let _ = self.command_tx.send(AudioCommand::SetLoop(region));
+    }
+
+    /// 丢弃已排队的段落，当前段播完后报告 `Finished`
+    pub fn clear_queue(&self) {
+        let _ = self.command_tx.send(AudioCommand::ClearQueue);
+    }
+
+    pub fn play(&self, segment_index: u32, stream: AudioStream) {
         // 重置状态，防止在新音频 Playing 状态到达前误判为 Finished
         if let Ok(mut current) = self.current_status.lock() {
             *current = AudioStatus::Idle;
@@ -64,7 +248,12 @@ impl AudioPlayer {
         if let Ok(mut notified) = self.has_notified_finished.lock() {
             *notified = false;
         }
-        let _ = self.command_tx.send(AudioCommand::Play(data));
+        let _ = self.command_tx.send(AudioCommand::Play { segment_index, stream });
+    }
+
+    /// 把之后的一段排进播放队列；当前没有播放时丢弃
+    pub fn enqueue(&self, segment_index: u32, stream: AudioStream) {
+        let _ = self.command_tx.send(AudioCommand::Enqueue { segment_index, stream });
     }
 
     pub fn stop(&self) {
@@ -91,11 +280,36 @@ impl AudioPlayer {
         
         self.current_status.lock().map(|s| *s).unwrap_or(AudioStatus::Idle)
     }
+
+    /// 自上次调用以来开始播放的段落
+    pub fn poll_started_segments(&self) -> Vec<u32> {
+        self.segment_rx.lock().map(|rx| rx.try_iter().collect()).unwrap_or_default()
+    }
+}
+
+/// 与音频线程共享的播放控制
+struct SharedControls {
+    volume: Arc<AtomicU32>,
+    normalize: Arc<AtomicBool>,
+    speed: Arc<AtomicU32>,
+    position: Arc<Mutex<Option<PlaybackPosition>>>,
+}
+
+/// 等待解码器就绪的段落
+struct PendingSegment {
+    index: u32,
+    stream: AudioStream,
+    source_rx: Receiver<Result<StreamingSource, String>>,
 }
 
 /// 音频线程主循环
-fn audio_thread(command_rx: Receiver<AudioCommand>, status_tx: Sender<AudioStatus>) {
-    use rodio::{Decoder, OutputStream, Sink};
+fn audio_thread(
+    command_rx: Receiver<AudioCommand>,
+    status_tx: Sender<AudioStatus>,
+    segment_tx: Sender<u32>,
+    shared: SharedControls,
+) {
+    use rodio::{OutputStream, Sink};
 
     let (_stream, stream_handle) = match OutputStream::try_default() {
         Ok(s) => s,
@@ -106,40 +320,89 @@ fn audio_thread(command_rx: Receiver<AudioCommand>, status_tx: Sender<AudioStatu
     };
 
     let mut current_sink: Option<Sink> = None;
+    let mut playlist = Playlist::default();
+    let mut transition = SegmentTransition::default();
+    // 解码器尚未就绪（等待音频头部）的段落，按播放顺序排列
+    let mut pending: VecDeque<PendingSegment> = VecDeque::new();
+    // 第一段解码器就绪后才创建 `PlaylistSource`（使用第一段的声道数和采样率）
+    let mut source_started = false;
+    let mut last_notified: Option<u32> = None;
+    let mut loop_region: Option<LoopRegion> = None;
+
+    let cancel_all = |pending: &mut VecDeque<PendingSegment>, playlist: &Playlist| {
+        for segment in pending.drain(..) {
+            segment.stream.cancel();
+        }
+        // 丢弃排队的 StreamingSource 会取消对应的下载
+        playlist.lock().upcoming.clear();
+    };
 
     loop {
         // 检查命令
         match command_rx.try_recv() {
-            Ok(AudioCommand::Play(data)) => {
+            Ok(AudioCommand::Play { segment_index, stream }) => {
                 // 停止当前播放
                 if let Some(sink) = current_sink.take() {
                     sink.stop();
                 }
+                cancel_all(&mut pending, &playlist);
 
-                // 创建新的 sink 并播放
-                let cursor = Cursor::new(data);
-                match Decoder::new(cursor) {
-                    Ok(source) => {
-                        match Sink::try_new(&stream_handle) {
-                            Ok(sink) => {
-                                sink.append(source);
-                                current_sink = Some(sink);
-                                let _ = status_tx.send(AudioStatus::Playing);
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to create sink: {}", e);
-                            }
-                        }
+                // 创建新的 sink，解码器就绪后再添加音频
+                match Sink::try_new(&stream_handle) {
+                    Ok(sink) => {
+                        playlist = Playlist::new(transition);
+                        source_started = false;
+                        last_notified = None;
+                        pending.push_back(PendingSegment {
+                            index: segment_index,
+                            stream: stream.clone(),
+                            source_rx: spawn_decoder(stream),
+                        });
+                        current_sink = Some(sink);
+                        let _ = status_tx.send(AudioStatus::Playing);
                     }
                     Err(e) => {
-                        eprintln!("Failed to decode audio: {}", e);
+                        eprintln!("Failed to create sink: {}", e);
+                        stream.cancel();
                     }
                 }
             }
+            Ok(AudioCommand::Enqueue { segment_index, stream }) => {
+                if current_sink.is_some() {
+                    pending.push_back(PendingSegment {
+                        index: segment_index,
+                        stream: stream.clone(),
+                        source_rx: spawn_decoder(stream),
+                    });
+                } else {
+                    stream.cancel();
+                }
+            }
+            Ok(AudioCommand::SetTransition(new_transition)) => {
+                transition = new_transition;
+                playlist.lock().transition = new_transition;
+            }
+            Ok(AudioCommand::Seek(position)) => {
+                if current_sink.is_some() {
+                    if let Some(current) = &playlist.lock().current {
+                        current.queue.seek(position, current.channels, current.sample_rate);
+                    }
+                }
+            }
+            Ok(AudioCommand::SetLoop(region)) => {
+                loop_region = region;
+                if let Some(current) = &playlist.lock().current {
+                    current.apply_loop(loop_region);
+                }
+            }
+            Ok(AudioCommand::ClearQueue) => {
+                cancel_all(&mut pending, &playlist);
+            }
             Ok(AudioCommand::Stop) => {
                 if let Some(sink) = current_sink.take() {
                     sink.stop();
                 }
+                cancel_all(&mut pending, &playlist);
                 let _ = status_tx.send(AudioStatus::Idle);
             }
             Ok(AudioCommand::Pause) => {
@@ -161,19 +424,653 @@ fn audio_thread(command_rx: Receiver<AudioCommand>, status_tx: Sender<AudioStatu
             }
         }
 
-        // 检查播放是否完成
-        if let Some(ref sink) = current_sink {
-            if sink.empty() {
+        // 按顺序把就绪的解码器交给播放队列
+        while let Some(segment) = pending.front() {
+            match segment.source_rx.try_recv() {
+                Ok(Ok(source)) => {
+                    let index = segment.index;
+                    pending.pop_front();
+                    let Some(sink) = &current_sink else { break };
+                    if !source_started {
+                        source_started = true;
+                        let playlist_source =
+                            PlaylistSource::new(playlist.clone(), shared.normalize.clone(), source.channels, source.sample_rate);
+                        sink.append(TimeStretch::new(playlist_source, shared.speed.clone()));
+                    }
+                    playlist.lock().upcoming.push_back((index, source));
+                }
+                Ok(Err(e)) => {
+                    eprintln!("Failed to decode audio for segment {}: {}", segment.index, e);
+                    pending.pop_front();
+                    if !source_started && pending.is_empty() {
+                        current_sink = None;
+                        let _ = status_tx.send(AudioStatus::Idle);
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    pending.pop_front();
+                }
+            }
+        }
+        playlist.lock().decoding = pending.len();
+
+        // 检查播放是否完成（队列中的段落全部播完）
+        if let Some(sink) = &current_sink {
+            if sink.empty() && pending.is_empty() {
                 let _ = status_tx.send(AudioStatus::Finished);
                 current_sink = None;
             }
         }
 
+        // 通知开始播放的段落
+        let current = if current_sink.is_some() { playlist.lock().current.clone() } else { None };
+        if let Some(current) = &current {
+            if last_notified != Some(current.index) {
+                last_notified = Some(current.index);
+                current.apply_loop(loop_region);
+                let _ = segment_tx.send(current.index);
+            }
+        }
+
+        // 同步音量和播放位置
+        let target_volume = f32::from_bits(shared.volume.load(Ordering::Relaxed));
+        if let Some(sink) = &current_sink {
+            if sink.volume() != target_volume {
+                sink.set_volume(target_volume);
+            }
+        }
+        let position = current.map(|current| {
+            let (elapsed, duration) = current.queue.progress(current.channels, current.sample_rate);
+            let (loop_passes, looping) = current.queue.loop_state();
+            PlaybackPosition { segment_index: current.index, elapsed, duration, loop_passes, looping }
+        });
+        if let Ok(mut shared_position) = shared.position.lock() {
+            *shared_position = position;
+        }
+
         // 短暂休眠以避免忙等待
         thread::sleep(std::time::Duration::from_millis(50));
     }
 }
 
+/// 把增益向目标移动至多 `GAIN_RAMP_DB`
+fn ramp_gain(current: f32, target: f32) -> f32 {
+    let step = 10f32.powf(GAIN_RAMP_DB / 20.0);
+    target.clamp(current / step, current * step)
+}
+
+// ============================================================================
+// 播放队列
+// ============================================================================
+
+/// 正在播放的段落，供音频线程报告播放位置、跳转和发送通知
+#[derive(Clone)]
+struct NowPlaying {
+    index: u32,
+    queue: SampleQueue,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl NowPlaying {
+    /// 应用段内循环，循环的是其他段落时取消本段的循环
+    fn apply_loop(&self, region: Option<LoopRegion>) {
+        let region = region.filter(|region| region.applies_to(self.index));
+        self.queue.set_loop(region, self.channels, self.sample_rate);
+    }
+}
+
+/// 音频线程与 `PlaylistSource` 共享的播放队列
+#[derive(Default)]
+struct PlaylistState {
+    /// 解码器已就绪、等待播放的段落
+    upcoming: VecDeque<(u32, StreamingSource)>,
+    /// 还在等待解码器就绪的段落数，大于 0 时当前段播完后等待而不是结束
+    decoding: usize,
+    /// 正在播放的段落（交叉淡化时为淡入的一段）
+    current: Option<NowPlaying>,
+    transition: SegmentTransition,
+}
+
+#[derive(Clone, Default)]
+struct Playlist(Arc<Mutex<PlaylistState>>);
+
+impl Playlist {
+    fn new(transition: SegmentTransition) -> Self {
+        Self(Arc::new(Mutex::new(PlaylistState { transition, ..Default::default() })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PlaylistState> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// `PlaylistSource` 中正在输出的一段
+struct ActiveSegment {
+    queue: SampleQueue,
+    /// 段落自身的声道数和采样率
+    channels: u16,
+    sample_rate: u32,
+    /// 转换为输出声道数和采样率后的样本
+    source: rodio::source::UniformSourceIterator<StreamingSource, i16>,
+    /// 正在应用的响度均衡增益
+    gain: f32,
+}
+
+impl ActiveSegment {
+    /// 解码完成后剩余的帧数（按输出采样率换算）
+    fn remaining_frames(&self, output_rate: u32) -> Option<usize> {
+        let decoded = self.queue.lock();
+        (decoded.done && !decoded.loop_pending()).then(|| {
+            let frames = decoded.unplayed() as u64 / u64::from(self.channels.max(1));
+            (frames * u64::from(output_rate) / u64::from(self.sample_rate.max(1))) as usize
+        })
+    }
+
+    /// 读取一帧并乘以增益；段落已结束时返回 `false`
+    fn read_frame(&mut self, frame: &mut [f32]) -> bool {
+        for (c, out) in frame.iter_mut().enumerate() {
+            match self.source.next() {
+                Some(sample) => *out = f32::from(sample) * self.gain,
+                None if c == 0 => return false,
+                None => *out = 0.0,
+            }
+        }
+        true
+    }
+}
+
+/// 依次播放队列中段落的 rodio Source
+///
+/// 所有段落转换为第一段的声道数和采样率后输出；段落之间按 `SegmentTransition`
+/// 衔接，并对每段应用各自的响度均衡增益（尚未测得响度时沿用上一段的增益）。
+/// 当前段播完且队列为空、也没有正在解码的段落时结束。
+struct PlaylistSource {
+    playlist: Playlist,
+    normalize: Arc<AtomicBool>,
+    channels: u16,
+    sample_rate: u32,
+    current: Option<ActiveSegment>,
+    /// 交叉淡入的下一段，以及淡化总帧数和已输出帧数
+    incoming: Option<(ActiveSegment, usize, usize)>,
+    /// 段落之间还需输出的静音帧数
+    silence: usize,
+    /// 上一段最后的增益
+    last_gain: f32,
+    frames_until_control: usize,
+    /// 当前帧中尚未输出的样本
+    frame: VecDeque<i16>,
+    scratch: (Vec<f32>, Vec<f32>),
+}
+
+impl PlaylistSource {
+    fn new(playlist: Playlist, normalize: Arc<AtomicBool>, channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        Self {
+            playlist,
+            normalize,
+            channels,
+            sample_rate,
+            current: None,
+            incoming: None,
+            silence: 0,
+            last_gain: 1.0,
+            frames_until_control: 0,
+            frame: VecDeque::new(),
+            scratch: (vec![0.0; channels as usize], vec![0.0; channels as usize]),
+        }
+    }
+
+    /// 段落的目标增益，未测得响度时为 `None`
+    fn target_gain(&self, queue: &SampleQueue) -> Option<f32> {
+        if self.normalize.load(Ordering::Relaxed) {
+            queue.lock().gain
+        } else {
+            Some(1.0)
+        }
+    }
+
+    /// 开始输出一段，并记录为正在播放
+    fn activate(&mut self, index: u32, source: StreamingSource) -> ActiveSegment {
+        let queue = source.queue.clone();
+        let (channels, sample_rate) = (source.channels, source.sample_rate);
+        let gain = self.target_gain(&queue).unwrap_or(self.last_gain);
+        self.playlist.lock().current = Some(NowPlaying { index, queue: queue.clone(), channels, sample_rate });
+        ActiveSegment {
+            queue,
+            channels,
+            sample_rate,
+            source: rodio::source::UniformSourceIterator::new(source, self.channels, self.sample_rate),
+            gain,
+        }
+    }
+
+    /// 定期更新均衡增益，交叉淡化模式下在当前段接近结尾时开始淡入下一段
+    fn control(&mut self) {
+        for segment in self.current.iter_mut().chain(self.incoming.as_mut().map(|(segment, _, _)| segment)) {
+            let target = match self.normalize.load(Ordering::Relaxed) {
+                true => segment.queue.lock().gain.unwrap_or(segment.gain),
+                false => 1.0,
+            };
+            segment.gain = ramp_gain(segment.gain, target);
+        }
+
+        let SegmentTransition::Crossfade { ms } = self.playlist.lock().transition else { return };
+        if self.incoming.is_some() {
+            return;
+        }
+        let fade_frames = ms as usize * self.sample_rate as usize / 1000;
+        let Some(remaining) = self.current.as_ref().and_then(|current| current.remaining_frames(self.sample_rate)) else {
+            return;
+        };
+        if remaining > fade_frames {
+            // 下次检查正好在淡化开始的位置
+            self.frames_until_control = self.frames_until_control.min(remaining - fade_frames);
+            return;
+        }
+        if remaining == 0 {
+            return;
+        }
+        let next = self.playlist.lock().upcoming.pop_front();
+        if let Some((index, source)) = next {
+            let segment = self.activate(index, source);
+            self.incoming = Some((segment, remaining, 0));
+        }
+    }
+
+    /// 输出一帧到 `self.frame`，播放队列结束时返回 `false`
+    fn mix_frame(&mut self) -> bool {
+        let channels = self.channels as usize;
+        loop {
+            if self.silence > 0 {
+                self.silence -= 1;
+                self.frame.extend(std::iter::repeat_n(0, channels));
+                return true;
+            }
+
+            if self.current.is_none() {
+                let (next, decoding) = {
+                    let mut state = self.playlist.lock();
+                    (state.upcoming.pop_front(), state.decoding > 0)
+                };
+                match next {
+                    Some((index, source)) => {
+                        let segment = self.activate(index, source);
+                        self.current = Some(segment);
+                    }
+                    None if decoding => {
+                        // 下一段还在等待解码器就绪
+                        self.frame.extend(std::iter::repeat_n(0, channels));
+                        return true;
+                    }
+                    None => {
+                        self.playlist.lock().current = None;
+                        return false;
+                    }
+                }
+            }
+
+            if self.frames_until_control == 0 {
+                self.frames_until_control = CONTROL_INTERVAL_FRAMES;
+                self.control();
+            }
+            self.frames_until_control -= 1;
+
+            let (out, other) = (&mut self.scratch.0, &mut self.scratch.1);
+            let Some(current) = self.current.as_mut() else { continue };
+            if !current.read_frame(out) {
+                // 当前段结束：交叉淡化中的下一段接替，否则按设置插入停顿
+                self.last_gain = current.gain;
+                self.current = self.incoming.take().map(|(segment, _, _)| segment);
+                if self.current.is_none() {
+                    let state = self.playlist.lock();
+                    let has_next = !state.upcoming.is_empty() || state.decoding > 0;
+                    if let (SegmentTransition::Pause { ms }, true) = (state.transition, has_next) {
+                        self.silence = ms as usize * self.sample_rate as usize / 1000;
+                    }
+                }
+                continue;
+            }
+
+            if let Some((incoming, total, done)) = self.incoming.as_mut() {
+                // 等功率交叉淡化
+                let t = (*done as f32 / *total as f32).min(1.0) * std::f32::consts::FRAC_PI_2;
+                if !incoming.read_frame(other) {
+                    other.fill(0.0);
+                }
+                for (a, b) in out.iter_mut().zip(other.iter()) {
+                    *a = *a * t.cos() + b * t.sin();
+                }
+                *done += 1;
+            }
+
+            self.frame
+                .extend(out.iter().map(|&sample| sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16));
+            return true;
+        }
+    }
+}
+
+impl Iterator for PlaylistSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.frame.is_empty() && !self.mix_frame() {
+            return None;
+        }
+        self.frame.pop_front()
+    }
+}
+
+impl rodio::Source for PlaylistSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// ============================================================================
+// 流式解码
+// ============================================================================
+
+/// 解码线程输出的样本
+#[derive(Default)]
+struct DecodedSamples {
+    /// 已解码的全部样本，播放过的部分也保留，供段落内往回跳转
+    samples: Vec<i16>,
+    /// 下一个要输出的样本
+    position: usize,
+    /// 尚未生效的跳转目标（样本位置，帧对齐），由 `StreamingSource` 在帧边界应用
+    seek_to: Option<usize>,
+    looping: Option<SampleLoop>,
+    /// 解码已结束（音频结束、下载失败或被取消）
+    done: bool,
+    /// 按已解码部分的响度计算的均衡增益，不足一个测量块时为 `None`
+    gain: Option<f32>,
+}
+
+impl DecodedSamples {
+    /// 播放位置（包括尚未生效的跳转）
+    fn playhead(&self) -> usize {
+        self.seek_to.unwrap_or(self.position)
+    }
+
+    /// 已解码但尚未播放的样本数
+    fn unplayed(&self) -> usize {
+        self.samples.len().saturating_sub(self.playhead())
+    }
+
+    /// 之后还会回到循环起点
+    fn loop_pending(&self) -> bool {
+        self.looping.is_some_and(|looping| looping.repeats != Some(0))
+    }
+
+    /// 播放到循环终点时回到起点，由 `StreamingSource` 在帧边界调用
+    fn wrap_loop(&mut self) {
+        let Some(looping) = &mut self.looping else { return };
+        let at_end = match looping.end {
+            Some(end) => self.position >= end,
+            None => self.done && self.position >= self.samples.len(),
+        };
+        if at_end && looping.repeats != Some(0) {
+            self.position = looping.start;
+            looping.repeats = looping.repeats.map(|repeats| repeats - 1);
+            looping.passes += 1;
+        }
+    }
+}
+
+/// 段内循环（样本位置，帧对齐）
+#[derive(Debug, Clone, Copy)]
+struct SampleLoop {
+    start: usize,
+    /// `None` 为段落结尾
+    end: Option<usize>,
+    /// 还要回到起点的次数，`None` 为一直循环
+    repeats: Option<u32>,
+    /// 已回到起点的次数
+    passes: u32,
+}
+
+#[derive(Clone, Default)]
+struct SampleQueue(Arc<Mutex<DecodedSamples>>);
+
+impl SampleQueue {
+    fn lock(&self) -> MutexGuard<'_, DecodedSamples> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, samples: &[i16]) {
+        self.lock().samples.extend_from_slice(samples);
+    }
+
+    fn set_gain(&self, gain: Option<f32>) {
+        self.lock().gain = gain;
+    }
+
+    fn finish(&self) {
+        self.lock().done = true;
+    }
+
+    /// 已播放时长和总时长（解码完成前总时长未知）
+    fn progress(&self, channels: u16, sample_rate: u32) -> (Duration, Option<Duration>) {
+        let decoded = self.lock();
+        let to_duration = |samples: usize| {
+            let frames = samples as u64 / u64::from(channels.max(1));
+            Duration::from_millis(frames * 1000 / u64::from(sample_rate.max(1)))
+        };
+        (to_duration(decoded.playhead()), decoded.done.then(|| to_duration(decoded.samples.len())))
+    }
+
+    /// 设置段内循环，终点不在起点之后时取消循环
+    fn set_loop(&self, region: Option<LoopRegion>, channels: u16, sample_rate: u32) {
+        let channels = channels.max(1) as usize;
+        let to_sample = |position: Duration| (position.as_secs_f64() * f64::from(sample_rate)) as usize * channels;
+        self.lock().looping = region
+            .map(|region| SampleLoop {
+                start: to_sample(region.start),
+                end: region.end.map(to_sample),
+                repeats: region.repeats,
+                passes: 0,
+            })
+            .filter(|looping| looping.end.is_none_or(|end| end > looping.start));
+    }
+
+    /// 段内循环已回到起点的次数，以及之后是否还会回到起点
+    fn loop_state(&self) -> (u32, bool) {
+        let decoded = self.lock();
+        (decoded.looping.map_or(0, |looping| looping.passes), decoded.loop_pending())
+    }
+
+    /// 跳转到 `position`，超过已解码的部分时停在已解码的末尾（之后照常缓冲）
+    fn seek(&self, position: Duration, channels: u16, sample_rate: u32) {
+        let channels = channels.max(1) as usize;
+        let mut decoded = self.lock();
+        let frame = (position.as_secs_f64() * f64::from(sample_rate)) as usize;
+        let decoded_frames = decoded.samples.len() / channels;
+        decoded.seek_to = Some(frame.min(decoded_frames) * channels);
+    }
+}
+
+/// 启动解码线程：读到音频头部后通过返回的 channel 交出 `StreamingSource`，
+/// 然后持续把解码出的样本写入队列，直到音频结束或 `stream` 被取消
+fn spawn_decoder(stream: AudioStream) -> Receiver<Result<StreamingSource, String>> {
+    let (source_tx, source_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let decoder = match open_decoder(&stream) {
+            Ok(decoder) => decoder,
+            Err(e) => {
+                let _ = source_tx.send(Err(e));
+                return;
+            }
+        };
+
+        let channels = rodio::Source::channels(&decoder);
+        let sample_rate = rodio::Source::sample_rate(&decoder);
+        let queue = SampleQueue::default();
+        let source = StreamingSource::new(queue.clone(), stream.clone(), channels, sample_rate, MIN_BUFFER);
+        if source_tx.send(Ok(source)).is_err() {
+            return;
+        }
+
+        let batch_len = DECODE_BATCH_FRAMES * channels.max(1) as usize;
+        let mut batch = Vec::with_capacity(batch_len);
+        let mut meter = LoudnessMeter::new(channels, sample_rate);
+        for sample in decoder {
+            batch.push(sample);
+            if batch.len() == batch_len {
+                if stream.is_cancelled() {
+                    break;
+                }
+                meter.push(&batch);
+                queue.push(&batch);
+                queue.set_gain(meter.gain());
+                batch.clear();
+            }
+        }
+        meter.push(&batch);
+        queue.push(&batch);
+        queue.set_gain(meter.gain());
+        queue.finish();
+    });
+    source_rx
+}
+
+/// 按服务器声明的格式创建解码器，格式未知时由 rodio 猜测
+pub(crate) fn open_decoder(stream: &AudioStream) -> Result<rodio::Decoder<AudioStreamReader>, String> {
+    use rodio::Decoder;
+
+    let reader = stream.reader();
+    let decoder = match stream.format() {
+        Some(AudioFormat::Wav) => Decoder::new_wav(reader),
+        Some(AudioFormat::Flac) => Decoder::new_flac(reader),
+        Some(AudioFormat::OggVorbis) => Decoder::new_vorbis(reader),
+        Some(AudioFormat::Mp3) => Decoder::new_mp3(reader),
+        Some(format @ AudioFormat::OggOpus) => return Err(format!("unsupported audio format {}", format.label())),
+        None => Decoder::new(reader),
+    };
+    decoder.map_err(|e| e.to_string())
+}
+
+/// 从样本队列播放的 rodio Source
+///
+/// 队列中未播放的样本不足最小缓冲时输出静音；播放到末尾且解码已结束时播放结束。
+/// 被丢弃（播放停止或结束）时取消对应的下载。
+struct StreamingSource {
+    queue: SampleQueue,
+    stream: AudioStream,
+    channels: u16,
+    sample_rate: u32,
+    /// 开始或恢复输出前需要积累的样本数
+    min_buffer_samples: usize,
+    buffering: bool,
+    /// 已输出的样本数，只在帧边界切换缓冲状态以保持声道对齐
+    emitted: u64,
+    underruns: u32,
+}
+
+impl StreamingSource {
+    fn new(queue: SampleQueue, stream: AudioStream, channels: u16, sample_rate: u32, min_buffer: Duration) -> Self {
+        let channels = channels.max(1);
+        let frames = (sample_rate as f64 * min_buffer.as_secs_f64()) as usize;
+        Self {
+            queue,
+            stream,
+            channels,
+            sample_rate,
+            min_buffer_samples: frames * channels as usize,
+            buffering: true,
+            emitted: 0,
+            underruns: 0,
+        }
+    }
+}
+
+impl Iterator for StreamingSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let mut decoded = self.queue.lock();
+        let at_frame_start = self.emitted.is_multiple_of(self.channels as u64);
+
+        if at_frame_start {
+            if let Some(target) = decoded.seek_to.take() {
+                decoded.position = target;
+            }
+            decoded.wrap_loop();
+        }
+        if self.buffering && at_frame_start && (decoded.unplayed() >= self.min_buffer_samples || decoded.done) {
+            self.buffering = false;
+            if self.underruns > 0 {
+                tracing::debug!("Audio stream rebuffered, resuming playback");
+            }
+        }
+
+        let sample = if self.buffering {
+            Some(0)
+        } else {
+            match decoded.samples.get(decoded.position).copied() {
+                Some(sample) => {
+                    decoded.position += 1;
+                    Some(sample)
+                }
+                None if decoded.done => None,
+                None => {
+                    // 欠载：下载跟不上播放，输出静音直到重新积累最小缓冲
+                    self.underruns += 1;
+                    self.buffering = true;
+                    tracing::warn!(
+                        "Audio stream underrun #{} ({} bytes received), rebuffering",
+                        self.underruns,
+                        self.stream.received()
+                    );
+                    Some(0)
+                }
+            }
+        };
+        if sample.is_some() {
+            self.emitted += 1;
+        }
+        sample
+    }
+}
+
+impl rodio::Source for StreamingSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Drop for StreamingSource {
+    fn drop(&mut self) {
+        self.stream.cancel();
+    }
+}
+
 /// 处理播放音频事件
 pub fn handle_play_audio(
     mut events: EventReader<PlayAudioEvent>,
@@ -182,7 +1079,31 @@ pub fn handle_play_audio(
     let Some(player) = audio_player else { return };
 
     for event in events.read() {
-        player.play(event.data.clone());
+        player.play(event.segment_index, event.stream.clone());
+    }
+}
+
+/// 处理排队音频事件
+pub fn handle_queue_audio(
+    mut events: EventReader<QueueAudioEvent>,
+    audio_player: Option<Res<AudioPlayer>>,
+) {
+    let Some(player) = audio_player else { return };
+
+    for event in events.read() {
+        player.enqueue(event.segment_index, event.stream.clone());
+    }
+}
+
+/// 处理段内跳转事件
+pub fn handle_seek_audio(
+    mut events: EventReader<SeekAudioEvent>,
+    audio_player: Option<Res<AudioPlayer>>,
+) {
+    let Some(player) = audio_player else { return };
+
+    for event in events.read() {
+        player.seek(event.position);
     }
 }
 
@@ -222,15 +1143,67 @@ pub fn handle_resume_audio(
     }
 }
 
-/// 检查音频是否播放完成
+/// 把当前会话的播放速度、响度均衡、段落衔接和循环设置同步到音频线程（音量由 `update_sleep_timer` 同步）
+///
+/// 离开 A-B 循环的段落时取消循环；循环设置变化后，已排队但不再是下一段的段落从播放队列中移除。
+pub fn sync_playback_settings(
+    mut app_state: ResMut<AppState>,
+    config: Res<AppConfig>,
+    audio_player: Option<Res<AudioPlayer>>,
+    mut last_loop: Local<Option<LoopRegion>>,
+) {
+    if matches!(app_state.repeat, Some(RepeatMode::AB { index, .. }) if index as usize != app_state.current_segment_index) {
+        app_state.repeat = None;
+    }
+    let stale_queue = app_state.queued_segment.is_some_and(|(queued, _)| {
+        let queued = queued as usize;
+        queued != app_state.current_segment_index && app_state.next_segment_index() != Some(queued)
+    });
+    if stale_queue {
+        app_state.queued_segment = None;
+    }
+
+    let Some(player) = audio_player else { return };
+    player.set_speed(app_state.current_session.as_ref().map_or(1.0, |session| session.speed));
+    player.set_normalize(config.volume.normalize);
+    if config.is_changed() {
+        player.set_transition(config.segment_transition);
+    }
+    let region = app_state.repeat.and_then(|repeat| repeat.loop_region());
+    if *last_loop != region {
+        *last_loop = region;
+        player.set_loop(region);
+    }
+    if stale_queue {
+        player.clear_queue();
+    }
+}
+
+/// 把当前段的播放位置同步到 `AppState`，其他段落（排队后尚未切换）的位置不显示
+pub fn track_playback_position(mut app_state: ResMut<AppState>, audio_player: Option<Res<AudioPlayer>>) {
+    let Some(player) = audio_player else { return };
+    let position = player
+        .position()
+        .filter(|position| position.segment_index as usize == app_state.current_segment_index);
+    if app_state.playback_position != position {
+        app_state.playback_position = position;
+    }
+}
+
+/// 检查开始播放的段落和音频是否播放完成
 pub fn check_audio_finished(
     audio_player: Option<Res<AudioPlayer>>,
+    mut started_events: EventWriter<SegmentStartedEvent>,
     mut finished_events: EventWriter<AudioFinishedEvent>,
 ) {
     let Some(player) = audio_player else {
         return;
     };
 
+    for segment_index in player.poll_started_segments() {
+        started_events.send(SegmentStartedEvent { segment_index });
+    }
+
     let status = player.poll_status();
     if status == AudioStatus::Finished {
         // 检查是否已经通知过，防止重复触发
@@ -242,3 +1215,155 @@ pub fn check_audio_finished(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::wav_tone;
+    use std::time::Instant;
+
+    #[test]
+    fn streaming_source_waits_for_min_buffer_and_rebuffers_after_underrun() {
+        let queue = SampleQueue::default();
+        let stream = AudioStream::new();
+        // 1 kHz 单声道，最小缓冲 4 个样本
+        let mut source = StreamingSource::new(queue.clone(), stream.clone(), 1, 1000, Duration::from_millis(4));
+
+        queue.push(&[1, 2]);
+        assert_eq!(source.next(), Some(0));
+        queue.push(&[3, 4]);
+        assert_eq!(source.by_ref().take(4).collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+        // 欠载后输出静音，重新积累最小缓冲再继续
+        assert_eq!(source.next(), Some(0));
+        assert_eq!(source.underruns, 1);
+        queue.push(&[5, 6, 7]);
+        assert_eq!(source.next(), Some(0));
+        queue.push(&[8]);
+        assert_eq!(source.by_ref().take(4).collect::<Vec<_>>(), [5, 6, 7, 8]);
+
+        // 解码结束后不足最小缓冲的剩余样本直接播放
+        queue.push(&[9]);
+        queue.finish();
+        assert_eq!(source.next(), Some(9));
+        assert_eq!(source.next(), None);
+
+        drop(source);
+        assert!(stream.is_cancelled());
+    }
+
+    #[test]
+    fn seeking_rewinds_played_samples_on_frame_boundaries() {
+        let queue = SampleQueue::default();
+        // 1 kHz 双声道，每帧 (n, -n)
+        let samples: Vec<i16> = (1..=10).flat_map(|n| [n, -n]).collect();
+        queue.push(&samples);
+        let mut source = StreamingSource::new(queue.clone(), AudioStream::new(), 2, 1000, Duration::ZERO);
+
+        assert_eq!(source.by_ref().take(7).collect::<Vec<_>>(), [1, -1, 2, -2, 3, -3, 4]);
+        assert_eq!(queue.progress(2, 1000), (Duration::from_millis(3), None));
+
+        // 跳转在当前帧输出完之后生效，报告的位置立即更新
+        queue.seek(Duration::from_millis(1), 2, 1000);
+        assert_eq!(queue.progress(2, 1000).0, Duration::from_millis(1));
+        assert_eq!(source.by_ref().take(3).collect::<Vec<_>>(), [-4, 2, -2]);
+
+        // 超过已解码的部分时停在末尾，输出静音等待后续样本
+        queue.seek(Duration::from_secs(5), 2, 1000);
+        assert_eq!(source.by_ref().take(2).collect::<Vec<_>>(), [0, 0]);
+        queue.push(&[11, -11]);
+        queue.finish();
+        assert_eq!(source.collect::<Vec<_>>(), [11, -11]);
+        assert_eq!(queue.progress(2, 1000), (Duration::from_millis(11), Some(Duration::from_millis(11))));
+    }
+
+    #[test]
+    fn loops_repeat_segment_and_ab_region() {
+        let queue = SampleQueue::default();
+        queue.push(&[1, 2, 3, 4, 5, 6]);
+        queue.finish();
+        let loop_region = |start: u64, end: Option<u64>, repeats: Option<u32>| LoopRegion {
+            segment_index: None,
+            start: Duration::from_millis(start),
+            end: end.map(Duration::from_millis),
+            repeats,
+        };
+
+        // 整段重复两遍后结束
+        queue.set_loop(Some(loop_region(0, None, Some(1))), 1, 1000);
+        let mut source = StreamingSource::new(queue.clone(), AudioStream::new(), 1, 1000, Duration::ZERO);
+        assert_eq!(source.by_ref().take(6).collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(queue.loop_state(), (0, true));
+        assert_eq!(source.by_ref().take(6).collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(queue.loop_state(), (1, false));
+        assert_eq!(source.next(), None);
+
+        // A-B 一直循环，跳到 B 之后同样回到 A
+        queue.seek(Duration::ZERO, 1, 1000);
+        queue.set_loop(Some(loop_region(1, Some(3), None)), 1, 1000);
+        let mut source = StreamingSource::new(queue.clone(), AudioStream::new(), 1, 1000, Duration::ZERO);
+        assert_eq!(source.by_ref().take(7).collect::<Vec<_>>(), [1, 2, 3, 2, 3, 2, 3]);
+        queue.seek(Duration::from_millis(5), 1, 1000);
+        assert_eq!(source.by_ref().take(2).collect::<Vec<_>>(), [2, 3]);
+
+        // 终点不在起点之后时不循环
+        queue.set_loop(Some(loop_region(3, Some(3), None)), 1, 1000);
+        assert_eq!(queue.loop_state(), (0, false));
+    }
+
+    /// 已解码完成的段落（1 kHz 单声道）
+    fn decoded_segment(samples: &[i16]) -> StreamingSource {
+        let queue = SampleQueue::default();
+        queue.push(samples);
+        queue.finish();
+        StreamingSource::new(queue, AudioStream::new(), 1, 1000, Duration::ZERO)
+    }
+
+    fn play_queue(transition: SegmentTransition, segments: Vec<(u32, &[i16])>) -> (Vec<i16>, Playlist) {
+        let playlist = Playlist::new(transition);
+        playlist.lock().upcoming.extend(segments.into_iter().map(|(index, samples)| (index, decoded_segment(samples))));
+        let source = PlaylistSource::new(playlist.clone(), Arc::new(AtomicBool::new(false)), 1, 1000);
+        (source.collect(), playlist)
+    }
+
+    #[test]
+    fn playlist_joins_segments_with_configured_transition() {
+        let (first, second) = ([100i16; 20], [200i16; 20]);
+
+        let (output, playlist) = play_queue(SegmentTransition::Gapless, vec![(3, &first), (4, &second)]);
+        assert_eq!(output, [first, second].concat());
+        assert!(playlist.lock().current.is_none());
+
+        // 1 kHz 下 5ms 为 5 帧静音，最后一段之后不加停顿
+        let (output, _) = play_queue(SegmentTransition::Pause { ms: 5 }, vec![(3, &first), (4, &second)]);
+        assert_eq!(output, [&first[..], &[0; 5], &second[..]].concat());
+
+        // 交叉淡化 10ms：重叠部分从第一段过渡到第二段，总长缩短 10 帧
+        let (output, _) = play_queue(SegmentTransition::Crossfade { ms: 10 }, vec![(3, &first), (4, &second)]);
+        assert_eq!(output.len(), 30);
+        assert!(output[..11].iter().all(|&s| s == 100));
+        assert!(output[11..20].iter().all(|&s| s > 100));
+        assert!(output[20..].iter().all(|&s| s == 200));
+    }
+
+    #[test]
+    fn decoder_starts_before_download_completes() {
+        let wav = wav_tone(200, 440.0);
+        let stream = AudioStream::new().with_format(Some(AudioFormat::Wav));
+        let source_rx = spawn_decoder(stream.clone());
+
+        stream.push(&wav[..1024]);
+        let source = source_rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!((source.channels, source.sample_rate), (1, 16_000));
+
+        stream.push(&wav[1024..]);
+        stream.finish();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !source.queue.lock().done {
+            assert!(Instant::now() < deadline, "decoder did not finish");
+            thread::sleep(Duration::from_millis(10));
+        }
+        // 16 kHz 单声道 200ms
+        assert_eq!(source.count(), 3200);
+    }
+}
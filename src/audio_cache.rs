@@ -0,0 +1,486 @@
+//! 音频磁盘缓存
+//!
+//! 已下载的段落音频按内容的 SHA-256 保存在 `<配置目录>/rovel-desk/audio_cache/<sha256>`，
+//! 索引 `index.json` 记录 (novel_id, voice_id, segment_index) 到内容的映射。
+//! - 总大小超过上限时按最近使用时间（LRU）淘汰
+//! - 读取时校验 SHA-256，文件损坏或丢失则丢弃该条目并重新下载
+//! - 多个段落内容相同时共用一个文件
+//! - 命中缓存只在内存中更新使用顺序，索引最多每 `INDEX_SAVE_INTERVAL` 写一次，退出时再写回
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::api::SegmentAudio;
+use crate::audio_format::AudioFormat;
+use crate::config::app_config_dir;
+use crate::fs_util::write_atomic;
+
+/// 索引文件名
+const INDEX_FILE_NAME: &str = "index.json";
+
+/// 只有使用顺序变化时，索引写回磁盘的最小间隔
+const INDEX_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 磁盘缓存策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioCachePolicy {
+    pub enabled: bool,
+    /// 缓存总大小上限（字节）
+    pub max_bytes: u64,
+}
+
+impl Default for AudioCachePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_bytes: 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// 一段缓存音频
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    novel_id: Uuid,
+    voice_id: Uuid,
+    segment_index: u32,
+    /// 内容的 SHA-256（十六进制），也是文件名
+    sha256: String,
+    size: u64,
+    #[serde(default)]
+    format: Option<AudioFormat>,
+    /// 最近使用序号，越大越新
+    last_used: u64,
+}
+
+impl CacheEntry {
+    fn key(&self) -> (Uuid, Uuid, u32) {
+        (self.novel_id, self.voice_id, self.segment_index)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    /// 单调递增的使用计数，用于 LRU 排序
+    clock: u64,
+    entries: Vec<CacheEntry>,
+}
+
+/// 单本小说的缓存占用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NovelCacheUsage {
+    pub novel_id: Uuid,
+    pub segments: usize,
+    pub bytes: u64,
+}
+
+/// 缓存占用统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    /// 磁盘上的总字节数（相同内容只计一次）
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    /// 按占用从大到小排列
+    pub novels: Vec<NovelCacheUsage>,
+}
+
+struct CacheState {
+    /// `None` 表示缓存已禁用
+    dir: Option<PathBuf>,
+    max_bytes: u64,
+    index: CacheIndex,
+    /// 内存中的索引有尚未写回磁盘的修改
+    dirty: bool,
+    last_saved: Instant,
+    /// 每次生成索引快照时递增
+    version: u64,
+}
+
+/// 在锁内序列化、在锁外写入的索引
+struct IndexSnapshot {
+    dir: PathBuf,
+    version: u64,
+    content: String,
+}
+
+/// 音频磁盘缓存资源（克隆后共享同一个索引，可在工作线程中使用）
+#[derive(Resource, Clone)]
+pub struct AudioCache {
+    state: Arc<Mutex<CacheState>>,
+    /// 已写入磁盘的索引版本，避免较旧的快照覆盖较新的
+    written: Arc<Mutex<u64>>,
+}
+
+impl AudioCache {
+    /// 打开缓存目录，丢弃文件已丢失的条目和没有条目引用的文件
+    pub fn open(dir: PathBuf, max_bytes: u64) -> Self {
+        let mut index = match std::fs::read_to_string(dir.join(INDEX_FILE_NAME)) {
+            Ok(content) => serde_json::from_str::<CacheIndex>(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring corrupt audio cache index in {:?}: {}", dir, e);
+                CacheIndex::default()
+            }),
+            Err(_) => CacheIndex::default(),
+        };
+        index.entries.retain(|entry| dir.join(&entry.sha256).is_file());
+
+        if let Ok(files) = std::fs::read_dir(&dir) {
+            for path in files.filter_map(|f| f.ok()).map(|f| f.path()) {
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                if name != INDEX_FILE_NAME && !index.entries.iter().any(|e| e.sha256 == name) {
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+
+        let cache = Self::with_state(CacheState::new(Some(dir), max_bytes, index));
+        cache.lock().evict(false);
+        cache
+    }
+
+    /// 不读写磁盘的缓存
+    pub fn disabled() -> Self {
+        Self::with_state(CacheState::new(None, 0, CacheIndex::default()))
+    }
+
+    /// 按配置创建，默认目录：`<配置目录>/rovel-desk/audio_cache`
+    pub fn from_policy(policy: &AudioCachePolicy) -> Self {
+        match app_config_dir() {
+            Some(dir) if policy.enabled => Self::open(dir.join("audio_cache"), policy.max_bytes),
+            _ => Self::disabled(),
+        }
+    }
+
+    fn with_state(state: CacheState) -> Self {
+        Self { state: Arc::new(Mutex::new(state)), written: Arc::new(Mutex::new(0)) }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 读取缓存的音频，内容校验失败时丢弃该条目
+    ///
+    /// 文件读取和校验在锁外进行，命中时只在内存中更新使用顺序。
+    pub fn get(&self, novel_id: Uuid, voice_id: Uuid, segment_index: u32) -> Option<SegmentAudio> {
+        let key = (novel_id, voice_id, segment_index);
+        let (dir, entry) = {
+            let state = self.lock();
+            let dir = state.dir.clone()?;
+            let entry = state.index.entries.iter().find(|e| e.key() == key)?.clone();
+            (dir, entry)
+        };
+
+        let data = match std::fs::read(dir.join(&entry.sha256)) {
+            Ok(data) if data.len() as u64 == entry.size && sha256_hex(&data) == entry.sha256 => data,
+            Ok(_) => {
+                tracing::warn!("Audio cache entry for segment {} is corrupt, discarding", segment_index);
+                let mut state = self.lock();
+                state.remove_where(|e| e.sha256 == entry.sha256);
+                state.dirty = true;
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to read cached audio for segment {}: {}", segment_index, e);
+                let mut state = self.lock();
+                state.remove_where(|e| e.key() == key && e.sha256 == entry.sha256);
+                state.dirty = true;
+                return None;
+            }
+        };
+
+        let mut state = self.lock();
+        state.index.clock += 1;
+        let clock = state.index.clock;
+        // 读取期间条目可能已被替换或淘汰，此时不更新使用顺序
+        if let Some(current) = state.index.entries.iter_mut().find(|e| e.key() == key && e.sha256 == entry.sha256) {
+            current.last_used = clock;
+            state.dirty = true;
+        }
+        let snapshot = if state.last_saved.elapsed() >= INDEX_SAVE_INTERVAL { state.snapshot() } else { None };
+        drop(state);
+        self.persist(snapshot);
+        Some(SegmentAudio { segment_index, format: entry.format, data })
+    }
+
+    /// 保存下载完成的音频，超出上限时淘汰最久未使用的段落
+    ///
+    /// 计算哈希和写文件在锁外进行，只在更新索引时持有锁。
+    pub fn insert(&self, novel_id: Uuid, voice_id: Uuid, audio: &SegmentAudio) {
+        let (dir, max_bytes) = {
+            let state = self.lock();
+            let Some(dir) = state.dir.clone() else { return };
+            (dir, state.max_bytes)
+        };
+        let size = audio.data.len() as u64;
+        if size == 0 || size > max_bytes {
+            return;
+        }
+
+        let sha256 = sha256_hex(&audio.data);
+        let path = dir.join(&sha256);
+        if !path.is_file() {
+            let written = std::fs::create_dir_all(&dir).and_then(|_| write_atomic(&path, &audio.data));
+            // 其他线程可能同时写入了相同内容
+            if let Err(e) = written.or_else(|e| if path.is_file() { Ok(()) } else { Err(e) }) {
+                tracing::warn!("Failed to write audio cache {:?}: {}", path, e);
+                return;
+            }
+        }
+
+        let mut state = self.lock();
+        let key = (novel_id, voice_id, audio.segment_index);
+        state.remove_where(|e| e.key() == key && e.sha256 != sha256);
+        state.index.entries.retain(|e| e.key() != key);
+        state.index.clock += 1;
+        let last_used = state.index.clock;
+        state.index.entries.push(CacheEntry {
+            novel_id,
+            voice_id,
+            segment_index: audio.segment_index,
+            sha256,
+            size,
+            format: audio.format,
+            last_used,
+        });
+        state.evict(true);
+        let snapshot = state.snapshot();
+        drop(state);
+        self.persist(snapshot);
+    }
+
+    /// 缓存占用统计
+    pub fn usage(&self) -> CacheUsage {
+        let state = self.lock();
+        let mut novels: Vec<NovelCacheUsage> = Vec::new();
+        for entry in &state.index.entries {
+            match novels.iter_mut().find(|n| n.novel_id == entry.novel_id) {
+                Some(novel) => {
+                    novel.segments += 1;
+                    novel.bytes += entry.size;
+                }
+                None => novels.push(NovelCacheUsage { novel_id: entry.novel_id, segments: 1, bytes: entry.size }),
+            }
+        }
+        novels.sort_by_key(|n| std::cmp::Reverse(n.bytes));
+        CacheUsage {
+            total_bytes: state.total_bytes(),
+            max_bytes: state.max_bytes,
+            novels,
+        }
+    }
+
+    /// 清除一本小说的缓存
+    pub fn clear_novel(&self, novel_id: Uuid) {
+        let mut state = self.lock();
+        state.remove_where(|e| e.novel_id == novel_id);
+        let snapshot = state.snapshot();
+        drop(state);
+        self.persist(snapshot);
+    }
+
+    /// 清除全部缓存
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.remove_where(|_| true);
+        let snapshot = state.snapshot();
+        drop(state);
+        self.persist(snapshot);
+    }
+
+    /// 在锁外写入索引快照，跳过比已写入版本更旧的快照
+    fn persist(&self, snapshot: Option<IndexSnapshot>) {
+        let Some(snapshot) = snapshot else { return };
+        let mut written = self.written.lock().unwrap_or_else(|e| e.into_inner());
+        if snapshot.version > *written {
+            snapshot.write();
+            *written = snapshot.version;
+        }
+    }
+}
+
+impl CacheState {
+    fn new(dir: Option<PathBuf>, max_bytes: u64, index: CacheIndex) -> Self {
+        Self { dir, max_bytes, index, dirty: false, last_saved: Instant::now(), version: 0 }
+    }
+
+    /// 磁盘占用（相同内容只计一次）
+    fn total_bytes(&self) -> u64 {
+        let mut seen = std::collections::HashSet::new();
+        self.index
+            .entries
+            .iter()
+            .filter(|e| seen.insert(e.sha256.as_str()))
+            .map(|e| e.size)
+            .sum()
+    }
+
+    /// 删除条目，没有其他条目引用的文件一并删除
+    fn remove_where(&mut self, remove: impl Fn(&CacheEntry) -> bool) {
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.index.entries)
+            .into_iter()
+            .partition(|e| remove(e));
+        self.index.entries = kept;
+        if let Some(dir) = &self.dir {
+            for entry in removed {
+                if !self.index.entries.iter().any(|e| e.sha256 == entry.sha256) {
+                    let _ = std::fs::remove_file(dir.join(&entry.sha256));
+                }
+            }
+        }
+    }
+
+    /// 按 LRU 淘汰，直到总大小不超过上限；`keep_newest` 为真时不淘汰刚写入的条目
+    fn evict(&mut self, keep_newest: bool) {
+        while self.total_bytes() > self.max_bytes {
+            let clock = self.index.clock;
+            let Some(oldest) = self
+                .index
+                .entries
+                .iter()
+                .filter(|e| !keep_newest || e.last_used != clock)
+                .min_by_key(|e| e.last_used)
+                .map(|e| e.key())
+            else {
+                break;
+            };
+            tracing::debug!("Evicting cached audio for segment {}", oldest.2);
+            self.remove_where(|e| e.key() == oldest);
+        }
+    }
+
+    /// 序列化索引并标记为已保存，返回待写入磁盘的快照
+    fn snapshot(&mut self) -> Option<IndexSnapshot> {
+        self.dirty = false;
+        self.last_saved = Instant::now();
+        let dir = self.dir.clone()?;
+        self.version += 1;
+        match serde_json::to_string(&self.index) {
+            Ok(content) => Some(IndexSnapshot { dir, version: self.version, content }),
+            Err(e) => {
+                tracing::warn!("Failed to serialize audio cache index: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl IndexSnapshot {
+    fn write(&self) {
+        let result = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| write_atomic(&self.dir.join(INDEX_FILE_NAME), self.content.as_bytes()));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save audio cache index in {:?}: {}", self.dir, e);
+        }
+    }
+}
+
+impl Drop for CacheState {
+    /// 最后一个 `AudioCache` 释放时（应用退出）写回尚未保存的使用顺序
+    fn drop(&mut self) {
+        if self.dirty {
+            if let Some(snapshot) = self.snapshot() {
+                snapshot.write();
+            }
+        }
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_util::TempDir;
+    use crate::mock_server::wav_tone;
+
+    fn temp_cache(max_bytes: u64) -> (TempDir, AudioCache) {
+        let dir = TempDir::new("cache");
+        let cache = AudioCache::open(dir.to_path_buf(), max_bytes);
+        (dir, cache)
+    }
+
+    fn segment(index: u32) -> SegmentAudio {
+        SegmentAudio {
+            segment_index: index,
+            format: Some(AudioFormat::Wav),
+            data: wav_tone(100, 220.0 + 20.0 * index as f32),
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_segments_over_the_cap() {
+        let size = segment(0).data.len() as u64;
+        let (dir, cache) = temp_cache(size * 2);
+        let (novel, voice) = (Uuid::new_v4(), Uuid::new_v4());
+
+        cache.insert(novel, voice, &segment(0));
+        cache.insert(novel, voice, &segment(1));
+        // 使用第 0 段后，第 1 段成为最久未使用
+        assert_eq!(cache.get(novel, voice, 0), Some(segment(0)));
+        cache.insert(novel, voice, &segment(2));
+
+        assert!(cache.get(novel, voice, 1).is_none());
+        assert_eq!(cache.get(novel, voice, 2), Some(segment(2)));
+        assert_eq!(cache.usage().total_bytes, size * 2);
+
+        // 重新打开后索引仍然有效
+        let reopened = AudioCache::open(dir.to_path_buf(), size * 2);
+        assert_eq!(reopened.get(novel, voice, 0), Some(segment(0)));
+    }
+
+    #[test]
+    fn cache_hits_are_persisted_on_drop_instead_of_every_read() {
+        let size = segment(0).data.len() as u64;
+        let (dir, cache) = temp_cache(size * 2);
+        let (novel, voice) = (Uuid::new_v4(), Uuid::new_v4());
+        cache.insert(novel, voice, &segment(0));
+        cache.insert(novel, voice, &segment(1));
+
+        let index_path = dir.join(INDEX_FILE_NAME);
+        let saved = std::fs::read_to_string(&index_path).unwrap();
+        assert_eq!(cache.get(novel, voice, 0), Some(segment(0)));
+        assert_eq!(std::fs::read_to_string(&index_path).unwrap(), saved);
+
+        // 退出时写回使用顺序：重新打开后第 1 段是最久未使用的
+        drop(cache);
+        let reopened = AudioCache::open(dir.to_path_buf(), size * 2);
+        reopened.insert(novel, voice, &segment(2));
+        assert!(reopened.get(novel, voice, 1).is_none());
+        assert_eq!(reopened.get(novel, voice, 0), Some(segment(0)));
+    }
+
+    #[test]
+    fn corrupt_files_are_discarded_and_novels_cleared_separately() {
+        let (dir, cache) = temp_cache(u64::MAX);
+        let (novel, other, voice) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        cache.insert(novel, voice, &segment(0));
+        cache.insert(novel, voice, &segment(1));
+        cache.insert(other, voice, &segment(0));
+
+        // 第 1 段的文件被截断
+        let corrupt = dir.join(sha256_hex(&segment(1).data));
+        std::fs::write(&corrupt, b"RIFF").unwrap();
+        assert!(cache.get(novel, voice, 1).is_none());
+        assert!(!corrupt.exists());
+
+        // 两本小说的第 0 段内容相同，共用一个文件
+        let usage = cache.usage();
+        assert_eq!(usage.total_bytes, segment(0).data.len() as u64);
+        assert_eq!(usage.novels.len(), 2);
+
+        cache.clear_novel(novel);
+        assert!(cache.get(novel, voice, 0).is_none());
+        assert_eq!(cache.get(other, voice, 0), Some(segment(0)));
+    }
+}
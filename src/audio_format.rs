@@ -0,0 +1,177 @@
+//! 音频格式协商
+//!
+//! 请求音频时按用户的流量偏好列出客户端能解码的格式（请求体 `formats` 字段和 `Accept` 头），
+//! 服务器从中选择一种返回；客户端根据响应的 `Content-Type`（缺失时根据文件头）记录实际格式，
+//! 并据此选择解码器，不再依赖 rodio 猜测格式。
+
+use serde::{Deserialize, Serialize};
+
+/// 音频格式（容器 + 编码）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    Wav,
+    Flac,
+    OggVorbis,
+    OggOpus,
+    Mp3,
+}
+
+impl AudioFormat {
+    /// MIME 类型（`Accept` 头中使用）
+    pub fn mime(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::OggVorbis => "audio/ogg; codecs=vorbis",
+            AudioFormat::OggOpus => "audio/ogg; codecs=opus",
+            AudioFormat::Mp3 => "audio/mpeg",
+        }
+    }
+
+    /// 编码名称
+    pub fn codec(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "pcm",
+            AudioFormat::Flac => "flac",
+            AudioFormat::OggVorbis => "vorbis",
+            AudioFormat::OggOpus => "opus",
+            AudioFormat::Mp3 => "mp3",
+        }
+    }
+
+    /// 界面显示名称
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "WAV",
+            AudioFormat::Flac => "FLAC",
+            AudioFormat::OggVorbis => "Ogg/Vorbis",
+            AudioFormat::OggOpus => "Ogg/Opus",
+            AudioFormat::Mp3 => "MP3",
+        }
+    }
+
+    /// 当前的 rodio 解码器能否播放
+    ///
+    /// rodio 没有 Opus 解码器，Ogg/Opus 暂不向服务器声明。
+    pub fn is_decodable(self) -> bool {
+        !matches!(self, AudioFormat::OggOpus)
+    }
+
+    /// 根据 `Content-Type` 识别格式，无法识别（如 `application/octet-stream`）时返回 `None`
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let content_type = content_type.to_ascii_lowercase();
+        let mut parts = content_type.split(';').map(str::trim);
+        let mime = parts.next().unwrap_or_default();
+        let opus = parts.any(|param| param.starts_with("codecs=") && param.contains("opus"));
+        match mime {
+            "audio/wav" | "audio/wave" | "audio/x-wav" | "audio/vnd.wave" => Some(AudioFormat::Wav),
+            "audio/flac" | "audio/x-flac" => Some(AudioFormat::Flac),
+            "audio/opus" => Some(AudioFormat::OggOpus),
+            "audio/ogg" | "application/ogg" if opus => Some(AudioFormat::OggOpus),
+            "audio/ogg" | "application/ogg" => Some(AudioFormat::OggVorbis),
+            "audio/mpeg" | "audio/mp3" => Some(AudioFormat::Mp3),
+            _ => None,
+        }
+    }
+
+    /// 根据文件头识别格式
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WAVE" {
+            Some(AudioFormat::Wav)
+        } else if data.starts_with(b"fLaC") {
+            Some(AudioFormat::Flac)
+        } else if data.starts_with(b"OggS") {
+            // 第一个 Ogg 页中的编码标识
+            let head = &data[..data.len().min(64)];
+            if head.windows(8).any(|w| w == b"OpusHead") {
+                Some(AudioFormat::OggOpus)
+            } else {
+                Some(AudioFormat::OggVorbis)
+            }
+        } else if data.starts_with(b"ID3") || (data.len() >= 2 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0) {
+            Some(AudioFormat::Mp3)
+        } else {
+            None
+        }
+    }
+}
+
+/// 音频传输的流量偏好
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BandwidthPreference {
+    /// 优先无压缩的 WAV
+    Original,
+    /// 优先无损压缩的 FLAC
+    #[default]
+    Lossless,
+    /// 优先有损压缩格式，适合 VPN / 移动网络
+    Saver,
+}
+
+impl BandwidthPreference {
+    pub const ALL: [BandwidthPreference; 3] = [
+        BandwidthPreference::Original,
+        BandwidthPreference::Lossless,
+        BandwidthPreference::Saver,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BandwidthPreference::Original => "原始音质 (WAV)",
+            BandwidthPreference::Lossless => "无损压缩 (FLAC)",
+            BandwidthPreference::Saver => "节省流量 (MP3 / Ogg)",
+        }
+    }
+
+    /// 按优先级排列的、客户端能解码的格式
+    pub fn formats(self) -> Vec<AudioFormat> {
+        use AudioFormat::*;
+        let order: &[AudioFormat] = match self {
+            BandwidthPreference::Original => &[Wav, Flac, OggOpus, OggVorbis, Mp3],
+            BandwidthPreference::Lossless => &[Flac, Wav, OggOpus, OggVorbis, Mp3],
+            BandwidthPreference::Saver => &[OggOpus, Mp3, OggVorbis, Flac, Wav],
+        };
+        order.iter().copied().filter(|format| format.is_decodable()).collect()
+    }
+}
+
+/// 按优先级生成 `Accept` 头；音频未就绪时服务器返回 JSON，因此附带低优先级的 `application/json`
+pub fn accept_header(formats: &[AudioFormat]) -> String {
+    let mut accept: Vec<String> = formats
+        .iter()
+        .enumerate()
+        .map(|(i, format)| format!("{}; q={:.1}", format.mime(), (1.0 - 0.1 * i as f32).max(0.2)))
+        .collect();
+    accept.push("application/json; q=0.1".to_string());
+    accept.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_content_types_and_file_headers() {
+        assert_eq!(AudioFormat::from_content_type("audio/wav"), Some(AudioFormat::Wav));
+        assert_eq!(AudioFormat::from_content_type("Audio/OGG; codecs=opus"), Some(AudioFormat::OggOpus));
+        assert_eq!(AudioFormat::from_content_type("audio/ogg"), Some(AudioFormat::OggVorbis));
+        assert_eq!(AudioFormat::from_content_type("application/octet-stream"), None);
+
+        assert_eq!(AudioFormat::sniff(&crate::mock_server::wav_tone(10, 440.0)), Some(AudioFormat::Wav));
+        assert_eq!(AudioFormat::sniff(b"fLaC\0\0\0\x22"), Some(AudioFormat::Flac));
+        assert_eq!(AudioFormat::sniff(b"ID3\x04\0\0"), Some(AudioFormat::Mp3));
+        assert_eq!(AudioFormat::sniff(b"hello"), None);
+    }
+
+    #[test]
+    fn advertises_only_decodable_formats_in_preference_order() {
+        let formats = BandwidthPreference::Saver.formats();
+        assert_eq!(formats, [AudioFormat::Mp3, AudioFormat::OggVorbis, AudioFormat::Flac, AudioFormat::Wav]);
+        assert_eq!(
+            accept_header(&formats[..2]),
+            "audio/mpeg; q=1.0, audio/ogg; codecs=vorbis; q=0.9, application/json; q=0.1"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! 流式音频数据 - 边下载边播放
+//!
+//! 下载线程通过 `push` 写入收到的字节，解码线程通过 `reader()` 读取；
+//! 读取位置超出已下载的数据时阻塞等待，直到新数据到达、下载结束或被取消。
+//! 克隆后共享同一份数据。
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use crate::audio_format::AudioFormat;
+
+#[derive(Default)]
+struct StreamState {
+    data: Vec<u8>,
+    /// 下载已结束（成功或失败）
+    finished: bool,
+    /// 下载失败的原因
+    error: Option<String>,
+    /// 播放方已放弃该音频，下载线程应尽快退出
+    cancelled: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<StreamState>,
+    changed: Condvar,
+}
+
+/// 正在下载的音频数据
+#[derive(Clone, Default)]
+pub struct AudioStream {
+    shared: Arc<Shared>,
+    /// 服务器返回的音频格式（无法识别时为 `None`，由解码器猜测）
+    format: Option<AudioFormat>,
+}
+
+impl AudioStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 已完整下载的音频，格式根据文件头识别
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        let stream = Self::new().with_format(AudioFormat::sniff(&data));
+        {
+            let mut state = stream.lock();
+            state.data = data;
+            state.finished = true;
+        }
+        stream
+    }
+
+    pub fn with_format(mut self, format: Option<AudioFormat>) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn format(&self) -> Option<AudioFormat> {
+        self.format
+    }
+
+    fn lock(&self) -> MutexGuard<'_, StreamState> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update(&self, f: impl FnOnce(&mut StreamState)) {
+        f(&mut self.lock());
+        self.shared.changed.notify_all();
+    }
+
+    /// 追加收到的数据
+    pub fn push(&self, chunk: &[u8]) {
+        self.update(|state| state.data.extend_from_slice(chunk));
+    }
+
+    /// 下载完成
+    pub fn finish(&self) {
+        self.update(|state| state.finished = true);
+    }
+
+    /// 下载失败，读取到已下载数据的末尾后返回错误
+    pub fn fail(&self, error: impl Into<String>) {
+        let error = error.into();
+        self.update(|state| {
+            state.finished = true;
+            state.error = Some(error);
+        });
+    }
+
+    /// 取消下载，等待新数据的读取立即返回错误（已下载的数据仍可读取）
+    pub fn cancel(&self) {
+        self.update(|state| state.cancelled = true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.lock().cancelled
+    }
+
+    /// 已下载的字节数
+    pub fn received(&self) -> usize {
+        self.lock().data.len()
+    }
+
+    /// 等待下载结束，成功时返回完整数据（下载失败或被取消时返回 `None`）
+    pub fn wait_complete(&self) -> Option<Vec<u8>> {
+        let state = self.wait_until(|state| state.finished).ok()?;
+        if state.error.is_some() {
+            return None;
+        }
+        Some(state.data.clone())
+    }
+
+    /// 从头读取的 reader
+    pub fn reader(&self) -> AudioStreamReader {
+        AudioStreamReader {
+            stream: self.clone(),
+            pos: 0,
+        }
+    }
+
+    /// 等待 `ready` 成立；尚未成立时被取消则返回错误
+    fn wait_until(&self, ready: impl Fn(&StreamState) -> bool) -> io::Result<MutexGuard<'_, StreamState>> {
+        let mut state = self.lock();
+        loop {
+            if ready(&state) {
+                return Ok(state);
+            }
+            // 不能使用 `Interrupted`，`read_exact` 等会在该错误上无限重试
+            if state.cancelled {
+                return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "audio stream cancelled"));
+            }
+            state = self.shared.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl fmt::Debug for AudioStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("AudioStream")
+            .field("format", &self.format)
+            .field("received", &state.data.len())
+            .field("finished", &state.finished)
+            .field("cancelled", &state.cancelled)
+            .finish()
+    }
+}
+
+/// `AudioStream` 的阻塞 reader，供解码器使用
+pub struct AudioStreamReader {
+    stream: AudioStream,
+    pos: u64,
+}
+
+impl Read for AudioStreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let pos = self.pos;
+        let state = self
+            .stream
+            .wait_until(|state| state.data.len() as u64 > pos || state.finished)?;
+        let available = state.data.len().saturating_sub(pos as usize);
+        if available == 0 {
+            return match &state.error {
+                Some(error) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, error.clone())),
+                None => Ok(0),
+            };
+        }
+        let n = available.min(buf.len());
+        let start = pos as usize;
+        buf[..n].copy_from_slice(&state.data[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for AudioStreamReader {
+    fn seek(&mut self, target: SeekFrom) -> io::Result<u64> {
+        let pos = match target {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            // 相对末尾的位置需要等下载完成才能确定
+            SeekFrom::End(delta) => {
+                let state = self.stream.wait_until(|state| state.finished)?;
+                (state.data.len() as u64).checked_add_signed(delta)
+            }
+        };
+        let pos = pos.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before start of audio stream"))?;
+        self.pos = pos;
+        Ok(pos)
+    }
+}
@@ -0,0 +1,75 @@
+//! 认证 - 登录 token 的保存与共享
+//!
+//! - 登录: 用户名/密码 → access token + refresh token（`/api/auth/login`）
+//! - 所有 HTTP 请求以及 `/ws/session/{id}`、`/ws/events` 握手都携带 `Authorization: Bearer <token>`
+//! - 收到 401 时用 refresh token 换取新 token（`/api/auth/refresh`）后重试一次
+//! - token 按服务器 profile 保存在配置文件中
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+/// 登录凭据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthToken {
+    /// 登录用户名（用于显示和预填登录框）
+    pub username: String,
+    pub access_token: String,
+    /// 服务端未下发 refresh token 时，access token 过期后需要重新登录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+/// 线程间共享的当前 token
+///
+/// `ApiClient` 的所有克隆和 WebSocket 线程共用同一份，
+/// 任一线程刷新 token 后其他线程立即使用新 token。
+#[derive(Clone, Default)]
+pub struct AuthState {
+    token: Arc<RwLock<Option<AuthToken>>>,
+    /// 串行化 token 刷新，避免多个请求同时收到 401 时重复刷新
+    refresh_lock: Arc<Mutex<()>>,
+    /// token 变化后置位，由主线程写回配置文件
+    changed: Arc<AtomicBool>,
+}
+
+impl AuthState {
+    pub fn new(token: Option<AuthToken>) -> Self {
+        Self {
+            token: Arc::new(RwLock::new(token)),
+            ..Default::default()
+        }
+    }
+
+    pub fn token(&self) -> Option<AuthToken> {
+        self.token.read().ok().and_then(|t| t.clone())
+    }
+
+    pub fn access_token(&self) -> Option<String> {
+        self.token
+            .read()
+            .ok()
+            .and_then(|t| t.as_ref().map(|t| t.access_token.clone()))
+    }
+
+    /// `Authorization` 请求头的值，未登录时为 `None`
+    pub fn bearer(&self) -> Option<String> {
+        self.access_token().map(|token| format!("Bearer {}", token))
+    }
+
+    pub fn set(&self, token: Option<AuthToken>) {
+        if let Ok(mut current) = self.token.write() {
+            *current = token;
+        }
+        self.changed.store(true, Ordering::Release);
+    }
+
+    /// token 自上次调用以来是否变化（登录、刷新、退出、失效）
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::AcqRel)
+    }
+
+    pub(crate) fn refresh_guard(&self) -> MutexGuard<'_, ()> {
+        self.refresh_lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
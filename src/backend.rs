@@ -0,0 +1,275 @@
+//! 后端抽象 - 小说 / 音色 / session / 推理 / 音频操作
+//!
+//! 系统只通过 `RovelBackend` 访问后端，具体实现由服务器 profile 的 `backend` 设置决定:
+//! - `rovel`（默认）: Rovel 服务器，即 `ApiClient`，任务状态通过 WebSocket 推送
+//! - `openai_speech`: OpenAI 兼容的 `/v1/audio/speech` 服务器，即 `OpenAiSpeechBackend`，
+//!   客户端分段并逐段合成，任务状态由后端本地产生
+//!
+//! 登录、分块上传续传等 Rovel 专有功能仍直接使用 `ApiClient`。
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::{
+    ApiClient, ApiError, ApiResult, ChangeVoiceResponse, CloseSessionResponse, NovelResponse, PlayResponse,
+    QueryTaskStatusResponse, RetryObserver, SeekResponse, SegmentAudio, SegmentsResponse, SubmitInferResponse,
+    VoiceResponse, WsEvent,
+};
+use crate::audio_format::AudioFormat;
+use crate::audio_stream::AudioStream;
+use crate::config::AppConfig;
+use crate::offline::{OfflineBackend, OfflineLibrary};
+use crate::openai_speech::{OpenAiSpeechBackend, OpenAiSpeechSettings};
+
+/// 服务器 profile 使用的后端类型
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackendKind {
+    /// Rovel 服务器
+    #[default]
+    Rovel,
+    /// OpenAI 兼容的语音合成服务器
+    OpenaiSpeech(OpenAiSpeechSettings),
+}
+
+impl BackendKind {
+    pub fn is_rovel(&self) -> bool {
+        matches!(self, BackendKind::Rovel)
+    }
+}
+
+/// TTS 后端
+///
+/// 所有方法都是阻塞调用，由 `handle_api_requests` 在工作线程中执行。
+pub trait RovelBackend: Send + Sync {
+    /// 返回在重试前通知 `observer` 的副本
+    fn observed_by(&self, observer: RetryObserver) -> Box<dyn RovelBackend>;
+
+    // ====== Novel ======
+    fn list_novels(&self) -> ApiResult<Vec<NovelResponse>>;
+    fn get_novel(&self, id: Uuid) -> ApiResult<NovelResponse>;
+    fn get_novel_segments(&self, novel_id: Uuid, start: Option<usize>, limit: Option<usize>) -> ApiResult<SegmentsResponse>;
+    fn upload_novel(
+        &self,
+        title: &str,
+        file_path: &Path,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(u64, u64),
+    ) -> ApiResult<NovelResponse>;
+    fn delete_novel(&self, id: Uuid) -> ApiResult<()>;
+
+    // ====== Voice ======
+    fn list_voices(&self) -> ApiResult<Vec<VoiceResponse>>;
+    fn upload_voice(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        file_path: &Path,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(u64, u64),
+    ) -> ApiResult<VoiceResponse>;
+    fn delete_voice(&self, id: Uuid) -> ApiResult<()>;
+
+    // ====== Session ======
+    fn play(&self, novel_id: Uuid, voice_id: Uuid, start_index: u32) -> ApiResult<PlayResponse>;
+    fn seek(&self, session_id: &str, segment_index: u32) -> ApiResult<SeekResponse>;
+    fn change_voice(&self, session_id: &str, voice_id: Uuid) -> ApiResult<ChangeVoiceResponse>;
+    fn close_session(&self, session_id: &str) -> ApiResult<CloseSessionResponse>;
+
+    // ====== Inference / Audio ======
+    fn submit_infer(&self, session_id: &str, segment_indices: Vec<u32>) -> ApiResult<SubmitInferResponse>;
+    fn query_task_status(&self, task_ids: Vec<String>) -> ApiResult<QueryTaskStatusResponse>;
+    /// `formats` 为客户端能解码的格式（按优先级），后端可以忽略。
+    /// 音频尚未生成时返回 `ApiError::NotReady`
+    fn get_audio(&self, novel_id: Uuid, segment_index: u32, voice_id: Uuid, formats: &[AudioFormat]) -> ApiResult<Vec<u8>>;
+    /// 以流的方式获取音频，默认完整下载后再返回
+    fn open_audio_stream(
+        &self,
+        novel_id: Uuid,
+        segment_index: u32,
+        voice_id: Uuid,
+        formats: &[AudioFormat],
+    ) -> ApiResult<AudioStream> {
+        self.get_audio(novel_id, segment_index, voice_id, formats).map(AudioStream::from_bytes)
+    }
+    /// 批量获取已生成的音频，未生成的段落不包含在结果中。默认逐段获取
+    fn get_audio_batch(
+        &self,
+        novel_id: Uuid,
+        voice_id: Uuid,
+        segment_indices: &[u32],
+        formats: &[AudioFormat],
+    ) -> ApiResult<Vec<SegmentAudio>> {
+        get_audio_each(self, novel_id, voice_id, segment_indices, formats)
+    }
+
+    /// 是否通过 `/ws/session/{id}`、`/ws/events` 推送事件
+    fn uses_websocket(&self) -> bool {
+        true
+    }
+
+    /// 取走后端本地产生的事件（不使用 WebSocket 的后端通过它上报任务状态）
+    fn take_events(&self) -> Vec<WsEvent> {
+        Vec::new()
+    }
+}
+
+impl RovelBackend for ApiClient {
+    fn observed_by(&self, observer: RetryObserver) -> Box<dyn RovelBackend> {
+        Box::new(self.clone().with_retry_observer(move |retry| observer(retry)))
+    }
+
+    fn list_novels(&self) -> ApiResult<Vec<NovelResponse>> {
+        ApiClient::list_novels(self)
+    }
+
+    fn get_novel(&self, id: Uuid) -> ApiResult<NovelResponse> {
+        ApiClient::get_novel(self, id)
+    }
+
+    fn get_novel_segments(&self, novel_id: Uuid, start: Option<usize>, limit: Option<usize>) -> ApiResult<SegmentsResponse> {
+        ApiClient::get_novel_segments(self, novel_id, start, limit)
+    }
+
+    fn upload_novel(
+        &self,
+        title: &str,
+        file_path: &Path,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(u64, u64),
+    ) -> ApiResult<NovelResponse> {
+        ApiClient::upload_novel(self, title, file_path, cancel, on_progress)
+    }
+
+    fn delete_novel(&self, id: Uuid) -> ApiResult<()> {
+        ApiClient::delete_novel(self, id)
+    }
+
+    fn list_voices(&self) -> ApiResult<Vec<VoiceResponse>> {
+        ApiClient::list_voices(self)
+    }
+
+    fn upload_voice(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        file_path: &Path,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(u64, u64),
+    ) -> ApiResult<VoiceResponse> {
+        ApiClient::upload_voice(self, name, description, file_path, cancel, on_progress)
+    }
+
+    fn delete_voice(&self, id: Uuid) -> ApiResult<()> {
+        ApiClient::delete_voice(self, id)
+    }
+
+    fn play(&self, novel_id: Uuid, voice_id: Uuid, start_index: u32) -> ApiResult<PlayResponse> {
+        ApiClient::play(self, novel_id, voice_id, start_index)
+    }
+
+    fn seek(&self, session_id: &str, segment_index: u32) -> ApiResult<SeekResponse> {
+        ApiClient::seek(self, session_id, segment_index)
+    }
+
+    fn change_voice(&self, session_id: &str, voice_id: Uuid) -> ApiResult<ChangeVoiceResponse> {
+        ApiClient::change_voice(self, session_id, voice_id)
+    }
+
+    fn close_session(&self, session_id: &str) -> ApiResult<CloseSessionResponse> {
+        ApiClient::close_session(self, session_id)
+    }
+
+    fn submit_infer(&self, session_id: &str, segment_indices: Vec<u32>) -> ApiResult<SubmitInferResponse> {
+        ApiClient::submit_infer(self, session_id, segment_indices)
+    }
+
+    fn query_task_status(&self, task_ids: Vec<String>) -> ApiResult<QueryTaskStatusResponse> {
+        ApiClient::query_task_status(self, task_ids)
+    }
+
+    fn get_audio(&self, novel_id: Uuid, segment_index: u32, voice_id: Uuid, formats: &[AudioFormat]) -> ApiResult<Vec<u8>> {
+        ApiClient::get_audio(self, novel_id, segment_index, voice_id, formats)
+    }
+
+    fn open_audio_stream(
+        &self,
+        novel_id: Uuid,
+        segment_index: u32,
+        voice_id: Uuid,
+        formats: &[AudioFormat],
+    ) -> ApiResult<AudioStream> {
+        ApiClient::open_audio_stream(self, novel_id, segment_index, voice_id, formats)
+    }
+
+    fn get_audio_batch(
+        &self,
+        novel_id: Uuid,
+        voice_id: Uuid,
+        segment_indices: &[u32],
+        formats: &[AudioFormat],
+    ) -> ApiResult<Vec<SegmentAudio>> {
+        ApiClient::get_audio_batch(self, novel_id, voice_id, segment_indices, formats)
+    }
+}
+
+/// 逐段获取音频，跳过尚未生成的段落
+pub(crate) fn get_audio_each(
+    backend: &(impl RovelBackend + ?Sized),
+    novel_id: Uuid,
+    voice_id: Uuid,
+    segment_indices: &[u32],
+    formats: &[AudioFormat],
+) -> ApiResult<Vec<SegmentAudio>> {
+    let mut segments = Vec::new();
+    for &segment_index in segment_indices {
+        match backend.get_audio(novel_id, segment_index, voice_id, formats) {
+            Ok(data) => segments.push(SegmentAudio { segment_index, format: AudioFormat::sniff(&data), data }),
+            Err(ApiError::NotReady) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(segments)
+}
+
+/// 当前 profile 的后端资源（克隆后共享同一个后端）
+#[derive(Resource, Clone)]
+pub struct Backend(Arc<dyn RovelBackend>);
+
+impl Backend {
+    pub fn new(backend: impl RovelBackend + 'static) -> Self {
+        Self(Arc::new(backend))
+    }
+
+    /// 按活动 profile 的 `backend` 设置创建后端，Rovel 服务器直接使用 `api_client`。
+    /// 已下载的离线副本优先从 `offline` 读取
+    pub fn from_config(config: &AppConfig, api_client: &ApiClient, offline: &OfflineLibrary) -> Self {
+        let profile = config.active();
+        let backend = match &profile.backend {
+            BackendKind::Rovel => Self::new(api_client.clone()),
+            BackendKind::OpenaiSpeech(settings) => {
+                tracing::info!("Using OpenAI-compatible speech backend at {}", profile.server_url);
+                Self::new(OpenAiSpeechBackend::from_profile(profile, settings, &config.timeouts))
+            }
+        };
+        Self::new(OfflineBackend::new(backend, offline.clone()))
+    }
+}
+
+impl From<Box<dyn RovelBackend>> for Backend {
+    fn from(backend: Box<dyn RovelBackend>) -> Self {
+        Self(Arc::from(backend))
+    }
+}
+
+impl std::ops::Deref for Backend {
+    type Target = dyn RovelBackend;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
@@ -0,0 +1,173 @@
+//! 书签和笔记 - 在播放器的段落列表中收藏段落并附加笔记
+//!
+//! 保存在 `<配置目录>/rovel-desk/bookmarks/<服务器地址的指纹>.json`，按小说分组。
+//! 书签保存段落文本的副本，导出 Markdown 时无需再向服务器请求段落。
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::config::app_config_dir;
+use crate::fs_util::write_atomic;
+use crate::upload_state::fingerprint;
+
+/// 一个书签
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub segment_index: u32,
+    /// 段落文本
+    pub text: String,
+    #[serde(default)]
+    pub note: String,
+    /// 创建时间（Unix 秒）
+    pub created_at: u64,
+}
+
+/// 书签资源（切换服务器时替换）
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Bookmarks {
+    /// `None` 时只保存在内存中
+    path: Option<PathBuf>,
+    /// 每本小说的书签，按段落顺序排列
+    novels: HashMap<Uuid, Vec<Bookmark>>,
+}
+
+impl Bookmarks {
+    /// 读取书签文件，文件不存在或损坏时从空白开始
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let novels = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| match serde_json::from_str(&content) {
+                Ok(novels) => Some(novels),
+                Err(e) => {
+                    tracing::warn!("Ignoring corrupt bookmarks {:?}: {}", path, e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self { path, novels }
+    }
+
+    /// 默认位置：`<配置目录>/rovel-desk/bookmarks/<服务器地址的指纹>.json`
+    pub fn default_path(server_url: &str) -> Option<PathBuf> {
+        app_config_dir()
+            .map(|dir| dir.join("bookmarks").join(format!("{:016x}.json", fingerprint(server_url.as_bytes()))))
+    }
+
+    pub fn for_novel(&self, novel_id: Uuid) -> &[Bookmark] {
+        self.novels.get(&novel_id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn contains(&self, novel_id: Uuid, segment_index: u32) -> bool {
+        self.for_novel(novel_id).iter().any(|b| b.segment_index == segment_index)
+    }
+
+    /// 添加或取消书签
+    pub fn toggle(&mut self, novel_id: Uuid, segment_index: u32, text: &str) {
+        let bookmarks = self.novels.entry(novel_id).or_default();
+        match bookmarks.binary_search_by_key(&segment_index, |b| b.segment_index) {
+            Ok(pos) => {
+                bookmarks.remove(pos);
+                if bookmarks.is_empty() {
+                    self.novels.remove(&novel_id);
+                }
+            }
+            Err(pos) => {
+                let created_at = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                bookmarks.insert(pos, Bookmark { segment_index, text: text.to_string(), note: String::new(), created_at });
+            }
+        }
+        self.save();
+    }
+
+    pub fn remove(&mut self, novel_id: Uuid, segment_index: u32) {
+        if self.contains(novel_id, segment_index) {
+            self.toggle(novel_id, segment_index, "");
+        }
+    }
+
+    /// 修改书签的笔记
+    pub fn set_note(&mut self, novel_id: Uuid, segment_index: u32, note: &str) {
+        let bookmark = self
+            .novels
+            .get_mut(&novel_id)
+            .and_then(|bookmarks| bookmarks.iter_mut().find(|b| b.segment_index == segment_index));
+        if let Some(bookmark) = bookmark {
+            bookmark.note = note.trim().to_string();
+            self.save();
+        }
+    }
+
+    /// 删除小说的全部书签
+    pub fn remove_novel(&mut self, novel_id: Uuid) {
+        if self.novels.remove(&novel_id).is_some() {
+            self.save();
+        }
+    }
+
+    /// 把小说的书签导出为 Markdown：段落文本作为引用，笔记跟在引用之后
+    pub fn to_markdown(&self, novel_id: Uuid, novel_title: &str) -> String {
+        let mut out = format!("# 《{}》书签\n", novel_title);
+        for bookmark in self.for_novel(novel_id) {
+            let _ = write!(out, "\n## 第 {} 段\n\n", bookmark.segment_index + 1);
+            for line in bookmark.text.lines().filter(|line| !line.trim().is_empty()) {
+                let _ = writeln!(out, "> {}", line.trim());
+            }
+            if !bookmark.note.is_empty() {
+                let _ = write!(out, "\n{}\n", bookmark.note);
+            }
+        }
+        out
+    }
+
+    pub fn export_markdown(&self, novel_id: Uuid, novel_title: &str, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_markdown(novel_id, novel_title))
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else { return };
+        let result = (|| -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            write_atomic(path, &serde_json::to_vec_pretty(&self.novels)?)
+        })();
+        if let Err(e) = result {
+            tracing::warn!("Failed to save bookmarks {:?}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_util::TempDir;
+
+    #[test]
+    fn bookmarks_persist_and_export_markdown_in_segment_order() {
+        let dir = TempDir::new("bookmarks");
+        let path = dir.join("bookmarks.json");
+        let novel = Uuid::new_v4();
+
+        let mut bookmarks = Bookmarks::open(Some(path.clone()));
+        bookmarks.toggle(novel, 9, "最后一段");
+        bookmarks.toggle(novel, 2, "他说：\n“走吧。”");
+        bookmarks.toggle(novel, 5, "要取消的段落");
+        bookmarks.toggle(novel, 5, "要取消的段落");
+        bookmarks.set_note(novel, 2, " 伏笔 ");
+
+        let reopened = Bookmarks::open(Some(path.clone()));
+        assert!(reopened.contains(novel, 2) && !reopened.contains(novel, 5));
+        assert_eq!(
+            reopened.to_markdown(novel, "小说"),
+            "# 《小说》书签\n\n## 第 3 段\n\n> 他说：\n> “走吧。”\n\n伏笔\n\n## 第 10 段\n\n> 最后一段\n"
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! Application configuration - 服务器配置档案
+//!
+//! 配置来源（优先级从低到高）:
+//! - 配置文件: `<配置目录>/rovel-desk/config.json`
+//! - 环境变量: `ROVEL_CONFIG` / `ROVEL_PROFILE` / `ROVEL_SERVER_URL`
+//! - 命令行参数: `--config <path>` / `--profile <name>` / `--server <url>`
+//!
+//! 每个 profile 保存服务器根地址（如 `http://192.168.2.31:5060` 或 `https://rovel.example.com`）、
+//! 该服务器的登录 token 和 TLS 信任设置，HTTP API 地址（`/api`）和 WebSocket 地址
+//! （`/ws` 或 `wss://.../ws`）由此推导。
+//!
+//! profile 的 `backend` 指定服务器类型，缺省为 Rovel 服务器；也可以指向 OpenAI 兼容的
+//! 语音合成服务器，例如 `"backend": {"type": "openai_speech", "model": "tts-1"}`。
+
+use anyhow::Result;
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::api::{ChunkedUploadPolicy, HttpTimeouts, RetryPolicy};
+use crate::audio::SegmentTransition;
+use crate::audio_cache::AudioCachePolicy;
+use crate::audio_format::BandwidthPreference;
+use crate::auth::AuthToken;
+use crate::backend::BackendKind;
+use crate::export::ExportSettings;
+use crate::loudness::VolumeSettings;
+use crate::tls::TlsSettings;
+
+/// 未配置时使用的默认服务器地址
+pub const DEFAULT_SERVER_URL: &str = "http://192.168.2.31:5060";
+
+/// 默认 profile 名称
+const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// 命令行 / 环境变量指定服务器地址时生成的临时 profile 名称
+const OVERRIDE_PROFILE_NAME: &str = "override";
+
+/// 配置文件名
+const CONFIG_FILE_NAME: &str = "config.json";
+
+// ============================================================================
+// Server Profile
+// ============================================================================
+
+/// 服务器配置档案
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ServerProfile {
+    /// 档案名称（唯一）
+    pub name: String,
+    /// 服务器根地址，例如 `http://192.168.2.31:5060`
+    pub server_url: String,
+    /// 登录 token（未登录时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthToken>,
+    /// https / wss 的证书信任设置
+    #[serde(default, skip_serializing_if = "TlsSettings::is_empty")]
+    pub tls: TlsSettings,
+    /// 服务器类型（Rovel 服务器或 OpenAI 兼容语音合成服务器）
+    #[serde(default, skip_serializing_if = "BackendKind::is_rovel")]
+    pub backend: BackendKind,
+    /// 临时档案（来自命令行/环境变量），不写入配置文件
+    #[serde(skip)]
+    pub transient: bool,
+}
+
+impl ServerProfile {
+    pub fn new(name: impl Into<String>, server_url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            server_url: normalize_server_url(&server_url.into()),
+            auth: None,
+            tls: TlsSettings::default(),
+            backend: BackendKind::default(),
+            transient: false,
+        }
+    }
+
+    /// HTTP API 基础地址，例如 `http://host:port/api`
+    pub fn api_base_url(&self) -> String {
+        format!("{}/api", self.server_url)
+    }
+
+    /// WebSocket 基础地址，例如 `ws://host:port/ws`
+    pub fn ws_base_url(&self) -> String {
+        let ws_root = if let Some(rest) = self.server_url.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = self.server_url.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            self.server_url.clone()
+        };
+        format!("{}/ws", ws_root)
+    }
+}
+
+/// 规范化服务器地址：去除首尾空白和末尾的 `/`，缺省协议时补全 `http://`
+pub fn normalize_server_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    }
+}
+
+// ============================================================================
+// App Config
+// ============================================================================
+
+/// 应用配置资源
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+    /// 当前使用的 profile 名称
+    pub active_profile: String,
+    /// 所有服务器 profile
+    pub profiles: Vec<ServerProfile>,
+    /// HTTP 超时（按操作类型）
+    #[serde(default)]
+    pub timeouts: HttpTimeouts,
+    /// 幂等 API 请求的重试策略
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// 大文件分块上传
+    #[serde(default)]
+    pub chunked_upload: ChunkedUploadPolicy,
+    /// 音频传输的流量偏好（决定向服务器声明的格式优先级）
+    #[serde(default)]
+    pub bandwidth: BandwidthPreference,
+    /// 音频磁盘缓存
+    #[serde(default)]
+    pub audio_cache: AudioCachePolicy,
+    /// 有声书导出设置
+    #[serde(default)]
+    pub export: ExportSettings,
+    /// 主音量、静音和响度均衡
+    #[serde(default)]
+    pub volume: VolumeSettings,
+    /// 段落之间的衔接方式
+    #[serde(default)]
+    pub segment_transition: SegmentTransition,
+    /// 配置文件路径（运行时确定，不序列化）
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            active_profile: DEFAULT_PROFILE_NAME.to_string(),
+            profiles: vec![ServerProfile::new(DEFAULT_PROFILE_NAME, DEFAULT_SERVER_URL)],
+            timeouts: HttpTimeouts::default(),
+            retry: RetryPolicy::default(),
+            chunked_upload: ChunkedUploadPolicy::default(),
+            bandwidth: BandwidthPreference::default(),
+            audio_cache: AudioCachePolicy::default(),
+            export: ExportSettings::default(),
+            volume: VolumeSettings::default(),
+            segment_transition: SegmentTransition::default(),
+            path: None,
+        }
+    }
+}
+
+/// 命令行 / 环境变量覆盖项
+#[derive(Debug, Default)]
+struct ConfigOverrides {
+    config_path: Option<PathBuf>,
+    profile: Option<String>,
+    server_url: Option<String>,
+}
+
+impl ConfigOverrides {
+    /// 读取环境变量，再用命令行参数覆盖
+    fn collect() -> Self {
+        let mut overrides = Self {
+            config_path: std::env::var_os("ROVEL_CONFIG").map(PathBuf::from),
+            profile: std::env::var("ROVEL_PROFILE").ok().filter(|s| !s.is_empty()),
+            server_url: std::env::var("ROVEL_SERVER_URL").ok().filter(|s| !s.is_empty()),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let (key, inline_value) = match arg.split_once('=') {
+                Some((k, v)) => (k.to_string(), Some(v.to_string())),
+                None => (arg.clone(), None),
+            };
+            let mut value = || inline_value.clone().or_else(|| args.next());
+            match key.as_str() {
+                "--config" => overrides.config_path = value().map(PathBuf::from),
+                "--profile" => overrides.profile = value(),
+                "--server" => overrides.server_url = value(),
+                _ => {}
+            }
+        }
+
+        overrides
+    }
+}
+
+impl AppConfig {
+    /// 加载配置：配置文件 → 环境变量 → 命令行参数
+    ///
+    /// 配置文件不存在或解析失败时使用默认配置，不会中断启动。
+    pub fn load() -> Self {
+        let overrides = ConfigOverrides::collect();
+        let path = overrides.config_path.clone().or_else(default_config_path);
+
+        let mut config = match &path {
+            Some(p) if p.exists() => match Self::load_from(p) {
+                Ok(config) => {
+                    tracing::info!("Loaded config from {:?}", p);
+                    config
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to load config {:?}: {}, using defaults", p, e);
+                    Self::default()
+                }
+            },
+            _ => Self::default(),
+        };
+        config.path = path;
+        config.ensure_valid();
+        config.apply_overrides(overrides);
+
+        tracing::info!(
+            "Using server profile '{}' ({})",
+            config.active_profile,
+            config.active().server_url
+        );
+        config
+    }
+
+    /// 从指定文件读取配置
+    pub fn load_from(path: &std::path::Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let mut config: Self = serde_json::from_str(&text)?;
+        for profile in config.profiles.iter_mut() {
+            profile.server_url = normalize_server_url(&profile.server_url);
+        }
+        Ok(config)
+    }
+
+    /// 保存配置到文件（临时 profile 不会写入）
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Err(anyhow::anyhow!("No config path available"));
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut persisted = self.clone();
+        persisted.profiles.retain(|p| !p.transient);
+        if !persisted.profiles.iter().any(|p| p.name == persisted.active_profile) {
+            // 当前是临时 profile，落盘时保留原来的第一个 profile 为活动项
+            if let Some(first) = persisted.profiles.first() {
+                persisted.active_profile = first.name.clone();
+            }
+        }
+
+        let text = serde_json::to_string_pretty(&persisted)?;
+        std::fs::write(path, text)?;
+        tracing::info!("Config saved to {:?}", path);
+        Ok(())
+    }
+
+    /// 当前活动的 profile
+    pub fn active(&self) -> &ServerProfile {
+        self.profiles
+            .iter()
+            .find(|p| p.name == self.active_profile)
+            .or_else(|| self.profiles.first())
+            .expect("AppConfig always has at least one profile")
+    }
+
+    /// 按名称查找 profile
+    pub fn profile(&self, name: &str) -> Option<&ServerProfile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// 切换活动 profile，返回是否成功
+    pub fn set_active(&mut self, name: &str) -> bool {
+        if self.profile(name).is_some() {
+            self.active_profile = name.to_string();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 添加或更新 profile
+    pub fn upsert_profile(&mut self, profile: ServerProfile) {
+        if let Some(existing) = self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            *existing = profile;
+        } else {
+            self.profiles.push(profile);
+        }
+    }
+
+    /// 添加新 profile，或把编辑后的地址和 TLS 设置合并到同名 profile（保留登录 token 和服务器类型）
+    ///
+    /// 地址变化时清除旧 token，避免把它发给另一台服务器。返回活动 profile 的连接参数是否发生变化。
+    pub fn edit_profile(&mut self, name: &str, server_url: &str, tls: TlsSettings) -> bool {
+        let server_url = normalize_server_url(server_url);
+        let Some(existing) = self.profiles.iter_mut().find(|p| p.name == name) else {
+            let mut profile = ServerProfile::new(name, server_url);
+            profile.tls = tls;
+            self.profiles.push(profile);
+            return false;
+        };
+        let url_changed = existing.server_url != server_url;
+        let changed = url_changed || existing.tls != tls;
+        if url_changed {
+            existing.auth = None;
+        }
+        existing.server_url = server_url;
+        existing.tls = tls;
+        changed && name == self.active_profile
+    }
+
+    /// 更新指定 profile 的登录 token，返回是否需要写回配置文件
+    pub fn set_profile_auth(&mut self, name: &str, auth: Option<AuthToken>) -> bool {
+        match self.profiles.iter_mut().find(|p| p.name == name) {
+            Some(profile) if profile.auth != auth => {
+                profile.auth = auth;
+                !profile.transient
+            }
+            _ => false,
+        }
+    }
+
+    /// 删除 profile（不能删除当前活动的 profile），返回是否成功
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        if name == self.active_profile {
+            return false;
+        }
+        let before = self.profiles.len();
+        self.profiles.retain(|p| p.name != name);
+        self.profiles.len() != before
+    }
+
+    /// 是否为保留的 profile 名称（由命令行 / 环境变量覆盖占用，不能手动添加）
+    pub fn is_reserved_profile_name(name: &str) -> bool {
+        name == OVERRIDE_PROFILE_NAME
+    }
+
+    /// 保证至少有一个 profile 且活动 profile 存在
+    fn ensure_valid(&mut self) {
+        if self.profiles.is_empty() {
+            self.profiles.push(ServerProfile::new(DEFAULT_PROFILE_NAME, DEFAULT_SERVER_URL));
+        }
+        if self.profile(&self.active_profile).is_none() {
+            self.active_profile = self.profiles[0].name.clone();
+        }
+    }
+
+    fn apply_overrides(&mut self, overrides: ConfigOverrides) {
+        if let Some(name) = overrides.profile {
+            if !self.set_active(&name) {
+                tracing::warn!("Unknown server profile '{}', keeping '{}'", name, self.active_profile);
+            }
+        }
+        if let Some(url) = overrides.server_url {
+            let mut profile = ServerProfile::new(OVERRIDE_PROFILE_NAME, url);
+            profile.transient = true;
+            self.upsert_profile(profile);
+            self.active_profile = OVERRIDE_PROFILE_NAME.to_string();
+        }
+    }
+}
+
+// ============================================================================
+// 配置目录
+// ============================================================================
+
+/// 应用配置目录: `<平台配置目录>/rovel-desk`
+pub fn app_config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+    };
+    base.map(|b| b.join("rovel-desk"))
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    app_config_dir().map(|d| d.join(CONFIG_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openai_speech::OpenAiSpeechSettings;
+
+    #[test]
+    fn edit_profile_keeps_auth_and_backend() {
+        let mut config = AppConfig::default();
+        let mut profile = ServerProfile::new("home", "http://10.0.0.2:5060");
+        profile.auth = Some(AuthToken {
+            username: "alice".to_string(),
+            access_token: "token".to_string(),
+            refresh_token: None,
+        });
+        profile.backend = BackendKind::OpenaiSpeech(OpenAiSpeechSettings::default());
+        config.upsert_profile(profile);
+
+        // 只改 TLS：保留 token 和服务器类型，非活动 profile 不需要重连
+        let tls = TlsSettings { ca_bundle: None, cert_sha256: Some("ab".repeat(32)) };
+        assert!(!config.edit_profile("home", "10.0.0.2:5060/", tls.clone()));
+        let home = config.profile("home").unwrap();
+        assert!(home.auth.is_some());
+        assert!(matches!(home.backend, BackendKind::OpenaiSpeech(_)));
+        assert_eq!(home.tls, tls);
+
+        // 活动 profile 换地址：需要重连，旧 token 不再发给新服务器
+        config.set_active("home");
+        assert!(config.edit_profile("home", "http://10.0.0.3:5060", tls.clone()));
+        let home = config.profile("home").unwrap();
+        assert_eq!(home.server_url, "http://10.0.0.3:5060");
+        assert!(home.auth.is_none());
+        assert!(matches!(home.backend, BackendKind::OpenaiSpeech(_)));
+        assert!(!config.edit_profile("home", "http://10.0.0.3:5060", tls));
+
+        assert!(AppConfig::is_reserved_profile_name(OVERRIDE_PROFILE_NAME));
+        assert!(!AppConfig::is_reserved_profile_name("home"));
+    }
+}
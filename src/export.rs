@@ -0,0 +1,624 @@
+//! 有声书导出 - 把小说各段的音频拼接为 WAV 文件
+//!
+//! - 章节由段落文本识别（「第N章」「序章」「Chapter N」等独立成段的标题），
+//!   没有识别到章节时整本小说作为一章
+//! - 段落之间插入可配置时长的静音
+//! - 单文件导出在 WAV 中写入 `cue ` / `LIST adtl` 章节标记和 `LIST INFO` 标题；
+//!   按章导出时每章一个 WAV，标题为章节名
+//! - 所有段落统一转换为第一段的采样率和声道数
+//! - 可同时在音频旁写出 SRT / LRC / VTT 字幕（见 `subtitles`），也可以只导出字幕
+//! - 先写入输出目录下的临时目录，全部完成后才移到输出目录；出错或取消时删除临时目录
+//!
+//! 音频通过后端获取，尚未生成的段落先提交推理任务（见 `offline::fetch_audio_in_batches`）。
+//! 依赖中没有 AAC / MP3 / FLAC 编码器，暂不支持 M4B / MP3 / FLAC 输出。
+
+use rodio::source::UniformSourceIterator;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use uuid::Uuid;
+
+use crate::api::{ApiError, ApiResult, SegmentAudio, SegmentResponse};
+use crate::audio::open_decoder;
+use crate::audio_format::AudioFormat;
+use crate::backend::RovelBackend;
+use crate::offline::{fetch_all_segments, fetch_audio_in_batches};
+use crate::subtitles::{append_extension, SubtitleFormat, SubtitleTrack};
+
+/// 章节标题的最大字数，超过的段落视为正文
+const MAX_HEADING_CHARS: usize = 30;
+
+/// WAV `data` 块的最大字节数（RIFF 块大小为 32 位，留出章节标记的空间）
+const MAX_WAV_DATA_BYTES: u64 = u32::MAX as u64 - 64 * 1024;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// 单个 WAV 文件，带章节标记
+    #[default]
+    WavWithChapters,
+    /// 每章一个 WAV 文件
+    WavPerChapter,
+    /// 只导出字幕，时间轴与单文件 WAV 相同
+    SubtitlesOnly,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] =
+        [ExportFormat::WavWithChapters, ExportFormat::WavPerChapter, ExportFormat::SubtitlesOnly];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::WavWithChapters => "单个 WAV（章节标记）",
+            ExportFormat::WavPerChapter => "每章一个 WAV",
+            ExportFormat::SubtitlesOnly => "仅字幕",
+        }
+    }
+}
+
+/// 导出设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    /// 段落之间的静音（毫秒）
+    pub silence_ms: u32,
+    /// 随音频一起写出的字幕格式
+    pub subtitles: Vec<SubtitleFormat>,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::default(),
+            silence_ms: 600,
+            subtitles: Vec::new(),
+        }
+    }
+}
+
+/// 章节：从 `start_segment` 开始到下一章之前
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    pub start_segment: u32,
+}
+
+/// 段落是否为章节标题，返回标题文本
+pub(crate) fn chapter_heading(text: &str) -> Option<String> {
+    let line = text.trim().lines().next()?.trim();
+    if line.is_empty() || line.chars().count() > MAX_HEADING_CHARS {
+        return None;
+    }
+
+    const SPECIAL: [&str; 7] = ["序章", "序言", "楔子", "引子", "尾声", "后记", "番外"];
+    if SPECIAL.iter().any(|prefix| line.starts_with(prefix)) {
+        return Some(line.to_string());
+    }
+
+    // 第十二章 / 第3回 / 第一卷
+    if let Some(rest) = line.strip_prefix('第') {
+        let numeral_len = rest
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || "零〇一二三四五六七八九十百千万两".contains(*c))
+            .count();
+        let marker = rest.chars().nth(numeral_len);
+        if numeral_len > 0 && marker.is_some_and(|c| "章回节卷集部篇".contains(c)) {
+            return Some(line.to_string());
+        }
+    }
+
+    let lower = line.to_lowercase();
+    let english = ["chapter ", "prologue", "epilogue"];
+    english.iter().any(|prefix| lower.starts_with(prefix)).then(|| line.to_string())
+}
+
+/// 根据段落文本划分章节；开头没有标题的段落归入以小说标题命名的第一章
+pub fn detect_chapters(novel_title: &str, segments: &[SegmentResponse]) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = segments
+        .iter()
+        .filter_map(|segment| {
+            chapter_heading(&segment.content).map(|title| Chapter { title, start_segment: segment.index as u32 })
+        })
+        .collect();
+    // 服务端的段落编号不一定从 0 开始
+    let first = segments.first().map_or(0, |segment| segment.index as u32);
+    if chapters.first().is_none_or(|chapter| chapter.start_segment > first) {
+        chapters.insert(0, Chapter { title: novel_title.to_string(), start_segment: first });
+    }
+    chapters
+}
+
+/// 导出结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportSummary {
+    /// 单文件导出时为文件路径，按章导出时为目录，仅字幕时为第一个字幕文件
+    pub output: PathBuf,
+    pub chapters: usize,
+}
+
+/// 把小说导出到 `output_dir`，`on_progress(done, total)` 报告已处理的段数
+#[allow(clippy::too_many_arguments)]
+pub fn export_audiobook(
+    backend: &dyn RovelBackend,
+    novel_id: Uuid,
+    voice_id: Uuid,
+    formats: &[AudioFormat],
+    settings: &ExportSettings,
+    output_dir: &Path,
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(usize, usize),
+) -> ApiResult<ExportSummary> {
+    if settings.format == ExportFormat::SubtitlesOnly && settings.subtitles.is_empty() {
+        return Err(ApiError::Invalid("请至少选择一种字幕格式".to_string()));
+    }
+    let novel = backend.get_novel(novel_id)?;
+    let voice_name = backend
+        .list_voices()
+        .ok()
+        .and_then(|voices| voices.into_iter().find(|v| v.id == voice_id))
+        .map(|v| v.name);
+    let segments = fetch_all_segments(backend, novel_id, cancel)?;
+    if segments.is_empty() {
+        return Err(ApiError::Invalid("小说没有段落".to_string()));
+    }
+    let chapters = detect_chapters(&novel.title, &segments);
+    let total = segments.len();
+    on_progress(0, total);
+
+    let name = sanitize_file_name(&novel.title);
+    let output_name = match settings.format {
+        ExportFormat::WavWithChapters => append_extension(Path::new(&name), "wav"),
+        ExportFormat::WavPerChapter => PathBuf::from(&name),
+        ExportFormat::SubtitlesOnly => append_extension(Path::new(&name), settings.subtitles[0].extension()),
+    };
+    let staging = StagingDir::create(output_dir, &name).map_err(io_error)?;
+    let base = staging.path.join(&name);
+    let output = staging.path.join(&output_name);
+    if settings.format == ExportFormat::WavPerChapter {
+        std::fs::create_dir_all(&output).map_err(io_error)?;
+    }
+
+    let mut writer: Option<WavWriter> = None;
+    // 当前文件的字幕和不含扩展名的路径
+    let mut track: Option<(PathBuf, SubtitleTrack)> = None;
+    // 当前文件的时间轴位置（帧），仅字幕时没有 writer 也要累加
+    let mut position = 0u64;
+    let mut spec: Option<(u16, u32)> = None;
+    let mut chapter_index = 0usize;
+    let mut done = 0usize;
+    let indices: Vec<u32> = segments.iter().map(|s| s.index as u32).collect();
+    fetch_audio_in_batches(backend, novel_id, voice_id, &indices, formats, cancel, &mut |audio: SegmentAudio| {
+        let index = audio.segment_index;
+        let samples = decode(&audio)?;
+        let (channels, sample_rate) = *spec.get_or_insert((samples.channels, samples.sample_rate));
+
+        let starts_chapter = chapters.get(chapter_index).is_some_and(|c| c.start_segment == index);
+        if starts_chapter {
+            let chapter = &chapters[chapter_index];
+            chapter_index += 1;
+            match settings.format {
+                ExportFormat::WavWithChapters | ExportFormat::SubtitlesOnly => {
+                    if track.is_none() {
+                        track = Some((base.clone(), SubtitleTrack::new(novel.title.clone(), voice_name.clone())));
+                    }
+                    if settings.format == ExportFormat::WavWithChapters && writer.is_none() {
+                        let mut info = vec![(*b"INAM", novel.title.clone()), (*b"ISFT", "rovel-desk".to_string())];
+                        if let Some(name) = &voice_name {
+                            info.push((*b"IART", name.clone()));
+                        }
+                        writer = Some(WavWriter::create(&output, channels, sample_rate, &info).map_err(io_error)?);
+                    }
+                    if let Some(writer) = writer.as_mut() {
+                        writer.add_cue(&chapter.title);
+                    }
+                }
+                ExportFormat::WavPerChapter => {
+                    if let Some(previous) = writer.take() {
+                        previous.finish().map_err(io_error)?;
+                    }
+                    if let Some((previous_base, previous)) = track.take() {
+                        previous.write(&previous_base, &settings.subtitles).map_err(io_error)?;
+                    }
+                    let chapter_base = output.join(format!("{:03} {}", chapter_index, sanitize_file_name(&chapter.title)));
+                    let path = append_extension(&chapter_base, "wav");
+                    track = Some((chapter_base, SubtitleTrack::new(chapter.title.clone(), voice_name.clone())));
+                    position = 0;
+                    let mut info = vec![
+                        (*b"INAM", chapter.title.clone()),
+                        (*b"IPRD", novel.title.clone()),
+                        (*b"ITRK", chapter_index.to_string()),
+                        (*b"ISFT", "rovel-desk".to_string()),
+                    ];
+                    if let Some(name) = &voice_name {
+                        info.push((*b"IART", name.clone()));
+                    }
+                    writer = Some(WavWriter::create(&path, channels, sample_rate, &info).map_err(io_error)?);
+                }
+            }
+        }
+
+        // 同一文件中段落之间插入静音
+        if position > 0 {
+            let silence = u64::from(sample_rate) * u64::from(settings.silence_ms) / 1000;
+            if let Some(writer) = writer.as_mut() {
+                writer.write_silence(silence).map_err(io_error)?;
+            }
+            position += silence;
+        }
+        let start = position;
+        let converted = samples.convert(channels, sample_rate);
+        let written = match writer.as_mut() {
+            Some(writer) => writer.write_samples(converted).map_err(io_error)?,
+            None => converted.count() as u64,
+        };
+        position += written / u64::from(channels);
+
+        let Some((_, track)) = track.as_mut() else {
+            return Err(ApiError::Invalid(format!("段落 {} 不在任何章节内", index)));
+        };
+        let to_ms = |frames: u64| frames * 1000 / u64::from(sample_rate);
+        let text = segments.iter().find(|s| s.index == index as usize).map_or("", |s| s.content.as_str());
+        track.push(to_ms(start), to_ms(position), text);
+
+        done += 1;
+        on_progress(done, total);
+        Ok(())
+    })?;
+
+    if let Some(writer) = writer {
+        writer.finish().map_err(io_error)?;
+    }
+    if let Some((base, track)) = track {
+        track.write(&base, &settings.subtitles).map_err(io_error)?;
+    }
+    staging.commit(output_dir).map_err(io_error)?;
+    Ok(ExportSummary { output: output_dir.join(output_name), chapters: chapters.len() })
+}
+
+/// 导出用的临时目录，被丢弃时连同其中不完整的文件一起删除
+struct StagingDir {
+    path: PathBuf,
+}
+
+impl StagingDir {
+    fn create(output_dir: &Path, name: &str) -> io::Result<Self> {
+        let path = output_dir.join(format!(".{}.partial", name));
+        // 清理上次异常退出留下的临时目录
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    /// 把导出的文件移到 `output_dir`，覆盖同名文件
+    fn commit(self, output_dir: &Path) -> io::Result<()> {
+        move_entries(&self.path, output_dir)
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+fn move_entries(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            move_entries(&entry.path(), &target)?;
+        } else {
+            std::fs::rename(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// 一段解码后的音频
+struct DecodedSegment {
+    channels: u16,
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+impl DecodedSegment {
+    /// 转换为指定的声道数和采样率
+    fn convert(self, channels: u16, sample_rate: u32) -> Box<dyn Iterator<Item = i16>> {
+        if self.channels == channels && self.sample_rate == sample_rate {
+            return Box::new(self.samples.into_iter());
+        }
+        let source = rodio::buffer::SamplesBuffer::new(self.channels, self.sample_rate, self.samples);
+        Box::new(UniformSourceIterator::<_, i16>::new(source, channels, sample_rate))
+    }
+}
+
+fn decode(audio: &SegmentAudio) -> ApiResult<DecodedSegment> {
+    let decoder = open_decoder(&audio.to_stream())
+        .map_err(|e| ApiError::Decode(format!("第 {} 段音频无法解码: {}", audio.segment_index + 1, e)))?;
+    Ok(DecodedSegment {
+        channels: decoder.channels(),
+        sample_rate: decoder.sample_rate(),
+        samples: decoder.collect(),
+    })
+}
+
+fn io_error(e: io::Error) -> ApiError {
+    ApiError::Io(e.to_string())
+}
+
+/// 去掉文件名中不允许的字符
+fn sanitize_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    let name = name.trim().trim_end_matches('.');
+    if name.is_empty() {
+        "audiobook".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+// ============================================================================
+// WAV 写入
+// ============================================================================
+
+/// 16 位 PCM WAV 写入器
+///
+/// 数据块大小在 `finish` 时回填；章节标记写在数据块之后的 `cue ` 和 `LIST adtl` 块中。
+struct WavWriter {
+    file: BufWriter<File>,
+    channels: u16,
+    /// `data` 块内容的起始位置
+    data_start: u64,
+    data_bytes: u64,
+    /// (位置（帧）, 标签)
+    cues: Vec<(u32, String)>,
+}
+
+impl WavWriter {
+    fn create(path: &Path, channels: u16, sample_rate: u32, info: &[([u8; 4], String)]) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(b"RIFF\0\0\0\0WAVE")?;
+
+        let block_align = channels * 2;
+        file.write_all(b"fmt ")?;
+        file.write_all(&16u32.to_le_bytes())?;
+        file.write_all(&1u16.to_le_bytes())?; // PCM
+        file.write_all(&channels.to_le_bytes())?;
+        file.write_all(&sample_rate.to_le_bytes())?;
+        file.write_all(&(sample_rate * u32::from(block_align)).to_le_bytes())?;
+        file.write_all(&block_align.to_le_bytes())?;
+        file.write_all(&16u16.to_le_bytes())?;
+
+        let mut list = b"INFO".to_vec();
+        for (id, text) in info {
+            write_text_chunk(&mut list, id, text.as_bytes());
+        }
+        write_chunk(&mut file, b"LIST", &list)?;
+
+        file.write_all(b"data\0\0\0\0")?;
+        let data_start = file.stream_position()?;
+        Ok(Self {
+            file,
+            channels,
+            data_start,
+            data_bytes: 0,
+            cues: Vec::new(),
+        })
+    }
+
+    /// 已写入的帧数
+    fn frames(&self) -> u64 {
+        self.data_bytes / (u64::from(self.channels) * 2)
+    }
+
+    /// 在当前位置添加章节标记
+    fn add_cue(&mut self, label: &str) {
+        self.cues.push((self.frames() as u32, label.to_string()));
+    }
+
+    /// 写入采样，返回写入的采样数；超过 WAV 大小上限时在写入前报错
+    fn write_samples(&mut self, samples: impl Iterator<Item = i16>) -> io::Result<u64> {
+        let before = self.data_bytes;
+        for sample in samples {
+            if self.data_bytes + 2 > MAX_WAV_DATA_BYTES {
+                return Err(io::Error::new(io::ErrorKind::FileTooLarge, "音频超过 WAV 文件 4GB 上限，请按章导出"));
+            }
+            self.file.write_all(&sample.to_le_bytes())?;
+            self.data_bytes += 2;
+        }
+        Ok((self.data_bytes - before) / 2)
+    }
+
+    fn write_silence(&mut self, frames: u64) -> io::Result<()> {
+        self.write_samples(std::iter::repeat_n(0, (frames * u64::from(self.channels)) as usize))
+            .map(|_| ())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if !self.cues.is_empty() {
+            let mut cue = (self.cues.len() as u32).to_le_bytes().to_vec();
+            let mut adtl = b"adtl".to_vec();
+            for (i, (position, label)) in self.cues.iter().enumerate() {
+                let id = i as u32 + 1;
+                cue.extend_from_slice(&id.to_le_bytes());
+                cue.extend_from_slice(&position.to_le_bytes());
+                cue.extend_from_slice(b"data");
+                cue.extend_from_slice(&0u32.to_le_bytes()); // chunk start
+                cue.extend_from_slice(&0u32.to_le_bytes()); // block start
+                cue.extend_from_slice(&position.to_le_bytes()); // sample offset
+
+                let mut labl = id.to_le_bytes().to_vec();
+                labl.extend_from_slice(label.as_bytes());
+                labl.push(0);
+                write_chunk(&mut adtl, b"labl", &labl)?;
+            }
+            write_chunk(&mut self.file, b"cue ", &cue)?;
+            write_chunk(&mut self.file, b"LIST", &adtl)?;
+        }
+
+        // 回填 RIFF 和 data 块大小
+        let file_len = self.file.stream_position()?;
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&((file_len - 8) as u32).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(self.data_start - 4))?;
+        self.file.write_all(&(self.data_bytes as u32).to_le_bytes())?;
+        self.file.flush()
+    }
+}
+
+/// 写入一个 RIFF 子块（奇数长度补一个字节）
+fn write_chunk(out: &mut impl Write, id: &[u8; 4], content: &[u8]) -> io::Result<()> {
+    out.write_all(id)?;
+    out.write_all(&(content.len() as u32).to_le_bytes())?;
+    out.write_all(content)?;
+    if content.len() % 2 == 1 {
+        out.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+/// `LIST INFO` 中以 0 结尾的文本
+fn write_text_chunk(out: &mut Vec<u8>, id: &[u8; 4], text: &[u8]) {
+    let mut content = text.to_vec();
+    content.push(0);
+    // 写入 Vec 不会失败
+    let _ = write_chunk(out, id, &content);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ApiClient;
+    use crate::fs_util::TempDir;
+    use crate::mock_server::MockRovelServer;
+
+    fn segment(index: usize, content: &str) -> SegmentResponse {
+        SegmentResponse { index, content: content.to_string(), char_count: content.chars().count() }
+    }
+
+    #[test]
+    fn detects_chapter_headings_in_segment_text() {
+        let segments = [
+            segment(0, "作者的话"),
+            segment(1, "第一章 出发"),
+            segment(2, "第一章里的故事很长，这一段是正文，不应该被当作章节标题来处理。"),
+            segment(3, "第12回"),
+            segment(4, "Chapter 3: Home"),
+            segment(5, "第三者插足"),
+            segment(6, "尾声"),
+        ];
+        let titles: Vec<(String, u32)> = detect_chapters("小说", &segments)
+            .into_iter()
+            .map(|c| (c.title, c.start_segment))
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                ("小说".to_string(), 0),
+                ("第一章 出发".to_string(), 1),
+                ("第12回".to_string(), 3),
+                ("Chapter 3: Home".to_string(), 4),
+                ("尾声".to_string(), 6),
+            ]
+        );
+
+        // 段落编号不从 0 开始时，开头的章节从第一段开始
+        let chapters = detect_chapters("小说", &[segment(3, "楔子"), segment(4, "第一章 出发")]);
+        assert_eq!(chapters[0].start_segment, 3);
+    }
+
+    #[test]
+    fn exports_single_wav_with_chapter_markers_and_silence() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("导出", &["第一章 开始", "一", "第二章 继续", "二"]);
+        let voice = server.add_voice("默认音色");
+        let dir = TempDir::new("export");
+        let client = ApiClient::new(server.base_url());
+        let settings = ExportSettings {
+            format: ExportFormat::WavWithChapters,
+            silence_ms: 100,
+            subtitles: vec![SubtitleFormat::Srt],
+        };
+
+        let mut progress = Vec::new();
+        let summary = export_audiobook(
+            &client,
+            novel.id,
+            voice.id,
+            &[],
+            &settings,
+            &dir,
+            &AtomicBool::new(false),
+            &mut |done, total| progress.push((done, total)),
+        )
+        .unwrap();
+        assert_eq!(summary.chapters, 2);
+        assert_eq!(progress.last(), Some(&(4, 4)));
+
+        let data = std::fs::read(&summary.output).unwrap();
+        let decoder = rodio::Decoder::new_wav(std::io::Cursor::new(data.clone())).unwrap();
+        assert_eq!((decoder.channels(), decoder.sample_rate()), (1, 16_000));
+        // 4 段 200ms 音频 + 3 段 100ms 静音
+        assert_eq!(decoder.count(), 16 * (4 * 200 + 3 * 100));
+        assert!(data.windows(4).any(|w| w == b"cue "));
+        let label = "第二章 继续\0".as_bytes();
+        assert!(data.windows(label.len()).any(|w| w == label));
+        assert!(data.windows(b"INAM".len()).any(|w| w == b"INAM"));
+
+        // 字幕时间轴包含段间静音
+        let srt = std::fs::read_to_string(dir.join("导出.srt")).unwrap();
+        assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:00,200\n第一章 开始\n\n2\n00:00:00,300 --> 00:00:00,500\n一\n"));
+        assert!(srt.contains("4\n00:00:00,900 --> 00:00:01,100\n二\n"));
+    }
+
+    #[test]
+    fn subtitles_only_export_requires_a_subtitle_format() {
+        // 校验在访问服务器之前完成
+        let client = ApiClient::new("http://127.0.0.1:9/api".to_string());
+        let dir = TempDir::new("export");
+        let settings = ExportSettings { format: ExportFormat::SubtitlesOnly, silence_ms: 0, subtitles: Vec::new() };
+        let result = export_audiobook(
+            &client,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            &[],
+            &settings,
+            &dir,
+            &AtomicBool::new(false),
+            &mut |_, _| {},
+        );
+        assert!(matches!(result, Err(ApiError::Invalid(_))), "unexpected result: {:?}", result);
+    }
+
+    #[test]
+    fn cancelled_export_leaves_no_partial_files() {
+        let server = MockRovelServer::start();
+        let texts: Vec<String> = (1..=12).map(|i| format!("第{}章", i)).collect();
+        let novel = server.add_novel("取消", &texts.iter().map(String::as_str).collect::<Vec<_>>());
+        let voice = server.add_voice("默认音色");
+        let client = ApiClient::new(server.base_url());
+
+        for format in [ExportFormat::WavWithChapters, ExportFormat::WavPerChapter] {
+            let dir = TempDir::new("export");
+            let settings = ExportSettings { format, silence_ms: 0, subtitles: vec![SubtitleFormat::Srt] };
+            // 第一批 8 段写完后取消
+            let cancel = AtomicBool::new(false);
+            let result = export_audiobook(&client, novel.id, voice.id, &[], &settings, &dir, &cancel, &mut |done, _| {
+                if done == 8 {
+                    cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+                }
+            });
+            assert_eq!(result.unwrap_err(), ApiError::Cancelled);
+            assert_eq!(std::fs::read_dir(&*dir).unwrap().count(), 0, "{:?} left files behind", format);
+        }
+    }
+}
@@ -6,6 +6,7 @@
 use bevy::prelude::*;
 use std::sync::{mpsc, Mutex};
 
+use crate::bookmarks::Bookmarks;
 use crate::state::{AppState, FilePickerRequest, FilePickerResult, FilePickerType};
 use crate::get_runtime;
 
@@ -60,6 +61,20 @@ pub fn handle_file_picker_requests(
                         .await
                         .map(|f| f.path().to_path_buf())
                 }
+                FilePickerType::ExportDir => {
+                    rfd::AsyncFileDialog::new()
+                        .pick_folder()
+                        .await
+                        .map(|f| f.path().to_path_buf())
+                }
+                FilePickerType::BookmarksFile => {
+                    rfd::AsyncFileDialog::new()
+                        .add_filter("Markdown", &["md"])
+                        .set_file_name("书签.md")
+                        .save_file()
+                        .await
+                        .map(|f| f.path().to_path_buf())
+                }
             };
             
             let _ = sender.send(FilePickerResult { picker_type, path });
@@ -86,8 +101,29 @@ pub fn poll_file_picker_tasks(
 pub fn handle_file_picker_results(
     mut events: EventReader<FilePickerResult>,
     mut app_state: ResMut<AppState>,
+    bookmarks: Res<Bookmarks>,
 ) {
     for event in events.read() {
+        match event.picker_type {
+            FilePickerType::ExportDir => {
+                app_state.export_dialog.picking_dir = false;
+                if let Some(path) = &event.path {
+                    app_state.export_dialog.output_dir = Some(path.clone());
+                }
+                continue;
+            }
+            FilePickerType::BookmarksFile => {
+                app_state.bookmark_panel.picking_file = false;
+                let novel = app_state.selected_novel.as_ref().map(|n| (n.id, n.title.clone()));
+                if let (Some(path), Some((novel_id, title))) = (&event.path, novel) {
+                    if let Err(e) = bookmarks.export_markdown(novel_id, &title, path) {
+                        app_state.set_error(format!("导出书签失败: {}", e));
+                    }
+                }
+                continue;
+            }
+            FilePickerType::Novel | FilePickerType::Voice => {}
+        }
         app_state.upload_dialog.picking_file = false;
         
         if let Some(path) = &event.path {
@@ -110,6 +146,7 @@ pub fn handle_file_picker_results(
                     }
                     app_state.upload_dialog.voice_file_path = Some(path.clone());
                 }
+                FilePickerType::ExportDir | FilePickerType::BookmarksFile => {}
             }
         }
     }
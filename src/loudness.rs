@@ -0,0 +1,230 @@
+//! 音量和响度均衡
+//!
+//! 不同段落的合成音量常有明显差异。解码线程按 ITU-R BS.1770（EBU R128 使用的测量方法）
+//! 测量每段的积分响度：K 加权滤波，400ms 块每 100ms 一个，绝对门限 -70 LUFS，
+//! 相对门限 -10 LU。播放时把各段增益调整到 `TARGET_LUFS`，连续段落的听感音量一致。
+//! 响度随解码逐步更新，开始播放时已有最小缓冲长度的测量结果。
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// 均衡目标响度（口语内容常用的 -18 LUFS，比广播的 -23 LUFS 更响）
+pub const TARGET_LUFS: f64 = -18.0;
+
+/// 均衡放大的上限，避免把几乎静音的段落放大成噪声
+const MAX_GAIN_DB: f64 = 12.0;
+
+/// 放大后峰值至少留出的余量
+const PEAK_HEADROOM_DB: f64 = 1.0;
+
+/// 测量块由 4 个 100ms 子块组成（75% 重叠）
+const SUB_BLOCKS_PER_BLOCK: usize = 4;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// 音量设置（保存在配置文件中）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumeSettings {
+    /// 主音量（0.0 - 1.0）
+    pub volume: f32,
+    pub muted: bool,
+    /// 响度均衡
+    pub normalize: bool,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self { volume: 1.0, muted: false, normalize: true }
+    }
+}
+
+impl VolumeSettings {
+    /// 实际输出音量，静音时为 0
+    pub fn effective(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume.clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// 二阶 IIR 滤波器（转置直接 II 型）
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// K 加权滤波器：高频搁架（模拟头部的声学效应）+ 高通（RLB 加权）
+///
+/// 系数按采样率由 BS.1770 的 48 kHz 原型推导。
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = f64::from(sample_rate.max(1));
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+fn block_loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// 一段音频的响度测量
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    channels: usize,
+    /// 每个声道的 K 加权滤波器
+    filters: Vec<[Biquad; 2]>,
+    /// 100ms 子块的帧数
+    sub_block_frames: usize,
+    /// 当前子块已累计的帧数和各声道平方和
+    frames: usize,
+    sum: f64,
+    /// 最近的子块均方，凑满 4 个组成一个测量块
+    recent: VecDeque<f64>,
+    /// 每个 400ms 测量块的均方（各声道之和）
+    blocks: Vec<f64>,
+    peak: u16,
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            filters: vec![k_weighting(sample_rate); channels],
+            sub_block_frames: (sample_rate.max(10) / 10) as usize,
+            frames: 0,
+            sum: 0.0,
+            recent: VecDeque::with_capacity(SUB_BLOCKS_PER_BLOCK),
+            blocks: Vec::new(),
+            peak: 0,
+        }
+    }
+
+    /// 追加交错的样本
+    pub fn push(&mut self, samples: &[i16]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (sample, [shelf, high_pass]) in frame.iter().zip(&mut self.filters) {
+                self.peak = self.peak.max(sample.unsigned_abs());
+                let x = f64::from(*sample) / 32768.0;
+                let y = high_pass.process(shelf.process(x));
+                self.sum += y * y;
+            }
+            self.frames += 1;
+            if self.frames == self.sub_block_frames {
+                if self.recent.len() == SUB_BLOCKS_PER_BLOCK {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(self.sum / self.frames as f64);
+                if self.recent.len() == SUB_BLOCKS_PER_BLOCK {
+                    self.blocks.push(self.recent.iter().sum::<f64>() / SUB_BLOCKS_PER_BLOCK as f64);
+                }
+                self.frames = 0;
+                self.sum = 0.0;
+            }
+        }
+    }
+
+    /// 积分响度（LUFS），不足一个测量块或全部低于门限时为 `None`
+    pub fn integrated(&self) -> Option<f64> {
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+        let gated: Vec<f64> = self
+            .blocks
+            .iter()
+            .copied()
+            .filter(|&z| block_loudness(z) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if gated.is_empty() {
+            return None;
+        }
+        let relative_gate = block_loudness(mean(&gated)) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = gated.into_iter().filter(|&z| block_loudness(z) > relative_gate).collect();
+        Some(block_loudness(mean(&gated)))
+    }
+
+    /// 把这段调整到目标响度的线性增益，放大时不让已测得的峰值削波
+    pub fn gain(&self) -> Option<f32> {
+        let mut gain_db = (TARGET_LUFS - self.integrated()?).min(MAX_GAIN_DB);
+        if self.peak > 0 {
+            let peak_db = 20.0 * (f64::from(self.peak) / 32768.0).log10();
+            gain_db = gain_db.min(-peak_db - PEAK_HEADROOM_DB);
+        }
+        Some(10f64.powf(gain_db / 20.0) as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measure(amplitude: f64, channels: u16) -> LoudnessMeter {
+        let samples: Vec<i16> = (0..48_000 * 2)
+            .map(|i| (amplitude * 32767.0 * (i as f64 * 997.0 * std::f64::consts::TAU / 48_000.0).sin()) as i16)
+            .flat_map(|s| std::iter::repeat_n(s, channels as usize))
+            .collect();
+        let mut meter = LoudnessMeter::new(channels, 48_000);
+        meter.push(&samples);
+        meter
+    }
+
+    #[test]
+    fn full_scale_sine_reads_minus_three_lufs() {
+        // BS.1770：单声道 0 dBFS 的 997 Hz 正弦为 -3.01 LUFS，双声道各加 3 dB
+        let mono = measure(1.0, 1).integrated().unwrap();
+        assert!((mono + 3.01).abs() < 0.05, "{}", mono);
+        let stereo = measure(1.0, 2).integrated().unwrap();
+        assert!(stereo.abs() < 0.05, "{}", stereo);
+    }
+
+    #[test]
+    fn gain_brings_quiet_and_loud_segments_to_target() {
+        // -20 dBFS 正弦为 -23 LUFS，放大 5 dB
+        let quiet = measure(0.1, 1).gain().unwrap();
+        assert!((20.0 * quiet.log10() - 5.0).abs() < 0.1, "{}", quiet);
+        // 0 dBFS 正弦为 -3 LUFS，衰减 15 dB
+        let loud = measure(1.0, 1).gain().unwrap();
+        assert!((20.0 * loud.log10() + 15.0).abs() < 0.1, "{}", loud);
+        // 放大不超过上限，也不让峰值削波
+        let faint = measure(0.01, 1).gain().unwrap();
+        assert!((20.0 * faint.log10() - 12.0).abs() < 0.01, "{}", faint);
+        let mut peaky = measure(0.05, 1);
+        peaky.push(&[29_490]);
+        assert!(peaky.gain().unwrap() < 1.0);
+
+        assert_eq!(LoudnessMeter::new(1, 48_000).gain(), None);
+        assert_eq!(measure(0.0, 1).gain(), None);
+    }
+}
@@ -9,10 +9,29 @@
 
 mod api;
 mod audio;
+mod audio_cache;
+mod audio_format;
+mod audio_stream;
+mod auth;
+mod backend;
+mod bookmarks;
+mod config;
+mod export;
 mod file_picker;
+mod fs_util;
+mod loudness;
+#[cfg(test)]
+mod mock_server;
+mod offline;
+mod openai_speech;
+mod progress;
 mod state;
+mod subtitles;
 mod systems;
+mod time_stretch;
+mod tls;
 mod ui;
+mod upload_state;
 mod websocket;
 
 use bevy::prelude::*;
@@ -20,16 +39,28 @@ use bevy_egui::{EguiContexts, EguiPlugin, EguiPreUpdateSet};
 use std::sync::OnceLock;
 
 use api::ApiClient;
-use audio::{check_audio_finished, handle_pause_audio, handle_play_audio, handle_resume_audio, handle_stop_audio, AudioPlayer};
+use audio_cache::AudioCache;
+use audio::{
+    check_audio_finished, handle_pause_audio, handle_play_audio, handle_queue_audio, handle_resume_audio,
+    handle_seek_audio, handle_stop_audio, sync_playback_settings, track_playback_position, AudioPlayer,
+};
+use backend::Backend;
+use config::AppConfig;
+use bookmarks::Bookmarks;
+use offline::OfflineLibrary;
+use progress::ReadingProgress;
 use file_picker::{handle_file_picker_requests, handle_file_picker_results, poll_file_picker_tasks, setup_file_picker_channel};
 use state::{
     ApiRequest, ApiResponse, AppState, AppView, AudioFinishedEvent, FilePickerRequest,
-    FilePickerResult, PauseAudioEvent, PlayAudioEvent, ResumeAudioEvent, StopAudioEvent, WsRequest, WsResponse,
+    FilePickerResult, PauseAudioEvent, PlayAudioEvent, QueueAudioEvent, ResumeAudioEvent, SeekAudioEvent,
+    SegmentStartedEvent, StopAudioEvent, SwitchProfileEvent,
+    WsRequest, WsResponse,
 };
 use systems::{
     cleanup_stale_tasks_system, clear_error_timer, handle_api_requests, handle_api_responses,
-    handle_audio_finished, handle_ws_responses, poll_api_tasks, poll_processing_novels,
-    prefetch_tasks_system, setup_api_channel, startup_load,
+    handle_audio_finished, handle_segment_started, handle_switch_profile, handle_ws_responses, persist_auth_token,
+    poll_api_tasks, poll_backend_events, poll_processing_novels, prefetch_audio_system, prefetch_tasks_system,
+    queue_next_segment, setup_api_channel, startup_load, track_reading_progress, update_sleep_timer,
 };
 use ui::ui_system;
 use websocket::{handle_ws_requests, poll_ws_responses, poll_global_ws_responses, setup_ws_client};
@@ -59,6 +90,15 @@ fn main() {
         // Force Winsock initialization with a real network operation
         let _ = std::net::UdpSocket::bind("127.0.0.1:0");
     }
+
+    // 配置：配置文件 + 环境变量 + 命令行参数
+    let config = AppConfig::load();
+    let api_client = ApiClient::from_config(&config);
+    let offline = OfflineLibrary::open(OfflineLibrary::default_dir(&config.active().server_url));
+    let backend = Backend::from_config(&config, &api_client, &offline);
+    let audio_cache = AudioCache::from_policy(&config.audio_cache);
+    let progress = ReadingProgress::open(ReadingProgress::default_path(&config.active().server_url));
+    let bookmarks = Bookmarks::open(Bookmarks::default_path(&config.active().server_url));
     
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
@@ -74,7 +114,13 @@ fn main() {
         .init_state::<AppView>()
         // 资源
         .init_resource::<AppState>()
-        .insert_resource(ApiClient::default())
+        .insert_resource(api_client)
+        .insert_resource(backend)
+        .insert_resource(offline)
+        .insert_resource(progress)
+        .insert_resource(bookmarks)
+        .insert_resource(audio_cache)
+        .insert_resource(config)
         // 音频播放器
         .add_systems(Startup, setup_audio)
         // API 响应通道
@@ -87,12 +133,16 @@ fn main() {
         .add_event::<ApiRequest>()
         .add_event::<ApiResponse>()
         .add_event::<PlayAudioEvent>()
+        .add_event::<QueueAudioEvent>()
+        .add_event::<SegmentStartedEvent>()
+        .add_event::<SeekAudioEvent>()
         .add_event::<StopAudioEvent>()
         .add_event::<PauseAudioEvent>()
         .add_event::<ResumeAudioEvent>()
         .add_event::<AudioFinishedEvent>()
         .add_event::<FilePickerRequest>()
         .add_event::<FilePickerResult>()
+        .add_event::<SwitchProfileEvent>()
         // V2: WebSocket 事件
         .add_event::<WsRequest>()
         .add_event::<WsResponse>()
@@ -104,13 +154,17 @@ fn main() {
         .add_systems(
             Update,
             (
+                // 切换服务器需在处理 API 请求前完成，保证新请求使用新的 ApiClient
+                handle_switch_profile,
                 handle_api_requests,
                 poll_api_tasks,
                 handle_api_responses,
+                persist_auth_token,
                 // V2: WebSocket 系统
                 handle_ws_requests,
                 poll_ws_responses,
                 poll_global_ws_responses,
+                poll_backend_events,
                 handle_ws_responses,
             )
                 .chain(),
@@ -119,17 +173,29 @@ fn main() {
         .add_systems(
             Update,
             (
-                // 音频系统
-                handle_play_audio,
-                handle_stop_audio,
-                handle_pause_audio,
-                handle_resume_audio,
-                check_audio_finished,
-                handle_audio_finished,
+                // 音频系统（播放命令先于排队和跳转命令发给音频线程）
+                (
+                    handle_play_audio,
+                    handle_queue_audio,
+                    handle_seek_audio,
+                    handle_stop_audio,
+                    handle_pause_audio,
+                    handle_resume_audio,
+                    sync_playback_settings,
+                    check_audio_finished,
+                    handle_segment_started,
+                    handle_audio_finished,
+                    queue_next_segment,
+                    track_playback_position,
+                    track_reading_progress,
+                    update_sleep_timer,
+                )
+                    .chain(),
                 // 定时任务
                 clear_error_timer,
                 poll_processing_novels,
                 prefetch_tasks_system,
+                prefetch_audio_system,
                 cleanup_stale_tasks_system,
                 // 其他
                 handle_voice_click,
@@ -0,0 +1,847 @@
+//! 本地 mock Rovel 服务器 - 集成测试用
+//!
+//! 在 `127.0.0.1` 的随机端口上实现客户端用到的全部接口，不需要 GPU TTS 服务器:
+//! - HTTP: `/api/novel/*`、`/api/voice/*`、`/api/session/*`、`/api/infer/*`、`/api/audio`
+//! - WebSocket: `/ws/session/{id}`、`/ws/events`
+//! - OpenAI 兼容语音合成: `/v1/audio/speech`
+//!
+//! 行为按固定脚本模拟真实服务器:
+//! - 提交推理任务后，经过 `infer_delay` 依次推送 `inferring` → `ready`
+//! - 音频为生成的 WAV 正弦波，推理完成前请求音频返回“未就绪”
+//! - 上传小说后状态为 `processing`，经过 `process_delay` 推送 `NovelReady`
+//! - 删除小说 / 音色时推送 `NovelDeleting` / `NovelDeleted` / `VoiceDeleted`
+//!
+//! 测试还可以通过 [`MockRovelServer::push_session_event`] / [`MockRovelServer::push_global_event`]
+//! 推送任意事件，并通过 `state` 检查服务器收到的请求或注入故障。
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api::{ERRNO_NOT_FOUND, NovelResponse, SegmentResponse, TaskStatusInfo, VoiceResponse, WsEvent};
+
+/// 音频尚未生成
+pub const ERRNO_AUDIO_NOT_READY: i32 = 40901;
+
+/// `/api/audio/batch` 响应的 multipart 分隔符
+const MOCK_BATCH_BOUNDARY: &str = "mock-audio-batch";
+const MOCK_BATCH_CONTENT_TYPE: &str = "multipart/mixed; boundary=mock-audio-batch";
+
+/// chunked transfer 返回音频时每个分块的大小
+const MOCK_AUDIO_CHUNK_BYTES: usize = 4096;
+
+/// mock 服务器中的小说
+#[derive(Debug, Clone)]
+pub struct MockNovel {
+    pub novel: NovelResponse,
+    pub segments: Vec<String>,
+}
+
+/// 播放 session
+#[derive(Debug, Clone)]
+pub struct MockSession {
+    pub novel_id: Uuid,
+    pub voice_id: Uuid,
+    pub current_index: u32,
+}
+
+/// mock 服务器状态（测试可直接读写）
+pub struct MockState {
+    pub novels: Vec<MockNovel>,
+    pub voices: Vec<VoiceResponse>,
+    pub sessions: HashMap<String, MockSession>,
+    /// 推理任务 task_id -> 状态
+    pub tasks: HashMap<String, TaskStatusInfo>,
+    /// 已生成音频的 (novel_id, voice_id, segment_index)
+    pub ready_audio: HashSet<(Uuid, Uuid, u32)>,
+    /// 推理任务从提交到 `inferring`、从 `inferring` 到 `ready` 的间隔
+    pub infer_delay: Duration,
+    /// 小说从上传完成到 `NovelReady` 的间隔
+    pub process_delay: Duration,
+    /// 收到的全部请求（`METHOD /path?query`）
+    pub requests: Vec<String>,
+    /// 分块上传: upload_id -> (title, 分块序号 -> 内容)
+    pub uploads: HashMap<String, (String, BTreeMap<u32, Vec<u8>>)>,
+    /// 每次收到的分块请求序号
+    pub part_requests: Vec<u32>,
+    /// 收到该分块时断开连接（模拟网络中断），次数用尽后恢复正常
+    pub drop_part: Option<(u32, usize)>,
+    /// 最近一次分块上传 complete 后拼接出的完整内容
+    pub completed: Option<Vec<u8>>,
+    /// 收到的 `/v1/audio/speech` 请求体
+    pub speech_requests: Vec<Value>,
+    /// 每次 `/api/audio` 请求声明的格式（请求体 `formats` 字段）
+    pub audio_formats: Vec<Value>,
+    /// 每次 `/api/audio/batch` 请求的段落序号
+    pub batch_audio_requests: Vec<Vec<u32>>,
+    /// `/api/audio` 以 chunked transfer 返回，每个分块之间的间隔（模拟慢速网络）
+    pub audio_chunk_delay: Duration,
+    /// 收完 `/api/novel/upload` 请求体后延迟响应（模拟服务端处理慢）
+    pub upload_response_delay: Duration,
+    /// `/api/novel/list` 延迟响应（模拟慢请求）
+    pub novel_list_delay: Duration,
+    /// `/api/novel/upload/status` 返回该 errno（模拟服务端故障）
+    pub upload_status_errno: Option<i32>,
+    /// `/api/session/seek` 取消所有未完成的推理任务（同真实服务端）
+    pub seek_cancels_tasks: bool,
+    session_subscribers: Vec<(String, Sender<WsEvent>)>,
+    global_subscribers: Vec<Sender<WsEvent>>,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        Self {
+            novels: Vec::new(),
+            voices: Vec::new(),
+            sessions: HashMap::new(),
+            tasks: HashMap::new(),
+            ready_audio: HashSet::new(),
+            infer_delay: Duration::from_millis(20),
+            process_delay: Duration::from_millis(100),
+            requests: Vec::new(),
+            uploads: HashMap::new(),
+            part_requests: Vec::new(),
+            drop_part: None,
+            completed: None,
+            speech_requests: Vec::new(),
+            audio_formats: Vec::new(),
+            batch_audio_requests: Vec::new(),
+            audio_chunk_delay: Duration::ZERO,
+            upload_response_delay: Duration::ZERO,
+            novel_list_delay: Duration::ZERO,
+            upload_status_errno: None,
+            seek_cancels_tasks: false,
+            session_subscribers: Vec::new(),
+            global_subscribers: Vec::new(),
+        }
+    }
+}
+
+impl MockState {
+    /// 推送 session channel 事件（连接已断开的订阅者会被移除）
+    fn push_session(&mut self, session_id: &str, event: WsEvent) {
+        self.session_subscribers
+            .retain(|(id, tx)| id != session_id || tx.send(event.clone()).is_ok());
+    }
+
+    /// 推送全局事件
+    fn push_global(&mut self, event: WsEvent) {
+        self.global_subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+/// 本地 mock 服务器，每个连接一个线程，随测试进程退出
+pub struct MockRovelServer {
+    addr: SocketAddr,
+    pub state: Arc<Mutex<MockState>>,
+}
+
+impl MockRovelServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let addr = listener.local_addr().expect("mock server address");
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let shared = state.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = shared.clone();
+                std::thread::spawn(move || serve_connection(stream, state));
+            }
+        });
+        Self { addr, state }
+    }
+
+    /// 服务器地址，例如 `http://127.0.0.1:port`（与 `ServerProfile::server_url` 格式相同）
+    pub fn server_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// HTTP API 基础地址
+    pub fn base_url(&self) -> String {
+        format!("{}/api", self.server_url())
+    }
+
+    /// WebSocket 基础地址
+    pub fn ws_base_url(&self) -> String {
+        format!("ws://{}/ws", self.addr)
+    }
+
+    /// 添加一本已处理完成的小说
+    pub fn add_novel(&self, title: &str, segments: &[&str]) -> NovelResponse {
+        let novel = NovelResponse {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            total_segments: segments.len(),
+            status: "ready".to_string(),
+            created_at: now(),
+            is_temporary: false,
+        };
+        self.lock().novels.push(MockNovel {
+            novel: novel.clone(),
+            segments: segments.iter().map(|s| s.to_string()).collect(),
+        });
+        novel
+    }
+
+    /// 添加一个音色
+    pub fn add_voice(&self, name: &str) -> VoiceResponse {
+        let voice = VoiceResponse {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            created_at: now(),
+        };
+        self.lock().voices.push(voice.clone());
+        voice
+    }
+
+    /// 向指定 session 的 `/ws/session/{id}` 连接推送事件
+    pub fn push_session_event(&self, session_id: &str, event: WsEvent) {
+        self.lock().push_session(session_id, event);
+    }
+
+    /// 向所有 `/ws/events` 连接推送事件
+    pub fn push_global_event(&self, event: WsEvent) {
+        self.lock().push_global(event);
+    }
+
+    /// 当前 `/ws/events` 连接数（测试据此等待全局通道连上）
+    pub fn global_subscriber_count(&self) -> usize {
+        self.lock().global_subscribers.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// 生成单声道 16 位 PCM 的 WAV 正弦波
+pub fn wav_tone(duration_ms: u32, frequency: f32) -> Vec<u8> {
+    const SAMPLE_RATE: u32 = 16_000;
+    let samples = SAMPLE_RATE * duration_ms / 1000;
+    let data_len = samples * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // 单声道
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for i in 0..samples {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let sample = (t * frequency * std::f32::consts::TAU).sin() * i16::MAX as f32 * 0.3;
+        wav.extend_from_slice(&(sample as i16).to_le_bytes());
+    }
+    wav
+}
+
+fn now() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// ============================================================================
+// HTTP
+// ============================================================================
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    /// 小写的头部名 -> 值
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    fn is_websocket_upgrade(&self) -> bool {
+        self.header("upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+    }
+}
+
+struct Response {
+    content_type: &'static str,
+    body: Vec<u8>,
+    /// 以 chunked transfer 发送，分块之间间隔该时长
+    chunk_delay: Option<Duration>,
+}
+
+impl Response {
+    fn ok(data: Value) -> Self {
+        Self::json(json!({ "errno": 0, "error": "", "data": data }))
+    }
+
+    fn error(errno: i32, message: &str) -> Self {
+        Self::json(json!({ "errno": errno, "error": message, "data": null }))
+    }
+
+    fn json(value: Value) -> Self {
+        Self {
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+            chunk_delay: None,
+        }
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> Option<Request> {
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).ok()? == 0 {
+        return None;
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let content_length = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method,
+        path: path.to_string(),
+        query,
+        headers,
+        body,
+    })
+}
+
+fn serve_connection(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let Ok(read_half) = stream.try_clone() else { return };
+    let mut reader = BufReader::new(read_half);
+    let mut writer = stream;
+    while let Some(request) = read_request(&mut reader) {
+        state.lock().unwrap().requests.push(format!("{} {}", request.method, request.path));
+
+        if request.is_websocket_upgrade() {
+            serve_websocket(writer, &request, &state);
+            return;
+        }
+
+        if request.path == "/api/novel/upload" {
+            let delay = state.lock().unwrap().upload_response_delay;
+            std::thread::sleep(delay);
+        }
+        if request.path == "/api/novel/list" {
+            let delay = state.lock().unwrap().novel_list_delay;
+            std::thread::sleep(delay);
+        }
+
+        let Some(response) = route(&request, &state) else {
+            // 模拟网络中断：不返回响应直接断开
+            let _ = writer.shutdown(std::net::Shutdown::Both);
+            return;
+        };
+        if write_response(&mut writer, &response).is_err() {
+            return;
+        }
+    }
+}
+
+fn write_response(writer: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+    let Some(delay) = response.chunk_delay else {
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            response.content_type,
+            response.body.len()
+        );
+        writer.write_all(head.as_bytes())?;
+        return writer.write_all(&response.body);
+    };
+
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\n\r\n",
+        response.content_type
+    );
+    writer.write_all(head.as_bytes())?;
+    for (i, chunk) in response.body.chunks(MOCK_AUDIO_CHUNK_BYTES).enumerate() {
+        if i > 0 {
+            std::thread::sleep(delay);
+        }
+        writer.write_all(format!("{:x}\r\n", chunk.len()).as_bytes())?;
+        writer.write_all(chunk)?;
+        writer.write_all(b"\r\n")?;
+        writer.flush()?;
+    }
+    writer.write_all(b"0\r\n\r\n")
+}
+
+/// 路由请求；返回 `None` 表示模拟网络中断
+fn route(request: &Request, shared: &Arc<Mutex<MockState>>) -> Option<Response> {
+    let body = request.json();
+    let uuid = |key: &str| body[key].as_str().and_then(|s| s.parse::<Uuid>().ok());
+    let mut state = shared.lock().unwrap();
+
+    let response = match request.path.as_str() {
+        // ====== Novel ======
+        "/api/novel/list" => Response::ok(json!(state.novels.iter().map(|n| &n.novel).collect::<Vec<_>>())),
+        "/api/novel/get" => match state.novels.iter().find(|n| Some(n.novel.id) == uuid("id")) {
+            Some(novel) => Response::ok(json!(novel.novel)),
+            None => Response::error(ERRNO_NOT_FOUND, "novel not found"),
+        },
+        "/api/novel/segments" => match state.novels.iter().find(|n| Some(n.novel.id) == uuid("novel_id")) {
+            Some(novel) => {
+                let start = body["start"].as_u64().unwrap_or(0) as usize;
+                let limit = body["limit"].as_u64().map_or(usize::MAX, |l| l as usize);
+                let segments: Vec<SegmentResponse> = novel
+                    .segments
+                    .iter()
+                    .enumerate()
+                    .skip(start)
+                    .take(limit)
+                    .map(|(index, content)| SegmentResponse {
+                        index,
+                        content: content.clone(),
+                        char_count: content.chars().count(),
+                    })
+                    .collect();
+                Response::ok(json!({
+                    "novel_id": novel.novel.id,
+                    "total": novel.segments.len(),
+                    "segments": segments,
+                }))
+            }
+            None => Response::error(ERRNO_NOT_FOUND, "novel not found"),
+        },
+        "/api/novel/upload" => {
+            let fields = multipart_fields(request);
+            let title = fields.get("title").map(|t| String::from_utf8_lossy(t).into_owned()).unwrap_or_default();
+            let content = fields.get("file").cloned().unwrap_or_default();
+            Response::ok(json!(add_processing_novel(&mut state, shared, title, &content)))
+        }
+        "/api/novel/upload/init" => {
+            let upload_id = Uuid::new_v4().to_string();
+            let title = body["title"].as_str().unwrap_or_default().to_string();
+            state.uploads.insert(upload_id.clone(), (title, BTreeMap::new()));
+            Response::ok(json!({ "upload_id": upload_id, "chunk_size": body["chunk_size"] }))
+        }
+        "/api/novel/upload/status" if state.upload_status_errno.is_some() => {
+            Response::error(state.upload_status_errno.unwrap_or_default(), "internal error")
+        }
+        "/api/novel/upload/status" => match state.uploads.get(body["upload_id"].as_str().unwrap_or_default()) {
+            Some((_, parts)) => Response::ok(json!({
+                "upload_id": body["upload_id"],
+                "received_parts": parts.keys().collect::<Vec<_>>(),
+            })),
+            None => Response::error(ERRNO_NOT_FOUND, "upload not found"),
+        },
+        "/api/novel/upload/part" => {
+            let index: u32 = request.query.get("index").and_then(|i| i.parse().ok()).unwrap_or_default();
+            state.part_requests.push(index);
+            if let Some((drop_index, remaining)) = state.drop_part {
+                if drop_index == index && remaining > 0 {
+                    state.drop_part = Some((drop_index, remaining - 1));
+                    return None;
+                }
+            }
+            let upload_id = request.query.get("upload_id").map(String::as_str).unwrap_or_default();
+            match state.uploads.get_mut(upload_id) {
+                Some((_, parts)) => {
+                    parts.insert(index, request.body.clone());
+                    Response::ok(json!({}))
+                }
+                None => Response::error(ERRNO_NOT_FOUND, "upload not found"),
+            }
+        }
+        "/api/novel/upload/complete" => match state.uploads.remove(body["upload_id"].as_str().unwrap_or_default()) {
+            Some((title, parts)) => {
+                let content: Vec<u8> = parts.into_values().flatten().collect();
+                state.completed = Some(content.clone());
+                Response::ok(json!(add_processing_novel(&mut state, shared, title, &content)))
+            }
+            None => Response::error(ERRNO_NOT_FOUND, "upload not found"),
+        },
+        "/api/novel/delete" => {
+            let Some(id) = uuid("id").filter(|id| state.novels.iter().any(|n| n.novel.id == *id)) else {
+                return Some(Response::error(ERRNO_NOT_FOUND, "novel not found"));
+            };
+            state.novels.retain(|n| n.novel.id != id);
+            state.push_global(WsEvent::NovelDeleting { novel_id: id });
+            state.push_global(WsEvent::NovelDeleted { novel_id: id });
+            Response::ok(json!({}))
+        }
+
+        // ====== Voice ======
+        "/api/voice/list" => Response::ok(json!(state.voices)),
+        "/api/voice/upload" => {
+            let fields = multipart_fields(request);
+            let text = |name: &str| fields.get(name).map(|v| String::from_utf8_lossy(v).into_owned());
+            let voice = VoiceResponse {
+                id: Uuid::new_v4(),
+                name: text("name").unwrap_or_default(),
+                description: text("description"),
+                created_at: now(),
+            };
+            state.voices.push(voice.clone());
+            Response::ok(json!(voice))
+        }
+        "/api/voice/delete" => {
+            let Some(id) = uuid("id").filter(|id| state.voices.iter().any(|v| v.id == *id)) else {
+                return Some(Response::error(ERRNO_NOT_FOUND, "voice not found"));
+            };
+            state.voices.retain(|v| v.id != id);
+            state.push_global(WsEvent::VoiceDeleted { voice_id: id });
+            Response::ok(json!({}))
+        }
+
+        // ====== Session ======
+        "/api/session/play" => {
+            let (Some(novel_id), Some(voice_id)) = (uuid("novel_id"), uuid("voice_id")) else {
+                return Some(Response::error(ERRNO_NOT_FOUND, "novel or voice not found"));
+            };
+            let session_id = Uuid::new_v4().to_string();
+            let current_index = body["start_index"].as_u64().unwrap_or(0) as u32;
+            state.sessions.insert(session_id.clone(), MockSession { novel_id, voice_id, current_index });
+            Response::ok(json!({
+                "session_id": session_id,
+                "novel_id": novel_id,
+                "voice_id": voice_id,
+                "current_index": current_index,
+            }))
+        }
+        "/api/session/seek" => {
+            let session_id = body["session_id"].as_str().unwrap_or_default();
+            let cancels = state.seek_cancels_tasks;
+            let Some(session) = state.sessions.get_mut(session_id) else {
+                return Some(Response::error(ERRNO_NOT_FOUND, "session not found"));
+            };
+            session.current_index = body["segment_index"].as_u64().unwrap_or(0) as u32;
+            let current_index = session.current_index;
+            let mut cancelled_tasks = 0;
+            if cancels {
+                for task in state.tasks.values_mut().filter(|t| t.state == "pending" || t.state == "inferring") {
+                    task.state = "cancelled".to_string();
+                    cancelled_tasks += 1;
+                }
+            }
+            Response::ok(json!({
+                "session_id": session_id,
+                "current_index": current_index,
+                "cancelled_tasks": cancelled_tasks,
+            }))
+        }
+        "/api/session/change_voice" => {
+            let session_id = body["session_id"].as_str().unwrap_or_default();
+            match (state.sessions.get_mut(session_id), uuid("voice_id")) {
+                (Some(session), Some(voice_id)) => {
+                    session.voice_id = voice_id;
+                    Response::ok(json!({
+                        "session_id": session_id,
+                        "voice_id": voice_id,
+                        "cancelled_tasks": 0,
+                    }))
+                }
+                _ => Response::error(ERRNO_NOT_FOUND, "session not found"),
+            }
+        }
+        "/api/session/close" => {
+            let session_id = body["session_id"].as_str().unwrap_or_default().to_string();
+            state.sessions.remove(&session_id);
+            state.session_subscribers.retain(|(id, _)| *id != session_id);
+            Response::ok(json!({ "session_id": session_id }))
+        }
+
+        // ====== Inference ======
+        "/api/infer/submit" => {
+            let session_id = body["session_id"].as_str().unwrap_or_default().to_string();
+            let Some(session) = state.sessions.get(&session_id).cloned() else {
+                return Some(Response::error(ERRNO_NOT_FOUND, "session not found"));
+            };
+            let indices: Vec<u32> = body["segment_indices"]
+                .as_array()
+                .map(|a| a.iter().filter_map(|i| i.as_u64()).map(|i| i as u32).collect())
+                .unwrap_or_default();
+            let mut tasks = Vec::new();
+            for segment_index in indices {
+                let task_id = Uuid::new_v4().to_string();
+                let key = (session.novel_id, session.voice_id, segment_index);
+                let ready = state.ready_audio.contains(&key);
+                let task = TaskStatusInfo {
+                    task_id: task_id.clone(),
+                    segment_index,
+                    state: if ready { "ready" } else { "pending" }.to_string(),
+                    error: None,
+                };
+                tasks.push(json!({ "task_id": task_id, "segment_index": segment_index, "state": task.state }));
+                state.tasks.insert(task_id.clone(), task);
+                if !ready {
+                    spawn_inference(shared.clone(), session_id.clone(), task_id, key);
+                }
+            }
+            Response::ok(json!({ "tasks": tasks }))
+        }
+        "/api/infer/status" => {
+            let tasks: Vec<&TaskStatusInfo> = body["task_ids"]
+                .as_array()
+                .map(|ids| ids.iter().filter_map(|id| id.as_str()).filter_map(|id| state.tasks.get(id)).collect())
+                .unwrap_or_default();
+            Response::ok(json!({ "tasks": tasks }))
+        }
+
+        // ====== Audio ======
+        "/api/audio" => {
+            let segment_index = body["segment_index"].as_u64().unwrap_or(0) as u32;
+            state.audio_formats.push(body["formats"].clone());
+            match (uuid("novel_id"), uuid("voice_id")) {
+                (Some(novel_id), Some(voice_id)) if state.ready_audio.contains(&(novel_id, voice_id, segment_index)) => {
+                    Response {
+                        content_type: "audio/wav",
+                        body: wav_tone(200, 220.0 + 20.0 * segment_index as f32),
+                        chunk_delay: Some(state.audio_chunk_delay),
+                    }
+                }
+                _ => Response::error(ERRNO_AUDIO_NOT_READY, "audio not ready"),
+            }
+        }
+
+        "/api/audio/batch" => {
+            let indices: Vec<u32> = body["segment_indices"]
+                .as_array()
+                .map(|a| a.iter().filter_map(|v| v.as_u64()).map(|v| v as u32).collect())
+                .unwrap_or_default();
+            state.batch_audio_requests.push(indices.clone());
+            let (Some(novel_id), Some(voice_id)) = (uuid("novel_id"), uuid("voice_id")) else {
+                return Some(Response::error(ERRNO_NOT_FOUND, "novel or voice not found"));
+            };
+
+            // multipart/mixed: 只包含已生成的段落
+            let mut body = Vec::new();
+            for idx in indices.into_iter().filter(|&idx| state.ready_audio.contains(&(novel_id, voice_id, idx))) {
+                body.extend_from_slice(
+                    format!(
+                        "--{}\r\nContent-Type: audio/wav\r\nX-Segment-Index: {}\r\n\r\n",
+                        MOCK_BATCH_BOUNDARY, idx
+                    )
+                    .as_bytes(),
+                );
+                body.extend_from_slice(&wav_tone(200, 220.0 + 20.0 * idx as f32));
+                body.extend_from_slice(b"\r\n");
+            }
+            body.extend_from_slice(format!("--{}--\r\n", MOCK_BATCH_BOUNDARY).as_bytes());
+            Response {
+                content_type: MOCK_BATCH_CONTENT_TYPE,
+                body,
+                chunk_delay: None,
+            }
+        }
+
+        // ====== OpenAI 兼容语音合成 ======
+        "/v1/audio/speech" => {
+            let input_len = body["input"].as_str().map_or(0, |s| s.chars().count()) as u32;
+            state.speech_requests.push(body);
+            Response {
+                content_type: "audio/wav",
+                body: wav_tone(50 * input_len.max(1), 440.0),
+                chunk_delay: None,
+            }
+        }
+
+        path => Response::error(ERRNO_NOT_FOUND, &format!("no route for {}", path)),
+    };
+    Some(response)
+}
+
+/// 新上传的小说：按行分段，`process_delay` 后推送 `NovelReady`
+fn add_processing_novel(
+    state: &mut MockState,
+    shared: &Arc<Mutex<MockState>>,
+    title: String,
+    content: &[u8],
+) -> NovelResponse {
+    let segments: Vec<String> = String::from_utf8_lossy(content)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    let novel = NovelResponse {
+        id: Uuid::new_v4(),
+        title,
+        total_segments: 0,
+        status: "processing".to_string(),
+        created_at: now(),
+        is_temporary: false,
+    };
+    state.novels.push(MockNovel { novel: novel.clone(), segments });
+
+    let shared = shared.clone();
+    let novel_id = novel.id;
+    let delay = state.process_delay;
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        let mut state = shared.lock().unwrap();
+        let Some(entry) = state.novels.iter_mut().find(|n| n.novel.id == novel_id) else { return };
+        entry.novel.status = "ready".to_string();
+        entry.novel.total_segments = entry.segments.len();
+        let event = WsEvent::NovelReady {
+            novel_id,
+            title: entry.novel.title.clone(),
+            total_segments: entry.segments.len(),
+        };
+        state.push_global(event);
+    });
+    novel
+}
+
+/// 模拟推理：`inferring` → `ready`，完成后音频可供下载
+fn spawn_inference(shared: Arc<Mutex<MockState>>, session_id: String, task_id: String, key: (Uuid, Uuid, u32)) {
+    std::thread::spawn(move || {
+        let segment_index = key.2;
+        for state_name in ["inferring", "ready"] {
+            let delay = shared.lock().unwrap().infer_delay;
+            std::thread::sleep(delay);
+            let mut state = shared.lock().unwrap();
+            // session 已关闭、切换了音色或 seek 时任务被取消
+            let still_wanted = state
+                .sessions
+                .get(&session_id)
+                .is_some_and(|s| s.novel_id == key.0 && s.voice_id == key.1)
+                && state.tasks.get(&task_id).is_some_and(|t| t.state != "cancelled");
+            if !still_wanted {
+                if let Some(task) = state.tasks.get_mut(&task_id) {
+                    task.state = "cancelled".to_string();
+                }
+                return;
+            }
+            if let Some(task) = state.tasks.get_mut(&task_id) {
+                task.state = state_name.to_string();
+            }
+            if state_name == "ready" {
+                state.ready_audio.insert(key);
+            }
+            let event = WsEvent::TaskStateChanged {
+                session_id: session_id.clone(),
+                task_id: task_id.clone(),
+                segment_index,
+                state: state_name.to_string(),
+                duration_ms: (state_name == "ready").then_some(200),
+                error: None,
+            };
+            state.push_session(&session_id, event);
+        }
+    });
+}
+
+/// 解析 multipart/form-data 请求体：字段名 -> 内容
+fn multipart_fields(request: &Request) -> HashMap<String, Vec<u8>> {
+    let Some(boundary) = request
+        .header("content-type")
+        .and_then(|ct| ct.split_once("boundary="))
+        .map(|(_, b)| format!("--{}", b.trim_matches('"')))
+    else {
+        return HashMap::new();
+    };
+
+    let mut fields = HashMap::new();
+    for part in split_bytes(&request.body, boundary.as_bytes()) {
+        let Some(header_end) = find_bytes(part, b"\r\n\r\n") else { continue };
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let Some(name) = headers
+            .split(';')
+            .find_map(|p| p.trim().strip_prefix("name="))
+            .map(|n| n.split(['"', '\r', '\n']).find(|s| !s.is_empty()).unwrap_or_default().to_string())
+        else {
+            continue;
+        };
+        let content = &part[header_end + 4..];
+        let content = content.strip_suffix(b"\r\n").unwrap_or(content);
+        fields.insert(name, content.to_vec());
+    }
+    fields
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn split_bytes<'a>(mut data: &'a [u8], separator: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    while let Some(pos) = find_bytes(data, separator) {
+        parts.push(&data[..pos]);
+        data = &data[pos + separator.len()..];
+    }
+    parts.push(data);
+    parts
+}
+
+// ============================================================================
+// WebSocket
+// ============================================================================
+
+/// 完成握手后把订阅的事件逐条写给客户端（不读取客户端消息）
+fn serve_websocket(mut stream: TcpStream, request: &Request, state: &Arc<Mutex<MockState>>) {
+    use tungstenite::protocol::{Role, WebSocket};
+    use tungstenite::Message;
+
+    let (tx, rx) = mpsc::channel();
+    {
+        let mut state = state.lock().unwrap();
+        if request.path == "/ws/events" {
+            state.global_subscribers.push(tx);
+        } else if let Some(session_id) = request.path.strip_prefix("/ws/session/") {
+            state.session_subscribers.push((session_id.to_string(), tx));
+        } else {
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            return;
+        }
+    }
+
+    let key = request.header("sec-websocket-key").unwrap_or_default();
+    let handshake = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        tungstenite::handshake::derive_accept_key(key.as_bytes())
+    );
+    if stream.write_all(handshake.as_bytes()).is_err() {
+        return;
+    }
+
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    for event in rx {
+        let text = serde_json::to_string(&event).expect("serialize WsEvent");
+        if socket.send(Message::Text(text)).is_err() {
+            return;
+        }
+    }
+}
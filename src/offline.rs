@@ -0,0 +1,674 @@
+//! 离线下载 - 整本小说的段落文本和音频保存在本地
+//!
+//! 保存位置：`<配置目录>/rovel-desk/offline/<服务器地址的指纹>/<novel_id>/`
+//! - `novel.json`: 小说信息、下载使用的音色和全部段落文本
+//! - `audio/<segment_index>`: 各段音频（格式根据文件头识别）
+//!
+//! 下载时按批提交推理任务，一批的音频全部保存后再提交下一批，避免占满服务器队列。
+//! `OfflineBackend` 包装当前后端：已完整下载的小说在本地创建 session，
+//! 段落文本和音频直接从本地读取，无需连接服务器。
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::api::{
+    ApiError, ApiResult, ChangeVoiceResponse, CloseSessionResponse, NovelResponse, PlayResponse,
+    QueryTaskStatusResponse, RetryObserver, SeekResponse, SegmentAudio, SegmentResponse, SegmentsResponse,
+    SubmitInferResponse, TaskInfo, VoiceResponse, WsEvent,
+};
+use crate::audio_format::AudioFormat;
+use crate::audio_stream::AudioStream;
+use crate::backend::{Backend, RovelBackend};
+use crate::config::app_config_dir;
+use crate::fs_util::write_atomic;
+use crate::upload_state::fingerprint;
+
+/// 每批提交的推理任务数
+const DOWNLOAD_BATCH_SIZE: usize = 8;
+/// 等待一批音频生成时的轮询间隔
+const DOWNLOAD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// 下载段落文本时每页的段数
+const SEGMENT_PAGE_SIZE: usize = 200;
+/// 本地 session ID 前缀
+const OFFLINE_SESSION_PREFIX: &str = "offline-";
+
+const MANIFEST_FILE_NAME: &str = "novel.json";
+const AUDIO_DIR_NAME: &str = "audio";
+
+/// 是否为离线副本上的本地 session（不连接 WebSocket）
+pub fn is_offline_session(session_id: &str) -> bool {
+    session_id.starts_with(OFFLINE_SESSION_PREFIX)
+}
+
+/// `novel.json` 的内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OfflineManifest {
+    novel: NovelResponse,
+    voice: VoiceResponse,
+    segments: Vec<SegmentResponse>,
+}
+
+/// 一本离线小说
+#[derive(Debug, Clone)]
+struct OfflineNovel {
+    manifest: OfflineManifest,
+    /// 已保存音频的段落
+    audio: BTreeSet<u32>,
+}
+
+impl OfflineNovel {
+    fn is_complete(&self) -> bool {
+        !self.manifest.segments.is_empty() && self.audio.len() >= self.manifest.segments.len()
+    }
+}
+
+/// 离线副本的下载状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineStatus {
+    pub voice_id: Uuid,
+    pub voice_name: String,
+    /// 已下载音频的段数
+    pub downloaded: usize,
+    pub total: usize,
+}
+
+impl OfflineStatus {
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.downloaded >= self.total
+    }
+}
+
+/// 离线小说库资源（克隆后共享同一份数据）
+#[derive(Resource, Clone, Default)]
+pub struct OfflineLibrary {
+    /// `None` 时只保存在内存中
+    dir: Option<PathBuf>,
+    novels: Arc<Mutex<HashMap<Uuid, OfflineNovel>>>,
+}
+
+impl OfflineLibrary {
+    /// 加载目录中的离线小说
+    pub fn open(dir: Option<PathBuf>) -> Self {
+        let mut novels = HashMap::new();
+        let entries = dir.as_ref().and_then(|dir| std::fs::read_dir(dir).ok());
+        for novel_dir in entries.into_iter().flatten().filter_map(|e| e.ok()).map(|e| e.path()) {
+            let Ok(content) = std::fs::read_to_string(novel_dir.join(MANIFEST_FILE_NAME)) else { continue };
+            let manifest: OfflineManifest = match serde_json::from_str(&content) {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    tracing::warn!("Ignoring corrupt offline novel {:?}: {}", novel_dir, e);
+                    continue;
+                }
+            };
+            let audio = std::fs::read_dir(novel_dir.join(AUDIO_DIR_NAME))
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.ok()?.file_name().to_str()?.parse::<u32>().ok())
+                .collect();
+            novels.insert(manifest.novel.id, OfflineNovel { manifest, audio });
+        }
+        Self {
+            dir,
+            novels: Arc::new(Mutex::new(novels)),
+        }
+    }
+
+    /// 默认位置：`<配置目录>/rovel-desk/offline/<服务器地址的指纹>`
+    pub fn default_dir(server_url: &str) -> Option<PathBuf> {
+        app_config_dir().map(|dir| dir.join("offline").join(format!("{:016x}", fingerprint(server_url.as_bytes()))))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, OfflineNovel>> {
+        self.novels.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn novel_dir(&self, novel_id: Uuid) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(novel_id.to_string()))
+    }
+
+    /// 下载状态，没有离线副本时返回 `None`
+    pub fn status(&self, novel_id: Uuid) -> Option<OfflineStatus> {
+        self.lock().get(&novel_id).map(|novel| OfflineStatus {
+            voice_id: novel.manifest.voice.id,
+            voice_name: novel.manifest.voice.name.clone(),
+            downloaded: novel.audio.len(),
+            total: novel.manifest.segments.len(),
+        })
+    }
+
+    /// 是否已用 `voice_id` 完整下载
+    pub fn is_complete(&self, novel_id: Uuid, voice_id: Uuid) -> bool {
+        self.lock()
+            .get(&novel_id)
+            .is_some_and(|novel| novel.manifest.voice.id == voice_id && novel.is_complete())
+    }
+
+    /// 所有离线小说
+    fn novels(&self) -> Vec<NovelResponse> {
+        let mut novels: Vec<NovelResponse> = self.lock().values().map(|n| n.manifest.novel.clone()).collect();
+        novels.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        novels
+    }
+
+    /// 离线小说使用的音色（去重）
+    fn voices(&self) -> Vec<VoiceResponse> {
+        let mut voices: Vec<VoiceResponse> = Vec::new();
+        for novel in self.lock().values() {
+            if !voices.iter().any(|v| v.id == novel.manifest.voice.id) {
+                voices.push(novel.manifest.voice.clone());
+            }
+        }
+        voices
+    }
+
+    fn novel(&self, novel_id: Uuid) -> Option<NovelResponse> {
+        self.lock().get(&novel_id).map(|n| n.manifest.novel.clone())
+    }
+
+    fn segments(&self, novel_id: Uuid, start: Option<usize>, limit: Option<usize>) -> Option<SegmentsResponse> {
+        let novels = self.lock();
+        let segments = &novels.get(&novel_id)?.manifest.segments;
+        let start = start.unwrap_or(0).min(segments.len());
+        let end = limit.map_or(segments.len(), |limit| (start + limit).min(segments.len()));
+        Some(SegmentsResponse {
+            novel_id,
+            total: segments.len(),
+            segments: segments[start..end].to_vec(),
+        })
+    }
+
+    fn has_audio(&self, novel_id: Uuid, voice_id: Uuid, segment_index: u32) -> bool {
+        self.lock()
+            .get(&novel_id)
+            .is_some_and(|n| n.manifest.voice.id == voice_id && n.audio.contains(&segment_index))
+    }
+
+    /// 读取本地音频
+    fn load_audio(&self, novel_id: Uuid, voice_id: Uuid, segment_index: u32) -> Option<SegmentAudio> {
+        if !self.has_audio(novel_id, voice_id, segment_index) {
+            return None;
+        }
+        let path = self.novel_dir(novel_id)?.join(AUDIO_DIR_NAME).join(segment_index.to_string());
+        match std::fs::read(&path) {
+            Ok(data) => Some(SegmentAudio { segment_index, format: AudioFormat::sniff(&data), data }),
+            Err(e) => {
+                tracing::warn!("Failed to read offline audio {:?}: {}", path, e);
+                if let Some(novel) = self.lock().get_mut(&novel_id) {
+                    novel.audio.remove(&segment_index);
+                }
+                None
+            }
+        }
+    }
+
+    /// 开始下载：保存小说信息和段落文本。换了音色时丢弃已下载的音频
+    fn begin(&self, novel: NovelResponse, voice: VoiceResponse, segments: Vec<SegmentResponse>) -> std::io::Result<()> {
+        let novel_id = novel.id;
+        let mut audio = BTreeSet::new();
+        if let Some(existing) = self.lock().get(&novel_id) {
+            if existing.manifest.voice.id == voice.id {
+                audio = existing.audio.clone();
+            }
+        }
+        let manifest = OfflineManifest { novel, voice, segments };
+        let dir = self.novel_dir(novel_id).ok_or_else(no_storage)?;
+        if audio.is_empty() {
+            let _ = std::fs::remove_dir_all(dir.join(AUDIO_DIR_NAME));
+        }
+        std::fs::create_dir_all(dir.join(AUDIO_DIR_NAME))?;
+        let content = serde_json::to_string(&manifest)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        write_atomic(&dir.join(MANIFEST_FILE_NAME), content.as_bytes())?;
+        self.lock().insert(novel_id, OfflineNovel { manifest, audio });
+        Ok(())
+    }
+
+    /// 保存一段音频
+    fn save_audio(&self, novel_id: Uuid, audio: &SegmentAudio) -> std::io::Result<()> {
+        let dir = self.novel_dir(novel_id).ok_or_else(no_storage)?;
+        write_atomic(&dir.join(AUDIO_DIR_NAME).join(audio.segment_index.to_string()), &audio.data)?;
+        if let Some(novel) = self.lock().get_mut(&novel_id) {
+            novel.audio.insert(audio.segment_index);
+        }
+        Ok(())
+    }
+
+    /// 删除离线副本
+    pub fn remove(&self, novel_id: Uuid) {
+        self.lock().remove(&novel_id);
+        if let Some(dir) = self.novel_dir(novel_id) {
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                tracing::warn!("Failed to remove offline novel {:?}: {}", dir, e);
+            }
+        }
+    }
+}
+
+fn no_storage() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::NotFound, "offline storage directory unavailable")
+}
+
+fn io_error(e: std::io::Error) -> ApiError {
+    ApiError::Io(e.to_string())
+}
+
+// ============================================================================
+// 下载
+// ============================================================================
+
+/// 下载整本小说的段落文本和音频，`on_progress(downloaded, total)` 报告已下载的段数
+///
+/// 已下载的段落会跳过，中断后再次调用即可继续。
+pub fn download_novel(
+    backend: &dyn RovelBackend,
+    library: &OfflineLibrary,
+    novel_id: Uuid,
+    voice_id: Uuid,
+    formats: &[AudioFormat],
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(usize, usize),
+) -> ApiResult<()> {
+    let novel = backend.get_novel(novel_id)?;
+    let voice = backend
+        .list_voices()?
+        .into_iter()
+        .find(|v| v.id == voice_id)
+        .ok_or_else(|| ApiError::Server { errno: 404, message: "voice not found".to_string() })?;
+    let segments = fetch_all_segments(backend, novel_id, cancel)?;
+    let total = segments.len();
+    library.begin(novel, voice, segments).map_err(io_error)?;
+
+    let pending: Vec<u32> = (0..total as u32)
+        .filter(|&index| !library.has_audio(novel_id, voice_id, index))
+        .collect();
+    let mut downloaded = total - pending.len();
+    on_progress(downloaded, total);
+    fetch_audio_in_batches(backend, novel_id, voice_id, &pending, formats, cancel, &mut |audio| {
+        library.save_audio(novel_id, &audio).map_err(io_error)?;
+        downloaded += 1;
+        on_progress(downloaded, total);
+        Ok(())
+    })
+}
+
+/// 分页获取小说的全部段落文本
+pub fn fetch_all_segments(backend: &dyn RovelBackend, novel_id: Uuid, cancel: &AtomicBool) -> ApiResult<Vec<SegmentResponse>> {
+    let mut segments: Vec<SegmentResponse> = Vec::new();
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(ApiError::Cancelled);
+        }
+        let page = backend.get_novel_segments(novel_id, Some(segments.len()), Some(SEGMENT_PAGE_SIZE))?;
+        let received = page.segments.len();
+        segments.extend(page.segments);
+        if received == 0 || segments.len() >= page.total {
+            return Ok(segments);
+        }
+    }
+}
+
+/// 按顺序获取 `indices` 各段的音频，交给 `on_audio`
+///
+/// 每批先取已生成的音频，其余段落提交推理任务并轮询，一批全部取到后再处理下一批。
+/// 需要推理时创建专用 session，结束后关闭。
+pub fn fetch_audio_in_batches(
+    backend: &dyn RovelBackend,
+    novel_id: Uuid,
+    voice_id: Uuid,
+    indices: &[u32],
+    formats: &[AudioFormat],
+    cancel: &AtomicBool,
+    on_audio: &mut dyn FnMut(SegmentAudio) -> ApiResult<()>,
+) -> ApiResult<()> {
+    let mut session_id: Option<String> = None;
+    let mut fetch = |session_id: &mut Option<String>| -> ApiResult<()> {
+        for batch in indices.chunks(DOWNLOAD_BATCH_SIZE) {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(ApiError::Cancelled);
+            }
+            let mut ready = backend.get_audio_batch(novel_id, voice_id, batch, formats)?;
+            let mut remaining: Vec<u32> = batch
+                .iter()
+                .copied()
+                .filter(|index| !ready.iter().any(|a| a.segment_index == *index))
+                .collect();
+
+            if !remaining.is_empty() {
+                let session = match session_id {
+                    Some(id) => id.clone(),
+                    None => session_id.insert(backend.play(novel_id, voice_id, remaining[0])?.session_id).clone(),
+                };
+                let tasks = backend.submit_infer(&session, remaining.clone())?.tasks;
+                while !remaining.is_empty() {
+                    std::thread::sleep(DOWNLOAD_POLL_INTERVAL);
+                    if cancel.load(Ordering::Relaxed) {
+                        return Err(ApiError::Cancelled);
+                    }
+                    let fetched = backend.get_audio_batch(novel_id, voice_id, &remaining, formats)?;
+                    remaining.retain(|index| !fetched.iter().any(|a| a.segment_index == *index));
+                    ready.extend(fetched);
+                    if remaining.is_empty() {
+                        break;
+                    }
+
+                    // 推理失败的段落无法获取，停止并报告
+                    let task_ids = tasks
+                        .iter()
+                        .filter(|t| remaining.contains(&t.segment_index))
+                        .map(|t| t.task_id.clone())
+                        .collect();
+                    let failed = backend
+                        .query_task_status(task_ids)?
+                        .tasks
+                        .into_iter()
+                        .find(|t| t.state == "failed" || t.state == "cancelled");
+                    if let Some(task) = failed {
+                        return Err(ApiError::Server {
+                            errno: 500,
+                            message: format!(
+                                "第 {} 段推理失败: {}",
+                                task.segment_index + 1,
+                                task.error.unwrap_or(task.state)
+                            ),
+                        });
+                    }
+                }
+            }
+
+            ready.sort_by_key(|a| a.segment_index);
+            for audio in ready {
+                on_audio(audio)?;
+            }
+        }
+        Ok(())
+    };
+    let result = fetch(&mut session_id);
+    if let Some(session_id) = session_id {
+        if let Err(e) = backend.close_session(&session_id) {
+            tracing::warn!("Failed to close download session: {}", e);
+        }
+    }
+    result
+}
+
+// ============================================================================
+// 离线后端
+// ============================================================================
+
+/// 本地 session: (novel_id, voice_id)
+type OfflineSessions = Arc<Mutex<HashMap<String, (Uuid, Uuid)>>>;
+
+/// 优先使用离线副本的后端
+///
+/// - 段落文本和已下载的音频总是从本地读取
+/// - 完整下载的小说用下载时的音色播放时创建本地 session，推理任务立即就绪
+/// - 无法连接服务器时，小说和音色列表返回离线副本
+#[derive(Clone)]
+pub struct OfflineBackend {
+    inner: Backend,
+    library: OfflineLibrary,
+    sessions: OfflineSessions,
+}
+
+impl OfflineBackend {
+    pub fn new(inner: Backend, library: OfflineLibrary) -> Self {
+        Self {
+            inner,
+            library,
+            sessions: OfflineSessions::default(),
+        }
+    }
+
+    fn session(&self, session_id: &str) -> Option<(Uuid, Uuid)> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner()).get(session_id).copied()
+    }
+}
+
+/// 连接不上服务器（可以退回离线副本）
+fn is_unreachable(error: &ApiError) -> bool {
+    matches!(error, ApiError::Transport(_) | ApiError::Timeout)
+}
+
+impl RovelBackend for OfflineBackend {
+    fn observed_by(&self, observer: RetryObserver) -> Box<dyn RovelBackend> {
+        Box::new(Self {
+            inner: Backend::from(self.inner.observed_by(observer)),
+            library: self.library.clone(),
+            sessions: self.sessions.clone(),
+        })
+    }
+
+    fn list_novels(&self) -> ApiResult<Vec<NovelResponse>> {
+        match self.inner.list_novels() {
+            Ok(mut novels) => {
+                // 服务器上已删除的小说仍可播放离线副本
+                for novel in self.library.novels() {
+                    if !novels.iter().any(|n| n.id == novel.id) {
+                        novels.push(novel);
+                    }
+                }
+                Ok(novels)
+            }
+            Err(e) if is_unreachable(&e) => {
+                tracing::warn!("Server unreachable ({}), listing offline novels", e);
+                Ok(self.library.novels())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_novel(&self, id: Uuid) -> ApiResult<NovelResponse> {
+        match self.inner.get_novel(id) {
+            Err(e) if is_unreachable(&e) => self.library.novel(id).ok_or(e),
+            result => result,
+        }
+    }
+
+    fn get_novel_segments(&self, novel_id: Uuid, start: Option<usize>, limit: Option<usize>) -> ApiResult<SegmentsResponse> {
+        match self.library.segments(novel_id, start, limit) {
+            Some(segments) => Ok(segments),
+            None => self.inner.get_novel_segments(novel_id, start, limit),
+        }
+    }
+
+    fn upload_novel(
+        &self,
+        title: &str,
+        file_path: &Path,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(u64, u64),
+    ) -> ApiResult<NovelResponse> {
+        self.inner.upload_novel(title, file_path, cancel, on_progress)
+    }
+
+    fn delete_novel(&self, id: Uuid) -> ApiResult<()> {
+        self.inner.delete_novel(id)
+    }
+
+    fn list_voices(&self) -> ApiResult<Vec<VoiceResponse>> {
+        match self.inner.list_voices() {
+            Err(e) if is_unreachable(&e) => Ok(self.library.voices()),
+            result => result,
+        }
+    }
+
+    fn upload_voice(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        file_path: &Path,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(u64, u64),
+    ) -> ApiResult<VoiceResponse> {
+        self.inner.upload_voice(name, description, file_path, cancel, on_progress)
+    }
+
+    fn delete_voice(&self, id: Uuid) -> ApiResult<()> {
+        self.inner.delete_voice(id)
+    }
+
+    fn play(&self, novel_id: Uuid, voice_id: Uuid, start_index: u32) -> ApiResult<PlayResponse> {
+        if !self.library.is_complete(novel_id, voice_id) {
+            return self.inner.play(novel_id, voice_id, start_index);
+        }
+        let session_id = format!("{}{}", OFFLINE_SESSION_PREFIX, Uuid::new_v4());
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_id.clone(), (novel_id, voice_id));
+        tracing::info!("Playing offline copy of novel {}", novel_id);
+        Ok(PlayResponse { session_id, novel_id, voice_id, current_index: start_index })
+    }
+
+    fn seek(&self, session_id: &str, segment_index: u32) -> ApiResult<SeekResponse> {
+        if self.session(session_id).is_none() {
+            return self.inner.seek(session_id, segment_index);
+        }
+        Ok(SeekResponse { session_id: session_id.to_string(), current_index: segment_index, cancelled_tasks: 0 })
+    }
+
+    fn change_voice(&self, session_id: &str, voice_id: Uuid) -> ApiResult<ChangeVoiceResponse> {
+        let Some((novel_id, _)) = self.session(session_id) else {
+            return self.inner.change_voice(session_id, voice_id);
+        };
+        if !self.library.is_complete(novel_id, voice_id) {
+            return Err(ApiError::Unsupported("离线副本没有该音色的音频".to_string()));
+        }
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_id.to_string(), (novel_id, voice_id));
+        Ok(ChangeVoiceResponse { session_id: session_id.to_string(), voice_id, cancelled_tasks: 0 })
+    }
+
+    fn close_session(&self, session_id: &str) -> ApiResult<CloseSessionResponse> {
+        if self.sessions.lock().unwrap_or_else(|e| e.into_inner()).remove(session_id).is_none() {
+            return self.inner.close_session(session_id);
+        }
+        Ok(CloseSessionResponse { session_id: session_id.to_string() })
+    }
+
+    fn submit_infer(&self, session_id: &str, segment_indices: Vec<u32>) -> ApiResult<SubmitInferResponse> {
+        if self.session(session_id).is_none() {
+            return self.inner.submit_infer(session_id, segment_indices);
+        }
+        // 离线副本的音频都已就绪
+        let tasks = segment_indices
+            .into_iter()
+            .map(|segment_index| TaskInfo {
+                task_id: Uuid::new_v4().to_string(),
+                segment_index,
+                state: "ready".to_string(),
+            })
+            .collect();
+        Ok(SubmitInferResponse { tasks })
+    }
+
+    fn query_task_status(&self, task_ids: Vec<String>) -> ApiResult<QueryTaskStatusResponse> {
+        self.inner.query_task_status(task_ids)
+    }
+
+    fn get_audio(&self, novel_id: Uuid, segment_index: u32, voice_id: Uuid, formats: &[AudioFormat]) -> ApiResult<Vec<u8>> {
+        match self.library.load_audio(novel_id, voice_id, segment_index) {
+            Some(audio) => Ok(audio.data),
+            None => self.inner.get_audio(novel_id, segment_index, voice_id, formats),
+        }
+    }
+
+    fn open_audio_stream(
+        &self,
+        novel_id: Uuid,
+        segment_index: u32,
+        voice_id: Uuid,
+        formats: &[AudioFormat],
+    ) -> ApiResult<AudioStream> {
+        match self.library.load_audio(novel_id, voice_id, segment_index) {
+            Some(audio) => Ok(audio.to_stream()),
+            None => self.inner.open_audio_stream(novel_id, segment_index, voice_id, formats),
+        }
+    }
+
+    fn get_audio_batch(
+        &self,
+        novel_id: Uuid,
+        voice_id: Uuid,
+        segment_indices: &[u32],
+        formats: &[AudioFormat],
+    ) -> ApiResult<Vec<SegmentAudio>> {
+        let mut segments: Vec<SegmentAudio> = segment_indices
+            .iter()
+            .filter_map(|&index| self.library.load_audio(novel_id, voice_id, index))
+            .collect();
+        let missing: Vec<u32> = segment_indices
+            .iter()
+            .copied()
+            .filter(|index| !segments.iter().any(|s| s.segment_index == *index))
+            .collect();
+        if !missing.is_empty() {
+            segments.extend(self.inner.get_audio_batch(novel_id, voice_id, &missing, formats)?);
+        }
+        Ok(segments)
+    }
+
+    fn uses_websocket(&self) -> bool {
+        self.inner.uses_websocket()
+    }
+
+    fn take_events(&self) -> Vec<WsEvent> {
+        self.inner.take_events()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{ApiClient, RetryPolicy};
+    use crate::fs_util::TempDir;
+    use crate::mock_server::MockRovelServer;
+
+    #[test]
+    fn downloads_whole_novel_and_plays_it_without_the_server() {
+        let server = MockRovelServer::start();
+        let texts: Vec<String> = (1..=20).map(|i| format!("第{}段", i)).collect();
+        let novel = server.add_novel("离线", &texts.iter().map(String::as_str).collect::<Vec<_>>());
+        let voice = server.add_voice("默认音色");
+        let dir = TempDir::new("offline");
+        let library = OfflineLibrary::open(Some(dir.to_path_buf()));
+        let client = ApiClient::new(server.base_url());
+
+        let mut progress = Vec::new();
+        download_novel(&client, &library, novel.id, voice.id, &[], &AtomicBool::new(false), &mut |done, total| {
+            progress.push((done, total))
+        })
+        .unwrap();
+        assert_eq!(progress.first(), Some(&(0, 20)));
+        assert_eq!(progress.last(), Some(&(20, 20)));
+        assert!(library.is_complete(novel.id, voice.id));
+        // 每批最多 8 段
+        assert_eq!(server.state.lock().unwrap().requests.iter().filter(|r| *r == "POST /api/infer/submit").count(), 3);
+
+        // 重新加载后，服务器不可达时仍可从本地播放
+        let backend = OfflineBackend::new(
+            Backend::new(
+                ApiClient::new("http://127.0.0.1:9/api".to_string())
+                    .with_retry_policy(RetryPolicy { max_attempts: 1, ..Default::default() }),
+            ),
+            OfflineLibrary::open(Some(dir.to_path_buf())),
+        );
+        assert_eq!(backend.list_novels().unwrap()[0].id, novel.id);
+        let session = backend.play(novel.id, voice.id, 0).unwrap();
+        assert!(is_offline_session(&session.session_id));
+        let tasks = backend.submit_infer(&session.session_id, vec![0, 1]).unwrap().tasks;
+        assert!(tasks.iter().all(|t| t.state == "ready"));
+        let segments = backend.get_novel_segments(novel.id, Some(19), Some(10)).unwrap();
+        assert_eq!((segments.total, segments.segments[0].content.as_str()), (20, "第20段"));
+        let audio = backend.get_audio(novel.id, 19, voice.id, &[]).unwrap();
+        assert_eq!(AudioFormat::sniff(&audio), Some(AudioFormat::Wav));
+    }
+}
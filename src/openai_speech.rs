@@ -0,0 +1,737 @@
+//! OpenAI 兼容语音合成后端 - `/v1/audio/speech`
+//!
+//! 服务器只提供逐段合成，其余功能在客户端完成:
+//! - 上传的小说在本地分段，保存在 `<配置目录>/rovel-desk/libraries/<key>/<novel_id>.json`
+//! - 音色为配置中的 voice 名称列表，不支持上传 / 删除
+//! - session 和推理任务保存在内存中，由后台线程按提交顺序逐段合成，
+//!   状态变化（`inferring` / `ready` / `failed`）通过 `take_events` 上报
+//! - 合成好的音频缓存在内存中，超过上限时淘汰离当前段最远的
+//!
+//! 请求格式: `POST {server_url}/v1/audio/speech`，
+//! `{"model": ..., "input": ..., "voice": ..., "response_format": "wav"}`，返回音频数据。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::api::{
+    convert_to_utf8, ApiClient, ApiError, ApiResult, ChangeVoiceResponse, CloseSessionResponse,
+    HttpTimeouts, NovelResponse, PlayResponse, QueryTaskStatusResponse, RetryObserver, SeekResponse,
+    SegmentResponse, SegmentsResponse, SubmitInferResponse, TaskInfo, TaskStatusInfo, VoiceResponse,
+    WsEvent,
+};
+use crate::audio_format::AudioFormat;
+use crate::backend::RovelBackend;
+use crate::config::{app_config_dir, ServerProfile};
+use crate::tls;
+use crate::upload_state::fingerprint;
+
+/// 内存中最多缓存的音频段数
+const MAX_CACHED_SEGMENTS: usize = 64;
+
+/// OpenAI 兼容后端设置（profile 的 `backend` 字段）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenAiSpeechSettings {
+    /// 模型名称，例如 `tts-1`
+    pub model: String,
+    /// 可用的音色名称（请求中的 `voice` 参数）
+    pub voices: Vec<String>,
+    /// API key，以 `Authorization: Bearer` 发送
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// 客户端分段时每段的最大字数
+    pub max_segment_chars: usize,
+}
+
+impl Default for OpenAiSpeechSettings {
+    fn default() -> Self {
+        Self {
+            model: "tts-1".to_string(),
+            voices: ["alloy", "echo", "fable", "onyx", "nova", "shimmer"]
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+            api_key: None,
+            max_segment_chars: 200,
+        }
+    }
+}
+
+// ============================================================================
+// 客户端分段
+// ============================================================================
+
+/// 按段落分段，超长段落在句末标点处切分，单句仍超长时按字数硬切
+pub fn split_segments(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut segments = Vec::new();
+    for paragraph in text.lines().map(str::trim).filter(|p| !p.is_empty()) {
+        if paragraph.chars().count() <= max_chars {
+            segments.push(paragraph.to_string());
+            continue;
+        }
+        let mut current = String::new();
+        for sentence in split_sentences(paragraph) {
+            if !current.is_empty() && current.chars().count() + sentence.chars().count() > max_chars {
+                segments.push(std::mem::take(&mut current));
+            }
+            if sentence.chars().count() > max_chars {
+                let chars: Vec<char> = sentence.chars().collect();
+                for chunk in chars.chunks(max_chars) {
+                    segments.push(chunk.iter().collect());
+                }
+            } else {
+                current.push_str(sentence);
+            }
+        }
+        if !current.is_empty() {
+            segments.push(current);
+        }
+    }
+    segments
+}
+
+/// 在句末标点（含其后的引号）之后切分
+fn split_sentences(paragraph: &str) -> Vec<&str> {
+    const TERMINATORS: &[char] = &['。', '！', '？', '!', '?', '；', ';', '…'];
+    const CLOSERS: &[char] = &['”', '’', '」', '』', '"', '\''];
+
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = paragraph.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !TERMINATORS.contains(&c) {
+            continue;
+        }
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, next)) = chars.peek() {
+            if TERMINATORS.contains(&next) || CLOSERS.contains(&next) {
+                end = j + next.len_utf8();
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        sentences.push(&paragraph[start..end]);
+        start = end;
+    }
+    if start < paragraph.len() {
+        sentences.push(&paragraph[start..]);
+    }
+    sentences
+}
+
+// ============================================================================
+// 本地书库
+// ============================================================================
+
+/// 本地保存的小说及其分段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LocalNovel {
+    novel: NovelResponse,
+    segments: Vec<String>,
+}
+
+/// 本地书库（每本小说一个 JSON 文件），`dir` 为 `None` 时只保存在内存中
+struct LocalLibrary {
+    dir: Option<PathBuf>,
+    novels: Mutex<Vec<LocalNovel>>,
+}
+
+impl LocalLibrary {
+    fn open(dir: Option<PathBuf>) -> Self {
+        let mut novels: Vec<LocalNovel> = dir
+            .as_deref()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect();
+        novels.sort_by(|a, b| a.novel.created_at.cmp(&b.novel.created_at));
+        Self {
+            dir,
+            novels: Mutex::new(novels),
+        }
+    }
+
+    /// 默认位置：`<配置目录>/rovel-desk/libraries/<服务器地址的指纹>`
+    fn default_dir(server_url: &str) -> Option<PathBuf> {
+        app_config_dir().map(|dir| dir.join("libraries").join(format!("{:016x}", fingerprint(server_url.as_bytes()))))
+    }
+
+    fn list(&self) -> Vec<NovelResponse> {
+        self.lock().iter().map(|n| n.novel.clone()).collect()
+    }
+
+    fn get(&self, id: Uuid) -> Option<LocalNovel> {
+        self.lock().iter().find(|n| n.novel.id == id).cloned()
+    }
+
+    fn add(&self, novel: LocalNovel) -> ApiResult<()> {
+        if let Some(dir) = &self.dir {
+            let write = || -> std::io::Result<()> {
+                std::fs::create_dir_all(dir)?;
+                let content = serde_json::to_string(&novel)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                std::fs::write(dir.join(format!("{}.json", novel.novel.id)), content)
+            };
+            write().map_err(|e| ApiError::Io(e.to_string()))?;
+        }
+        self.lock().push(novel);
+        Ok(())
+    }
+
+    fn remove(&self, id: Uuid) -> bool {
+        let mut novels = self.lock();
+        let before = novels.len();
+        novels.retain(|n| n.novel.id != id);
+        if let Some(dir) = &self.dir {
+            let _ = std::fs::remove_file(dir.join(format!("{}.json", id)));
+        }
+        novels.len() != before
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<LocalNovel>> {
+        self.novels.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// ============================================================================
+// 合成任务
+// ============================================================================
+
+/// 音频缓存的键: (novel_id, voice_id, segment_index)
+type AudioKey = (Uuid, Uuid, u32);
+
+#[derive(Debug, Clone)]
+struct LocalSession {
+    novel_id: Uuid,
+    voice_id: Uuid,
+    current_index: u32,
+}
+
+/// 一个待合成的分段
+struct SynthJob {
+    session_id: String,
+    task_id: String,
+    key: AudioKey,
+    text: String,
+    voice: String,
+}
+
+/// session、任务、音频缓存和待上报事件（与合成线程共享）
+#[derive(Default)]
+struct SynthState {
+    sessions: HashMap<String, LocalSession>,
+    tasks: HashMap<String, (AudioKey, TaskStatusInfo)>,
+    audio: HashMap<AudioKey, Vec<u8>>,
+    events: Vec<WsEvent>,
+}
+
+impl SynthState {
+    /// 取消 session 中尚未完成的任务，返回取消数量
+    fn cancel_session_tasks(&mut self, session_id: &str, keep: impl Fn(&AudioKey) -> bool) -> usize {
+        let Some(session) = self.sessions.get(session_id) else { return 0 };
+        let (novel_id, voice_id) = (session.novel_id, session.voice_id);
+        let mut cancelled = 0;
+        for (key, task) in self.tasks.values_mut() {
+            let unfinished = task.state == "pending" || task.state == "inferring";
+            if unfinished && key.0 == novel_id && key.1 == voice_id && !keep(key) {
+                task.state = "cancelled".to_string();
+                cancelled += 1;
+            }
+        }
+        self.tasks.retain(|_, (_, task)| task.state != "cancelled");
+        cancelled
+    }
+
+    /// 缓存音频，超出上限时淘汰离新分段最远的
+    fn cache_audio(&mut self, key: AudioKey, data: Vec<u8>) {
+        self.audio.insert(key, data);
+        while self.audio.len() > MAX_CACHED_SEGMENTS {
+            let farthest = self
+                .audio
+                .keys()
+                .max_by_key(|k| (k.0 != key.0 || k.1 != key.1, k.2.abs_diff(key.2)))
+                .copied();
+            match farthest {
+                Some(k) => self.audio.remove(&k),
+                None => break,
+            };
+        }
+    }
+
+    fn set_task_state(&mut self, job: &SynthJob, state: &str, duration_ms: Option<u32>, error: Option<String>) {
+        if let Some((_, task)) = self.tasks.get_mut(&job.task_id) {
+            task.state = state.to_string();
+            task.error = error.clone();
+        }
+        self.events.push(WsEvent::TaskStateChanged {
+            session_id: job.session_id.clone(),
+            task_id: job.task_id.clone(),
+            segment_index: job.key.2,
+            state: state.to_string(),
+            duration_ms,
+            error,
+        });
+    }
+}
+
+/// 调用 `/v1/audio/speech` 合成一段
+struct Synthesizer {
+    agent: ureq::Agent,
+    speech_url: String,
+    model: String,
+    api_key: Option<String>,
+    timeout: Duration,
+}
+
+impl Synthesizer {
+    fn synthesize(&self, text: &str, voice: &str) -> ApiResult<Vec<u8>> {
+        let mut request = self.agent.post(&self.speech_url).timeout(self.timeout);
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let resp = request
+            .send_json(serde_json::json!({
+                "model": self.model,
+                "input": text,
+                "voice": voice,
+                "response_format": "wav",
+            }))
+            .map_err(ApiError::from)?;
+        let mut bytes = Vec::new();
+        resp.into_reader().read_to_end(&mut bytes).map_err(ApiError::from_body_error)?;
+        Ok(bytes)
+    }
+
+    /// 合成线程：按提交顺序逐段合成，已取消的任务跳过
+    fn run(self, jobs: Receiver<SynthJob>, state: Arc<Mutex<SynthState>>) {
+        for job in jobs {
+            {
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                let pending = state.tasks.get(&job.task_id).is_some_and(|(_, t)| t.state == "pending");
+                if !pending {
+                    continue;
+                }
+                state.set_task_state(&job, "inferring", None, None);
+            }
+
+            let result = self.synthesize(&job.text, &job.voice);
+            // 在锁外解码，按样本数计算时长
+            let duration_ms = result.as_ref().ok().and_then(|audio| audio_duration_ms(audio));
+
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            if !state.tasks.contains_key(&job.task_id) {
+                // 合成期间被取消
+                continue;
+            }
+            match result {
+                Ok(audio) => {
+                    tracing::info!("Synthesized segment {} ({} bytes)", job.key.2, audio.len());
+                    state.cache_audio(job.key, audio);
+                    state.set_task_state(&job, "ready", duration_ms, None);
+                }
+                Err(e) => {
+                    tracing::warn!("Speech synthesis failed for segment {}: {}", job.key.2, e);
+                    state.set_task_state(&job, "failed", None, Some(e.to_string()));
+                }
+            }
+        }
+    }
+}
+
+/// 解码合成的音频，按样本数计算时长（毫秒）；无法解码时返回 `None`
+fn audio_duration_ms(audio: &[u8]) -> Option<u32> {
+    use rodio::Source;
+
+    let decoder = rodio::Decoder::new(std::io::Cursor::new(audio.to_vec())).ok()?;
+    let channels = u64::from(decoder.channels().max(1));
+    let sample_rate = u64::from(decoder.sample_rate().max(1));
+    let frames = decoder.count() as u64 / channels;
+    u32::try_from(frames * 1000 / sample_rate).ok()
+}
+
+// ============================================================================
+// Backend
+// ============================================================================
+
+/// OpenAI 兼容语音合成后端（克隆后共享书库、任务和合成线程）
+#[derive(Clone)]
+pub struct OpenAiSpeechBackend {
+    settings: Arc<OpenAiSpeechSettings>,
+    library: Arc<LocalLibrary>,
+    state: Arc<Mutex<SynthState>>,
+    jobs: Arc<Mutex<Sender<SynthJob>>>,
+}
+
+impl OpenAiSpeechBackend {
+    pub fn new(
+        server_url: &str,
+        settings: OpenAiSpeechSettings,
+        agent: ureq::Agent,
+        timeouts: &HttpTimeouts,
+        library_dir: Option<PathBuf>,
+    ) -> Self {
+        let state = Arc::new(Mutex::new(SynthState::default()));
+        let (jobs, job_rx) = mpsc::channel();
+        let synthesizer = Synthesizer {
+            agent,
+            speech_url: format!("{}/v1/audio/speech", server_url.trim_end_matches('/')),
+            model: settings.model.clone(),
+            api_key: settings.api_key.clone(),
+            timeout: Duration::from_secs(timeouts.audio_secs.max(1)),
+        };
+        let worker_state = state.clone();
+        std::thread::spawn(move || synthesizer.run(job_rx, worker_state));
+
+        Self {
+            settings: Arc::new(settings),
+            library: Arc::new(LocalLibrary::open(library_dir)),
+            state,
+            jobs: Arc::new(Mutex::new(jobs)),
+        }
+    }
+
+    /// 使用 profile 的服务器地址和 TLS 设置，书库放在默认位置
+    pub fn from_profile(profile: &ServerProfile, settings: &OpenAiSpeechSettings, timeouts: &HttpTimeouts) -> Self {
+        let tls = tls::client_config(&profile.tls)
+            .inspect_err(|e| tracing::error!("Invalid TLS settings for {}: {:#}", profile.server_url, e))
+            .or_else(|_| tls::client_config(&Default::default()))
+            .expect("default TLS config");
+        Self::new(
+            &profile.server_url,
+            settings.clone(),
+            ApiClient::build_agent(timeouts, &tls),
+            timeouts,
+            LocalLibrary::default_dir(&profile.server_url),
+        )
+    }
+
+    /// 音色 ID 由模型和音色名称推导，重启后保持不变
+    fn voice_id(&self, name: &str) -> Uuid {
+        Uuid::from_u64_pair(fingerprint(self.settings.model.as_bytes()), fingerprint(name.as_bytes()))
+    }
+
+    fn voice_name(&self, id: Uuid) -> Option<&str> {
+        self.settings.voices.iter().map(String::as_str).find(|name| self.voice_id(name) == id)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SynthState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn not_found(what: &str) -> ApiError {
+    ApiError::Server { errno: 404, message: format!("{} not found", what) }
+}
+
+impl RovelBackend for OpenAiSpeechBackend {
+    /// 合成在后台线程中进行，失败以任务状态上报，不经过重试
+    fn observed_by(&self, _observer: RetryObserver) -> Box<dyn RovelBackend> {
+        Box::new(self.clone())
+    }
+
+    fn list_novels(&self) -> ApiResult<Vec<NovelResponse>> {
+        Ok(self.library.list())
+    }
+
+    fn get_novel(&self, id: Uuid) -> ApiResult<NovelResponse> {
+        self.library.get(id).map(|n| n.novel).ok_or_else(|| not_found("novel"))
+    }
+
+    fn get_novel_segments(&self, novel_id: Uuid, start: Option<usize>, limit: Option<usize>) -> ApiResult<SegmentsResponse> {
+        let novel = self.library.get(novel_id).ok_or_else(|| not_found("novel"))?;
+        let segments = novel
+            .segments
+            .iter()
+            .enumerate()
+            .skip(start.unwrap_or(0))
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(index, content)| SegmentResponse {
+                index,
+                content: content.clone(),
+                char_count: content.chars().count(),
+            })
+            .collect();
+        Ok(SegmentsResponse {
+            novel_id,
+            total: novel.segments.len(),
+            segments,
+        })
+    }
+
+    fn upload_novel(
+        &self,
+        title: &str,
+        file_path: &Path,
+        cancel: &AtomicBool,
+        on_progress: &mut dyn FnMut(u64, u64),
+    ) -> ApiResult<NovelResponse> {
+        let bytes = std::fs::read(file_path).map_err(|e| ApiError::Io(e.to_string()))?;
+        if cancel.load(Ordering::Relaxed) {
+            return Err(ApiError::Cancelled);
+        }
+        let segments = split_segments(&convert_to_utf8(&bytes), self.settings.max_segment_chars);
+        let novel = NovelResponse {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            total_segments: segments.len(),
+            status: "ready".to_string(),
+            created_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            is_temporary: false,
+        };
+        self.library.add(LocalNovel { novel: novel.clone(), segments })?;
+        on_progress(bytes.len() as u64, bytes.len() as u64);
+        Ok(novel)
+    }
+
+    fn delete_novel(&self, id: Uuid) -> ApiResult<()> {
+        if !self.library.remove(id) {
+            return Err(not_found("novel"));
+        }
+        self.lock().audio.retain(|key, _| key.0 != id);
+        Ok(())
+    }
+
+    fn list_voices(&self) -> ApiResult<Vec<VoiceResponse>> {
+        Ok(self
+            .settings
+            .voices
+            .iter()
+            .map(|name| VoiceResponse {
+                id: self.voice_id(name),
+                name: name.clone(),
+                description: Some(self.settings.model.clone()),
+                created_at: String::new(),
+            })
+            .collect())
+    }
+
+    fn upload_voice(
+        &self,
+        _name: &str,
+        _description: Option<&str>,
+        _file_path: &Path,
+        _cancel: &AtomicBool,
+        _on_progress: &mut dyn FnMut(u64, u64),
+    ) -> ApiResult<VoiceResponse> {
+        Err(ApiError::Unsupported("上传音色".to_string()))
+    }
+
+    fn delete_voice(&self, _id: Uuid) -> ApiResult<()> {
+        Err(ApiError::Unsupported("删除音色".to_string()))
+    }
+
+    fn play(&self, novel_id: Uuid, voice_id: Uuid, start_index: u32) -> ApiResult<PlayResponse> {
+        if self.library.get(novel_id).is_none() {
+            return Err(not_found("novel"));
+        }
+        if self.voice_name(voice_id).is_none() {
+            return Err(not_found("voice"));
+        }
+        let session_id = Uuid::new_v4().to_string();
+        self.lock().sessions.insert(
+            session_id.clone(),
+            LocalSession { novel_id, voice_id, current_index: start_index },
+        );
+        Ok(PlayResponse { session_id, novel_id, voice_id, current_index: start_index })
+    }
+
+    fn seek(&self, session_id: &str, segment_index: u32) -> ApiResult<SeekResponse> {
+        let mut state = self.lock();
+        if !state.sessions.contains_key(session_id) {
+            return Err(not_found("session"));
+        }
+        // 跳转后旧位置附近尚未合成的分段不再需要
+        let cancelled_tasks = state.cancel_session_tasks(session_id, |key| key.2 >= segment_index);
+        if let Some(session) = state.sessions.get_mut(session_id) {
+            session.current_index = segment_index;
+        }
+        Ok(SeekResponse {
+            session_id: session_id.to_string(),
+            current_index: segment_index,
+            cancelled_tasks,
+        })
+    }
+
+    fn change_voice(&self, session_id: &str, voice_id: Uuid) -> ApiResult<ChangeVoiceResponse> {
+        if self.voice_name(voice_id).is_none() {
+            return Err(not_found("voice"));
+        }
+        let mut state = self.lock();
+        if !state.sessions.contains_key(session_id) {
+            return Err(not_found("session"));
+        }
+        let cancelled_tasks = state.cancel_session_tasks(session_id, |_| false);
+        if let Some(session) = state.sessions.get_mut(session_id) {
+            session.voice_id = voice_id;
+        }
+        Ok(ChangeVoiceResponse {
+            session_id: session_id.to_string(),
+            voice_id,
+            cancelled_tasks,
+        })
+    }
+
+    fn close_session(&self, session_id: &str) -> ApiResult<CloseSessionResponse> {
+        let mut state = self.lock();
+        state.cancel_session_tasks(session_id, |_| false);
+        state.sessions.remove(session_id);
+        Ok(CloseSessionResponse { session_id: session_id.to_string() })
+    }
+
+    fn submit_infer(&self, session_id: &str, segment_indices: Vec<u32>) -> ApiResult<SubmitInferResponse> {
+        let session = self.lock().sessions.get(session_id).cloned().ok_or_else(|| not_found("session"))?;
+        let novel = self.library.get(session.novel_id).ok_or_else(|| not_found("novel"))?;
+        let voice = self.voice_name(session.voice_id).ok_or_else(|| not_found("voice"))?.to_string();
+
+        let mut state = self.lock();
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let mut tasks = Vec::new();
+        for segment_index in segment_indices {
+            let Some(text) = novel.segments.get(segment_index as usize) else { continue };
+            let key = (session.novel_id, session.voice_id, segment_index);
+
+            // 同一分段已有未完成的任务时直接返回，不重复合成
+            let existing = state
+                .tasks
+                .iter()
+                .find(|(_, (k, t))| *k == key && (t.state == "pending" || t.state == "inferring"))
+                .map(|(id, (_, t))| (id.clone(), t.state.clone()));
+            if let Some((task_id, state_name)) = existing {
+                tasks.push(TaskInfo { task_id, segment_index, state: state_name });
+                continue;
+            }
+
+            let task_id = Uuid::new_v4().to_string();
+            let ready = state.audio.contains_key(&key);
+            let task_state = if ready { "ready" } else { "pending" };
+            state.tasks.insert(
+                task_id.clone(),
+                (key, TaskStatusInfo {
+                    task_id: task_id.clone(),
+                    segment_index,
+                    state: task_state.to_string(),
+                    error: None,
+                }),
+            );
+            if !ready {
+                let _ = jobs.send(SynthJob {
+                    session_id: session_id.to_string(),
+                    task_id: task_id.clone(),
+                    key,
+                    text: text.clone(),
+                    voice: voice.clone(),
+                });
+            }
+            tasks.push(TaskInfo { task_id, segment_index, state: task_state.to_string() });
+        }
+        Ok(SubmitInferResponse { tasks })
+    }
+
+    fn query_task_status(&self, task_ids: Vec<String>) -> ApiResult<QueryTaskStatusResponse> {
+        let state = self.lock();
+        let tasks = task_ids
+            .iter()
+            .filter_map(|id| state.tasks.get(id).map(|(_, task)| task.clone()))
+            .collect();
+        Ok(QueryTaskStatusResponse { tasks })
+    }
+
+    /// 合成时固定请求 WAV，忽略 `formats`
+    fn get_audio(&self, novel_id: Uuid, segment_index: u32, voice_id: Uuid, _formats: &[AudioFormat]) -> ApiResult<Vec<u8>> {
+        self.lock()
+            .audio
+            .get(&(novel_id, voice_id, segment_index))
+            .cloned()
+            .ok_or(ApiError::NotReady)
+    }
+
+    fn uses_websocket(&self) -> bool {
+        false
+    }
+
+    fn take_events(&self) -> Vec<WsEvent> {
+        std::mem::take(&mut self.lock().events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_util::TempDir;
+    use crate::mock_server::MockRovelServer;
+    use std::time::Instant;
+
+    #[test]
+    fn splits_long_paragraphs_at_sentence_ends() {
+        let text = "第一段。\n\n  第二段很长。这是第二句！第三句？“引号结尾。”最后\n";
+        assert_eq!(
+            split_segments(text, 12),
+            vec!["第一段。", "第二段很长。这是第二句！", "第三句？“引号结尾。”", "最后"]
+        );
+        // 没有标点的超长句按字数切分
+        assert_eq!(split_segments("一二三四五六七", 3), vec!["一二三", "四五六", "七"]);
+    }
+
+    #[test]
+    fn synthesizes_submitted_segments_and_reports_events() {
+        let server = MockRovelServer::start();
+        let backend = OpenAiSpeechBackend::new(
+            &server.server_url(),
+            OpenAiSpeechSettings { max_segment_chars: 10, ..Default::default() },
+            ureq::Agent::new(),
+            &HttpTimeouts::default(),
+            None,
+        );
+        let dir = TempDir::new("speech");
+        let path = dir.join("novel.txt");
+        std::fs::write(&path, "第一段。\n第二段。\n第三段。\n").unwrap();
+        let novel = backend.upload_novel("本地", &path, &AtomicBool::new(false), &mut |_, _| {}).unwrap();
+        assert_eq!(novel.total_segments, 3);
+        assert_eq!(backend.list_novels().unwrap().len(), 1);
+
+        let voice = backend.list_voices().unwrap().remove(0);
+        let session = backend.play(novel.id, voice.id, 0).unwrap();
+        assert_eq!(backend.get_audio(novel.id, 0, voice.id, &[]), Err(ApiError::NotReady));
+        let submitted = backend.submit_infer(&session.session_id, vec![0, 1, 7]).unwrap();
+        // 超出范围的分段被忽略
+        assert_eq!(submitted.tasks.iter().map(|t| t.segment_index).collect::<Vec<_>>(), vec![0, 1]);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut events = Vec::new();
+        while events.iter().filter(|e| matches!(e, WsEvent::TaskStateChanged { state, .. } if state == "ready")).count() < 2 {
+            assert!(Instant::now() < deadline, "timed out waiting for synthesis: {:?}", events);
+            events.extend(backend.take_events());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(matches!(&events[0], WsEvent::TaskStateChanged { segment_index: 0, state, .. } if state == "inferring"));
+        // mock 服务器每字合成 50ms 音频
+        assert!(events.iter().any(|e| matches!(
+            e,
+            WsEvent::TaskStateChanged { segment_index: 0, state, duration_ms: Some(200), .. } if state == "ready"
+        )));
+        assert_eq!(&backend.get_audio(novel.id, 0, voice.id, &[]).unwrap()[..4], b"RIFF");
+
+        let requests = &server.state.lock().unwrap().speech_requests;
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["input"], "第一段。");
+        assert_eq!(requests[0]["voice"], voice.name.as_str());
+
+        // 已合成的分段再次提交时直接返回 ready
+        let resubmitted = backend.submit_infer(&session.session_id, vec![0]).unwrap();
+        assert_eq!(resubmitted.tasks[0].state, "ready");
+    }
+}
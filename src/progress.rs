@@ -0,0 +1,161 @@
+//! 收听进度 - 按小说保存最后播放的段落、音色、播放速度和时间
+//!
+//! 保存在 `<配置目录>/rovel-desk/progress/<服务器地址的指纹>.json`，
+//! 不同服务器的小说 ID 互不干扰。播放切换段落时写入，应用重启后
+//! 小说列表显示「继续收听」，播放时从保存的段落开始。
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::config::app_config_dir;
+use crate::fs_util::write_atomic;
+use crate::upload_state::fingerprint;
+
+/// 一本小说的收听进度
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NovelProgress {
+    /// 最后使用的音色
+    pub voice_id: Uuid,
+    /// 最后播放的段落；听完后为 `total_segments`
+    pub segment_index: u32,
+    pub total_segments: usize,
+    /// 播放速度（倍数）
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    /// 最后收听时间（Unix 秒）
+    pub updated_at: u64,
+}
+
+fn default_speed() -> f32 {
+    1.0
+}
+
+impl NovelProgress {
+    /// 是否已听完最后一段
+    pub fn is_finished(&self) -> bool {
+        self.segment_index as usize >= self.total_segments
+    }
+
+    /// 继续播放的起始段落，听完后从头开始
+    pub fn start_index(&self) -> u32 {
+        if self.is_finished() {
+            0
+        } else {
+            self.segment_index
+        }
+    }
+}
+
+/// 收听进度资源（切换服务器时替换）
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ReadingProgress {
+    /// `None` 时只保存在内存中
+    path: Option<PathBuf>,
+    novels: HashMap<Uuid, NovelProgress>,
+}
+
+impl ReadingProgress {
+    /// 读取进度文件，文件不存在或损坏时从空白开始
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let novels = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| match serde_json::from_str(&content) {
+                Ok(novels) => Some(novels),
+                Err(e) => {
+                    tracing::warn!("Ignoring corrupt reading progress {:?}: {}", path, e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self { path, novels }
+    }
+
+    /// 默认位置：`<配置目录>/rovel-desk/progress/<服务器地址的指纹>.json`
+    pub fn default_path(server_url: &str) -> Option<PathBuf> {
+        app_config_dir()
+            .map(|dir| dir.join("progress").join(format!("{:016x}.json", fingerprint(server_url.as_bytes()))))
+    }
+
+    pub fn get(&self, novel_id: Uuid) -> Option<&NovelProgress> {
+        self.novels.get(&novel_id)
+    }
+
+    /// `novel_ids` 中最近收听且未听完的小说
+    pub fn latest(&self, novel_ids: impl IntoIterator<Item = Uuid>) -> Option<(Uuid, NovelProgress)> {
+        novel_ids
+            .into_iter()
+            .filter_map(|id| self.novels.get(&id).map(|progress| (id, *progress)))
+            .filter(|(_, progress)| !progress.is_finished())
+            .max_by_key(|(_, progress)| progress.updated_at)
+    }
+
+    /// 播放小说时的起始段落
+    pub fn start_index(&self, novel_id: Uuid) -> u32 {
+        self.get(novel_id).map_or(0, NovelProgress::start_index)
+    }
+
+    /// 小说上次使用的播放速度，没有记录时为原速
+    pub fn speed(&self, novel_id: Uuid) -> f32 {
+        self.get(novel_id).map_or(1.0, |progress| progress.speed)
+    }
+
+    /// 记录进度并写入磁盘
+    pub fn record(&mut self, novel_id: Uuid, voice_id: Uuid, segment_index: u32, total_segments: usize, speed: f32) {
+        let updated_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.novels.insert(novel_id, NovelProgress { voice_id, segment_index, total_segments, speed, updated_at });
+        self.save();
+    }
+
+    /// 删除小说的进度
+    pub fn remove(&mut self, novel_id: Uuid) {
+        if self.novels.remove(&novel_id).is_some() {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else { return };
+        let result = (|| -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            write_atomic(path, &serde_json::to_vec_pretty(&self.novels)?)
+        })();
+        if let Err(e) = result {
+            tracing::warn!("Failed to save reading progress {:?}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_util::TempDir;
+
+    #[test]
+    fn progress_survives_reopen_and_restarts_finished_novels() {
+        let dir = TempDir::new("progress");
+        let path = dir.join("progress.json");
+        let (novel, finished, voice) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let mut progress = ReadingProgress::open(Some(path.clone()));
+        progress.record(novel, voice, 42, 100, 1.5);
+        progress.record(finished, voice, 10, 10, 1.0);
+
+        let reopened = ReadingProgress::open(Some(path.clone()));
+        assert_eq!(reopened.get(novel).map(|p| (p.voice_id, p.segment_index)), Some((voice, 42)));
+        assert_eq!(reopened.start_index(novel), 42);
+        assert_eq!(reopened.speed(novel), 1.5);
+        assert_eq!(reopened.speed(Uuid::new_v4()), 1.0);
+        assert_eq!(reopened.start_index(finished), 0);
+        assert_eq!(reopened.start_index(Uuid::new_v4()), 0);
+        assert_eq!(reopened.latest([novel, finished]).map(|(id, _)| id), Some(novel));
+    }
+}
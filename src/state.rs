@@ -8,10 +8,16 @@
 use bevy::prelude::*;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::api::{NovelResponse, VoiceResponse, SegmentResponse, TaskInfo, WsEvent};
+use crate::api::{ApiError, NovelResponse, SegmentAudio, VoiceResponse, SegmentResponse, TaskInfo, WsEvent};
+use crate::audio::{LoopRegion, PlaybackPosition};
+use crate::audio_format::AudioFormat;
+use crate::audio_stream::AudioStream;
+use crate::export::{ExportSettings, ExportSummary};
 
 /// 应用视图状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, States, Hash)]
@@ -26,6 +32,10 @@ pub enum AppView {
 pub enum FilePickerType {
     Novel,
     Voice,
+    /// 有声书导出目录
+    ExportDir,
+    /// 书签导出的 Markdown 文件
+    BookmarksFile,
 }
 
 /// 上传对话框状态
@@ -64,6 +74,189 @@ impl UploadDialogState {
     }
 }
 
+/// 上传类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadKind {
+    Novel,
+    Voice,
+}
+
+/// 进行中的上传任务
+#[derive(Debug, Clone)]
+pub struct UploadTask {
+    pub kind: UploadKind,
+    /// 小说标题或音色名称
+    pub label: String,
+    /// 已发送字节数
+    pub sent: u64,
+    /// 请求体总字节数（开始发送前为 0）
+    pub total: u64,
+    /// 取消标记，由上传线程轮询
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl UploadTask {
+    pub fn new(kind: UploadKind, label: impl Into<String>) -> Self {
+        Self {
+            kind,
+            label: label.into(),
+            sent: 0,
+            total: 0,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// 上传进度 (0.0 - 1.0)
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.sent as f32 / self.total as f32
+        }
+    }
+}
+
+/// 进行中的离线下载
+#[derive(Debug, Clone, Default)]
+pub struct DownloadTask {
+    /// 已下载的段数
+    pub downloaded: usize,
+    /// 总段数（段落文本下载完成前为 0）
+    pub total: usize,
+    /// 取消标记，由下载线程轮询
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl DownloadTask {
+    /// 下载进度 (0.0 - 1.0)
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.downloaded as f32 / self.total as f32
+        }
+    }
+}
+
+/// 导出有声书对话框状态
+#[derive(Default)]
+pub struct ExportDialogState {
+    /// 要导出的小说（`None` 时不显示对话框）
+    pub novel: Option<NovelResponse>,
+    pub voice_id: Option<Uuid>,
+    /// 输出目录
+    pub output_dir: Option<PathBuf>,
+    /// 是否正在选择目录
+    pub picking_dir: bool,
+}
+
+impl ExportDialogState {
+    pub fn open(&mut self, novel: NovelResponse, voice_id: Uuid) {
+        self.novel = Some(novel);
+        self.voice_id = Some(voice_id);
+    }
+
+    pub fn close(&mut self) {
+        self.novel = None;
+        self.voice_id = None;
+        self.picking_dir = false;
+    }
+}
+
+/// 有声书导出任务
+#[derive(Debug, Clone, Default)]
+pub struct ExportTask {
+    /// 已处理的段数
+    pub done: usize,
+    pub total: usize,
+    pub cancel: Arc<AtomicBool>,
+    /// 导出完成后的结果
+    pub finished: Option<ExportSummary>,
+}
+
+impl ExportTask {
+    /// 导出进度 (0.0 - 1.0)
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+}
+
+/// 播放器书签侧栏状态
+#[derive(Default)]
+pub struct BookmarkPanelState {
+    pub show: bool,
+    /// 正在编辑笔记的段落
+    pub editing: Option<u32>,
+    pub note_draft: String,
+    /// 是否正在选择导出文件
+    pub picking_file: bool,
+}
+
+/// 设置对话框状态
+#[derive(Default)]
+pub struct SettingsDialogState {
+    /// 是否显示设置对话框
+    pub show: bool,
+    /// 新 profile 名称
+    pub new_profile_name: String,
+    /// 新 profile 服务器地址
+    pub new_profile_url: String,
+    /// 新 profile 自定义 CA 证书路径（可选）
+    pub new_profile_ca: String,
+    /// 新 profile 证书 SHA-256 指纹（可选）
+    pub new_profile_fingerprint: String,
+}
+
+impl SettingsDialogState {
+    pub fn reset(&mut self) {
+        self.show = false;
+        self.new_profile_name.clear();
+        self.new_profile_url.clear();
+        self.new_profile_ca.clear();
+        self.new_profile_fingerprint.clear();
+    }
+}
+
+/// 登录对话框状态
+#[derive(Default)]
+pub struct LoginDialogState {
+    /// 是否显示登录对话框
+    pub show: bool,
+    pub username: String,
+    pub password: String,
+    /// 提示信息（如 token 已过期、登录失败原因）
+    pub message: Option<String>,
+    /// 登录请求进行中
+    pub pending: bool,
+}
+
+impl LoginDialogState {
+    /// 打开登录对话框并显示提示
+    pub fn open(&mut self, message: Option<String>) {
+        self.show = true;
+        self.message = message;
+    }
+
+    pub fn reset(&mut self) {
+        self.show = false;
+        self.password.clear();
+        self.message = None;
+        self.pending = false;
+    }
+}
+
+/// 切换服务器 profile 事件
+#[derive(Event)]
+pub struct SwitchProfileEvent {
+    pub profile_name: String,
+    /// 即使已是活动 profile 也重建连接（活动 profile 的地址或 TLS 设置被修改时）
+    pub reconnect: bool,
+}
+
 /// 文件选择请求事件
 #[derive(Event)]
 pub struct FilePickerRequest {
@@ -92,6 +285,8 @@ pub struct CurrentSession {
     pub voice_id: Uuid,
     /// 当前播放段落索引
     pub current_index: u32,
+    /// 播放速度（倍数），按小说保存在收听进度中
+    pub speed: f32,
 }
 
 // ============================================================================
@@ -155,6 +350,11 @@ impl TaskManager {
         self.tasks.clear();
     }
 
+    /// 丢弃尚未就绪的任务（服务端 seek 时取消了它们）
+    pub fn drop_unfinished(&mut self) {
+        self.tasks.retain(|_, task| task.state == TaskState::Ready);
+    }
+
     /// 计算需要预取的段落索引（只返回不存在的任务）
     pub fn calculate_prefetch_range(&self, current_index: u32, total_segments: u32) -> Vec<u32> {
         let mut needed = Vec::new();
@@ -249,6 +449,69 @@ impl TaskManager {
     }
 }
 
+// ============================================================================
+// Audio Prefetch Buffer
+// ============================================================================
+
+/// 音频缓冲的 key: (novel_id, voice_id, segment_index)
+pub type AudioKey = (Uuid, Uuid, u32);
+
+/// 预取音频的内存缓冲
+///
+/// 同一小说、音色、段落的音频内容不变，因此 seek 或切换 session 后缓冲仍然有效；
+/// 只保留当前小说和音色、当前段及之后的段落。
+#[derive(Default)]
+pub struct AudioPrefetchBuffer {
+    segments: HashMap<AudioKey, SegmentAudio>,
+    /// 已发出批量请求、尚未返回的段落
+    in_flight: HashSet<AudioKey>,
+}
+
+impl AudioPrefetchBuffer {
+    /// 段落既未缓冲也不在请求中
+    pub fn needs_fetch(&self, key: &AudioKey) -> bool {
+        !self.segments.contains_key(key) && !self.in_flight.contains(key)
+    }
+
+    /// 段落音频已在缓冲中
+    pub fn contains(&self, key: &AudioKey) -> bool {
+        self.segments.contains_key(key)
+    }
+
+    /// 记录已发出批量请求的段落
+    pub fn mark_requested(&mut self, novel_id: Uuid, voice_id: Uuid, indices: &[u32]) {
+        self.in_flight.extend(indices.iter().map(|&idx| (novel_id, voice_id, idx)));
+    }
+
+    /// 批量请求结束（成功或失败），清除请求中标记
+    pub fn finish_request(&mut self, novel_id: Uuid, voice_id: Uuid, indices: &[u32]) {
+        for &idx in indices {
+            self.in_flight.remove(&(novel_id, voice_id, idx));
+        }
+    }
+
+    /// 保存预取结果，并丢弃其他小说/音色以及 `keep_from` 之前的段落
+    pub fn insert(&mut self, novel_id: Uuid, voice_id: Uuid, segments: Vec<SegmentAudio>, keep_from: u32) {
+        self.segments
+            .retain(|&(n, v, idx), _| n == novel_id && v == voice_id && idx >= keep_from);
+        for audio in segments {
+            if audio.segment_index >= keep_from {
+                self.segments.insert((novel_id, voice_id, audio.segment_index), audio);
+            }
+        }
+    }
+
+    /// 取出缓冲的音频（播放后不再保留）
+    pub fn take(&mut self, key: &AudioKey) -> Option<SegmentAudio> {
+        self.segments.remove(key)
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.in_flight.clear();
+    }
+}
+
 // ============================================================================
 // V2 WebSocket State
 // ============================================================================
@@ -303,6 +566,61 @@ pub enum PlaybackState {
     Loading,
 }
 
+/// 没有合成时长可参考时，估算收听时长用的每字耗时（约每秒 4 字）
+const DEFAULT_MS_PER_CHAR: f64 = 250.0;
+
+/// 整本小说的收听时长估算（按当前播放速度）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListeningEstimate {
+    /// 从开头到当前播放位置
+    pub elapsed: Duration,
+    /// 从当前播放位置到结尾
+    pub remaining: Duration,
+}
+
+/// 睡眠定时
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepTimer {
+    /// 再播放这么长时间后停止（暂停时不计时）
+    Time { remaining: Duration },
+    /// 播放完这一段后停止
+    AfterSegment(u32),
+    /// 播放到本章最后一段（下一段是章节标题）后停止
+    EndOfChapter,
+}
+
+/// 循环播放模式（语言学习用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// 每段重复播放 `times` 遍后再播下一段，`None` 为一直重复当前段
+    Segment { times: Option<u32> },
+    /// 在第 `start` 到第 `end` 段之间循环
+    Range { start: u32, end: u32 },
+    /// 第 `index` 段内 A、B 两点之间循环，只标记了 A 点时 `b` 为 `None`
+    AB { index: u32, a: Duration, b: Option<Duration> },
+}
+
+impl RepeatMode {
+    /// 由音频线程执行的段内循环，段落范围循环在切换段落时处理
+    pub fn loop_region(&self) -> Option<LoopRegion> {
+        match *self {
+            RepeatMode::Segment { times } => Some(LoopRegion {
+                segment_index: None,
+                start: Duration::ZERO,
+                end: None,
+                repeats: times.map(|times| times.saturating_sub(1)),
+            }),
+            RepeatMode::Range { .. } => None,
+            RepeatMode::AB { index, a, b } => b.filter(|&b| b > a).map(|b| LoopRegion {
+                segment_index: Some(index),
+                start: a,
+                end: Some(b),
+                repeats: None,
+            }),
+        }
+    }
+}
+
 /// 应用状态资源 - V2
 #[derive(Resource, Default)]
 pub struct AppState {
@@ -330,6 +648,12 @@ pub struct AppState {
     pub error: Option<String>,
     /// 上传对话框状态
     pub upload_dialog: UploadDialogState,
+    /// 设置对话框状态
+    pub settings_dialog: SettingsDialogState,
+    /// 导出有声书对话框
+    pub export_dialog: ExportDialogState,
+    /// 登录对话框
+    pub login_dialog: LoginDialogState,
     /// 正在处理中的小说 IDs（用于轮询）
     pub processing_novels: HashSet<Uuid>,
     /// V2: 任务管理器
@@ -338,24 +662,210 @@ pub struct AppState {
     pub ws_state: WsConnectionState,
     /// V2: 等待音频就绪后自动播放
     pub waiting_for_audio: bool,
+    /// 当前段音频的实际传输格式（服务器协商结果）
+    pub current_audio_format: Option<AudioFormat>,
+    /// 预取到内存的音频，切换到下一段时无需网络请求
+    pub audio_buffer: AudioPrefetchBuffer,
+    /// 已排进音频线程播放队列的下一段及其音频格式
+    pub queued_segment: Option<(u32, Option<AudioFormat>)>,
+    /// 当前段的播放位置（由音频线程报告）
+    pub playback_position: Option<PlaybackPosition>,
     /// 需要滚动到的段落索引（用于滑块拖动后同步滚动视图）
     pub scroll_to_segment: Option<usize>,
+    /// 书签侧栏
+    pub bookmark_panel: BookmarkPanelState,
+    /// 睡眠定时
+    pub sleep_timer: Option<SleepTimer>,
+    /// 循环播放
+    pub repeat: Option<RepeatMode>,
+    /// 可重试的失败请求（错误对话框中显示"重试"按钮）
+    pub retry_request: Option<ApiRequest>,
+    /// 自动重试中的请求提示
+    pub retry_status: Option<String>,
+    /// 正在自动重试的请求（收到它的最终响应时清除 `retry_status`）
+    pub retrying_request: Option<ApiRequest>,
+    /// 服务器 profile 的代数，每次切换服务器加一；旧代数请求的响应会被丢弃
+    pub server_generation: u64,
+    /// 进行中的上传（小说上传以临时小说 ID 为 key）
+    pub uploads: HashMap<Uuid, UploadTask>,
+    /// 进行中的离线下载（以小说 ID 为 key）
+    pub downloads: HashMap<Uuid, DownloadTask>,
+    /// 有声书导出（以小说 ID 为 key，完成后保留结果直到用户关闭）
+    pub exports: HashMap<Uuid, ExportTask>,
 }
 
 impl AppState {
     pub fn clear_error(&mut self) {
         self.error = None;
+        self.retry_request = None;
     }
 
     pub fn set_error(&mut self, msg: impl Into<String>) {
         self.error = Some(msg.into());
     }
 
+    /// 取消进行中的上传
+    pub fn cancel_upload(&mut self, upload_id: Uuid) {
+        if let Some(upload) = self.uploads.get(&upload_id) {
+            upload.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 取消进行中的离线下载
+    pub fn cancel_download(&mut self, novel_id: Uuid) {
+        if let Some(download) = self.downloads.get(&novel_id) {
+            download.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 取消进行中的有声书导出
+    pub fn cancel_export(&mut self, novel_id: Uuid) {
+        if let Some(export) = self.exports.get(&novel_id) {
+            export.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 切换服务器后清空与旧服务器相关的数据
+    pub fn reset_server_data(&mut self) {
+        self.server_generation += 1;
+        self.novels.clear();
+        self.voices.clear();
+        self.selected_novel = None;
+        self.selected_voice = None;
+        self.current_session = None;
+        self.segments.clear();
+        self.segment_pagination = SegmentPagination::default();
+        self.current_segment_index = 0;
+        self.playback_state = PlaybackState::Stopped;
+        self.loading = false;
+        self.processing_novels.clear();
+        self.task_manager.clear();
+        self.ws_state = WsConnectionState::Disconnected;
+        self.waiting_for_audio = false;
+        self.current_audio_format = None;
+        self.audio_buffer.clear();
+        self.queued_segment = None;
+        self.playback_position = None;
+        self.scroll_to_segment = None;
+        self.retry_request = None;
+        self.retry_status = None;
+        self.retrying_request = None;
+        for upload in self.uploads.values() {
+            upload.cancel.store(true, Ordering::Relaxed);
+        }
+        self.uploads.clear();
+        for download in self.downloads.values() {
+            download.cancel.store(true, Ordering::Relaxed);
+        }
+        self.downloads.clear();
+        for export in self.exports.values() {
+            export.cancel.store(true, Ordering::Relaxed);
+        }
+        self.exports.clear();
+        self.export_dialog.close();
+        self.bookmark_panel = BookmarkPanelState::default();
+        self.sleep_timer = None;
+        self.repeat = None;
+        self.login_dialog.reset();
+    }
+
     pub fn init_segment_pagination(&mut self, total_segments: usize) {
         self.segment_pagination = SegmentPagination::new(100); // 默认每页100段
         self.segment_pagination.total_segments = total_segments;
     }
 
+    /// 当前段播完后接着播放的段落，已播放到最后一段时为 `None`
+    ///
+    /// 段落范围循环时，播完范围的最后一段回到第一段。
+    pub fn next_segment_index(&self) -> Option<usize> {
+        self.segment_after(self.current_segment_index)
+    }
+
+    fn segment_after(&self, index: usize) -> Option<usize> {
+        if let Some(RepeatMode::Range { start, end }) = self.repeat {
+            if index == end as usize {
+                return Some(start as usize);
+            }
+        }
+        (index + 1 < self.segment_pagination.total_segments).then_some(index + 1)
+    }
+
+    /// 第 `from` 段之后依次要播放的至多 `count` 段，段落范围循环时在范围末尾接回开头
+    pub fn upcoming_segments(&self, from: usize, count: u32) -> Vec<u32> {
+        let mut upcoming = Vec::new();
+        let mut index = from;
+        while upcoming.len() < count as usize {
+            match self.segment_after(index) {
+                Some(next) if next != from && !upcoming.contains(&(next as u32)) => {
+                    upcoming.push(next as u32);
+                    index = next;
+                }
+                _ => break,
+            }
+        }
+        upcoming
+    }
+
+    /// 睡眠定时是否在第 `index` 段播放完后停止
+    ///
+    /// 本章结束模式需要下一段已加载才能判断它是否为章节标题。
+    pub fn sleep_timer_stops_after(&self, index: usize) -> bool {
+        match self.sleep_timer {
+            Some(SleepTimer::AfterSegment(last)) => index as u32 >= last,
+            Some(SleepTimer::EndOfChapter) => self
+                .segments
+                .iter()
+                .find(|s| s.index == index + 1)
+                .is_some_and(|next| crate::export::chapter_heading(&next.content).is_some()),
+            Some(SleepTimer::Time { .. }) | None => false,
+        }
+    }
+
+    /// 按当前播放速度估算整本小说已收听和剩余的时长
+    ///
+    /// 已合成的段落按实际时长（`duration_ms`）计，其余段落按字数估算：每字耗时取已合成段落的平均值，
+    /// 没有时按 `DEFAULT_MS_PER_CHAR`；未加载的段落按已加载段落的平均时长计。
+    /// 当前段内的位置取音频线程报告的播放位置。
+    pub fn listening_estimate(&self) -> Option<ListeningEstimate> {
+        let session = self.current_session.as_ref()?;
+        let total = self.segment_pagination.total_segments;
+        if self.segments.is_empty() || total == 0 {
+            return None;
+        }
+        let current = self.current_segment_index;
+
+        let measured = |s: &SegmentResponse| {
+            let task = self.task_manager.tasks.get(&(s.index as u32))?;
+            task.duration_ms.map(u64::from)
+        };
+        let (measured_ms, measured_chars) = self
+            .segments
+            .iter()
+            .filter_map(|s| Some((measured(s)?, s.char_count as u64)))
+            .fold((0, 0), |(ms, chars), (d, c)| (ms + d, chars + c));
+        let ms_per_char = if measured_chars > 0 {
+            measured_ms as f64 / measured_chars as f64
+        } else {
+            DEFAULT_MS_PER_CHAR
+        };
+        let segment_ms = |s: &SegmentResponse| measured(s).map_or(s.char_count as f64 * ms_per_char, |ms| ms as f64);
+
+        let loaded_ms: f64 = self.segments.iter().map(segment_ms).sum();
+        let average_ms = loaded_ms / self.segments.len() as f64;
+        let (loaded_before, before_ms) = self
+            .segments
+            .iter()
+            .filter(|s| s.index < current)
+            .fold((0, 0.0), |(count, ms), s| (count + 1, ms + segment_ms(s)));
+        let in_current = self.playback_position.map_or(0.0, |position| position.elapsed.as_secs_f64() * 1000.0);
+        let elapsed_ms = before_ms + current.saturating_sub(loaded_before) as f64 * average_ms + in_current;
+        let total_ms = loaded_ms + total.saturating_sub(self.segments.len()) as f64 * average_ms;
+
+        let speed = f64::from(crate::time_stretch::clamp_speed(session.speed));
+        let to_duration = |ms: f64| Duration::from_secs_f64(ms.max(0.0) / 1000.0 / speed);
+        Some(ListeningEstimate { elapsed: to_duration(elapsed_ms), remaining: to_duration(total_ms - elapsed_ms) })
+    }
+
     /// 初始化任务管理器
     pub fn init_task_manager(&mut self) {
         self.task_manager = TaskManager::new(3); // 向前预取 3 段
@@ -367,13 +877,20 @@ impl AppState {
 // ============================================================================
 
 /// API 请求事件 - V2
-#[derive(Event)]
+#[derive(Event, Debug, Clone, PartialEq)]
 pub enum ApiRequest {
+    // Auth
+    /// 用户名/密码登录
+    Login { username: String, password: String },
+    /// 退出登录
+    Logout,
+
     // Novel
     LoadNovels,
     LoadVoices,
-    UploadNovel { title: String, file_path: PathBuf },
-    UploadVoice { name: String, description: Option<String>, file_path: PathBuf },
+    /// 上传小说，`temp_id` 为列表中临时小说的 ID
+    UploadNovel { title: String, file_path: PathBuf, temp_id: Uuid },
+    UploadVoice { name: String, description: Option<String>, file_path: PathBuf, upload_id: Uuid },
     DeleteNovel(Uuid),
     DeleteVoice(Uuid),
     PollNovelStatus(Uuid),
@@ -382,7 +899,9 @@ pub enum ApiRequest {
     /// 开始播放（按需创建 session）
     Play { novel_id: Uuid, voice_id: Uuid, start_index: u32 },
     /// Seek 到指定段落
-    Seek { session_id: String, segment_index: u32 },
+    ///
+    /// `sync_only` 时只同步服务端 session 的位置（段落范围循环回到开头），不重新加载音频。
+    Seek { session_id: String, segment_index: u32, sync_only: bool },
     /// 切换音色
     ChangeVoice { session_id: String, voice_id: Uuid },
     /// 关闭 session
@@ -397,20 +916,61 @@ pub enum ApiRequest {
     // Audio (V2)
     /// 获取音频
     LoadAudio { novel_id: Uuid, segment_index: u32, voice_id: Uuid },
+    /// 批量预取已就绪的音频到内存缓冲
+    PrefetchAudio { novel_id: Uuid, voice_id: Uuid, segment_indices: Vec<u32> },
+    /// 下载整本小说的文本和音频到本地
+    DownloadNovel { novel_id: Uuid, voice_id: Uuid },
+    /// 导出有声书到 `output_dir`
+    ExportAudiobook { novel_id: Uuid, voice_id: Uuid, settings: ExportSettings, output_dir: PathBuf },
     
     // Segments
     /// 加载段落列表
     LoadSegments { novel_id: Uuid, start: Option<usize>, limit: Option<usize> },
 }
 
+impl ApiRequest {
+    /// 请求的简短描述（用于错误提示）
+    pub fn label(&self) -> &'static str {
+        match self {
+            ApiRequest::Login { .. } => "登录",
+            ApiRequest::Logout => "退出登录",
+            ApiRequest::LoadNovels => "加载小说列表",
+            ApiRequest::LoadVoices => "加载音色列表",
+            ApiRequest::UploadNovel { .. } => "上传小说",
+            ApiRequest::UploadVoice { .. } => "上传音色",
+            ApiRequest::DeleteNovel(_) => "删除小说",
+            ApiRequest::DeleteVoice(_) => "删除音色",
+            ApiRequest::PollNovelStatus(_) => "查询小说状态",
+            ApiRequest::Play { .. } => "开始播放",
+            ApiRequest::Seek { .. } => "跳转段落",
+            ApiRequest::ChangeVoice { .. } => "切换音色",
+            ApiRequest::CloseSession(_) => "关闭会话",
+            ApiRequest::SubmitInfer { .. } => "提交推理任务",
+            ApiRequest::QueryTaskStatus { .. } => "查询任务状态",
+            ApiRequest::LoadAudio { .. } => "加载音频",
+            ApiRequest::PrefetchAudio { .. } => "预取音频",
+            ApiRequest::DownloadNovel { .. } => "离线下载",
+            ApiRequest::ExportAudiobook { .. } => "导出有声书",
+            ApiRequest::LoadSegments { .. } => "加载段落",
+        }
+    }
+}
+
 /// API 响应事件 - V2
 #[derive(Event)]
 pub enum ApiResponse {
+    // Auth
+    LoggedIn { username: String },
+    LoggedOut,
+
     // Novel
     NovelsLoaded(Vec<NovelResponse>),
     VoicesLoaded(Vec<VoiceResponse>),
-    NovelUploaded(NovelResponse),
-    VoiceUploaded(VoiceResponse),
+    /// 小说上传完成，替换 `temp_id` 对应的临时小说
+    NovelUploaded { temp_id: Uuid, novel: NovelResponse },
+    VoiceUploaded { upload_id: Uuid, voice: VoiceResponse },
+    /// 上传进度（`upload_id` 对应 `AppState::uploads` 的 key）
+    UploadProgress { upload_id: Uuid, sent: u64, total: u64 },
     NovelDeleted(Uuid),
     VoiceDeleted(Uuid),
     NovelStatusUpdated(NovelResponse),
@@ -419,7 +979,7 @@ pub enum ApiResponse {
     /// 播放开始，session 已创建
     PlayStarted { session_id: String, novel_id: Uuid, voice_id: Uuid, current_index: u32 },
     /// Seek 完成
-    SeekCompleted { session_id: String, current_index: u32, cancelled_tasks: usize },
+    SeekCompleted { session_id: String, current_index: u32, cancelled_tasks: usize, sync_only: bool },
     /// 音色切换完成
     VoiceChanged { session_id: String, voice_id: Uuid, cancelled_tasks: usize },
     /// Session 已关闭
@@ -432,17 +992,68 @@ pub enum ApiResponse {
     TaskStatusQueried { tasks: Vec<crate::api::TaskStatusInfo> },
     
     // Audio (V2)
-    /// 音频已加载
-    AudioLoaded { novel_id: Uuid, segment_index: u32, data: Vec<u8> },
+    /// 音频开始下载（边下载边播放）
+    AudioLoaded { novel_id: Uuid, segment_index: u32, stream: AudioStream },
     /// 音频未就绪
     AudioNotReady { novel_id: Uuid, segment_index: u32 },
+    /// 批量预取完成（`segments` 只包含已生成的段落）
+    AudioPrefetched { novel_id: Uuid, voice_id: Uuid, requested: Vec<u32>, segments: Vec<SegmentAudio> },
+    /// 离线下载进度
+    DownloadProgress { novel_id: Uuid, downloaded: usize, total: usize },
+    /// 离线下载完成
+    NovelDownloaded { novel_id: Uuid },
+    /// 有声书导出进度
+    ExportProgress { novel_id: Uuid, done: usize, total: usize },
+    /// 有声书导出完成
+    AudiobookExported { novel_id: Uuid, summary: ExportSummary },
     
     // Segments
     /// 段落已加载
     SegmentsLoaded { novel_id: Uuid, total: usize, segments: Vec<SegmentResponse> },
     
-    // Error
-    Error(String),
+    // Retry / Error
+    /// 请求失败，正在自动重试（`attempt` 为即将进行的尝试次数）
+    Retrying { request: ApiRequest, attempt: u32, max_attempts: u32, error: ApiError },
+    /// 请求失败，附带原始请求以便定位和重试
+    Error { request: ApiRequest, error: ApiError },
+}
+
+impl ApiResponse {
+    /// 是否为 `request` 的最终响应（成功或失败，不含进度和重试通知）
+    pub fn completes(&self, request: &ApiRequest) -> bool {
+        match (self, request) {
+            (ApiResponse::Error { request: failed, .. }, _) => failed == request,
+            (ApiResponse::NovelUploaded { temp_id, .. }, ApiRequest::UploadNovel { temp_id: id, .. }) => temp_id == id,
+            (ApiResponse::VoiceUploaded { upload_id, .. }, ApiRequest::UploadVoice { upload_id: id, .. }) => upload_id == id,
+            (ApiResponse::NovelDeleted(id), ApiRequest::DeleteNovel(requested))
+            | (ApiResponse::VoiceDeleted(id), ApiRequest::DeleteVoice(requested))
+            | (ApiResponse::NovelDownloaded { novel_id: id }, ApiRequest::DownloadNovel { novel_id: requested, .. })
+            | (ApiResponse::AudiobookExported { novel_id: id, .. }, ApiRequest::ExportAudiobook { novel_id: requested, .. })
+            | (ApiResponse::SegmentsLoaded { novel_id: id, .. }, ApiRequest::LoadSegments { novel_id: requested, .. })
+            | (ApiResponse::AudioPrefetched { novel_id: id, .. }, ApiRequest::PrefetchAudio { novel_id: requested, .. }) => {
+                id == requested
+            }
+            (ApiResponse::NovelStatusUpdated(novel), ApiRequest::PollNovelStatus(id)) => novel.id == *id,
+            (ApiResponse::SeekCompleted { sync_only, .. }, ApiRequest::Seek { sync_only: requested, .. }) => {
+                sync_only == requested
+            }
+            (
+                ApiResponse::AudioLoaded { novel_id, segment_index, .. }
+                | ApiResponse::AudioNotReady { novel_id, segment_index },
+                ApiRequest::LoadAudio { novel_id: id, segment_index: index, .. },
+            ) => novel_id == id && segment_index == index,
+            (ApiResponse::LoggedIn { .. }, ApiRequest::Login { .. })
+            | (ApiResponse::LoggedOut, ApiRequest::Logout)
+            | (ApiResponse::NovelsLoaded(_), ApiRequest::LoadNovels)
+            | (ApiResponse::VoicesLoaded(_), ApiRequest::LoadVoices)
+            | (ApiResponse::PlayStarted { .. }, ApiRequest::Play { .. })
+            | (ApiResponse::VoiceChanged { .. }, ApiRequest::ChangeVoice { .. })
+            | (ApiResponse::SessionClosed(_), ApiRequest::CloseSession(_))
+            | (ApiResponse::InferSubmitted { .. }, ApiRequest::SubmitInfer { .. })
+            | (ApiResponse::TaskStatusQueried { .. }, ApiRequest::QueryTaskStatus { .. }) => true,
+            _ => false,
+        }
+    }
 }
 
 // ============================================================================
@@ -489,14 +1100,52 @@ pub enum WsResponse {
     VoiceDeleted { voice_id: Uuid },
 }
 
+impl From<WsEvent> for WsResponse {
+    /// 不区分 channel 的事件转换（用于后端本地产生的事件）
+    fn from(event: WsEvent) -> Self {
+        match event {
+            WsEvent::TaskStateChanged { .. } => WsResponse::TaskStateChanged(event),
+            WsEvent::SessionClosed { session_id, reason } => WsResponse::SessionClosedByServer { session_id, reason },
+            WsEvent::NovelReady { novel_id, title, total_segments } => {
+                WsResponse::NovelReady { novel_id, title, total_segments }
+            }
+            WsEvent::NovelFailed { novel_id, error } => WsResponse::NovelFailed { novel_id, error },
+            WsEvent::NovelDeleting { novel_id } => WsResponse::NovelDeleting { novel_id },
+            WsEvent::NovelDeleted { novel_id } => WsResponse::NovelDeleted { novel_id },
+            WsEvent::NovelDeleteFailed { novel_id, error } => WsResponse::NovelDeleteFailed { novel_id, error },
+            WsEvent::VoiceDeleted { voice_id } => WsResponse::VoiceDeleted { voice_id },
+        }
+    }
+}
+
 // ============================================================================
 // Audio Events
 // ============================================================================
 
-/// 音频播放事件
+/// 音频播放事件：停止当前播放，从这一段开始
 #[derive(Event)]
 pub struct PlayAudioEvent {
-    pub data: Vec<u8>,
+    pub segment_index: u32,
+    pub stream: AudioStream,
+}
+
+/// 把下一段排进播放队列，当前段播完后无缝播放
+#[derive(Event)]
+pub struct QueueAudioEvent {
+    pub segment_index: u32,
+    pub stream: AudioStream,
+}
+
+/// 音频线程开始播放某一段（包括排队段落的自动衔接）
+#[derive(Event)]
+pub struct SegmentStartedEvent {
+    pub segment_index: u32,
+}
+
+/// 跳转到当前段内的位置
+#[derive(Event)]
+pub struct SeekAudioEvent {
+    pub position: Duration,
 }
 
 /// 停止音频事件
@@ -511,6 +1160,6 @@ pub struct PauseAudioEvent;
 #[derive(Event)]
 pub struct ResumeAudioEvent;
 
-/// 音频播放完成事件
+/// 音频播放完成事件（播放队列中的段落全部播完）
 #[derive(Event)]
 pub struct AudioFinishedEvent;
@@ -0,0 +1,194 @@
+//! 字幕导出 - 把段落文本和音频时间轴写成 SRT / LRC / VTT
+//!
+//! 时间轴由有声书导出时解码出的音频长度累加而来，并计入段落之间的静音，
+//! 所以字幕和同一次导出的 WAV 完全对齐。
+//! WebSocket 推送的 `TaskStateChanged.duration_ms` 只覆盖当前会话推理过的段落，
+//! 离线副本和 OpenAI 兼容后端也不提供时长，因此这里不依赖它。
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 字幕格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleFormat {
+    /// SubRip，视频编辑器通用
+    Srt,
+    /// 歌词格式，音乐播放器同步显示
+    Lrc,
+    /// WebVTT，网页播放器
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub const ALL: [SubtitleFormat; 3] = [SubtitleFormat::Srt, SubtitleFormat::Lrc, SubtitleFormat::Vtt];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "SRT",
+            SubtitleFormat::Lrc => "LRC",
+            SubtitleFormat::Vtt => "VTT",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Lrc => "lrc",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// 一条字幕（对应一个段落）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// 一个音频文件对应的字幕
+#[derive(Debug, Clone, Default)]
+pub struct SubtitleTrack {
+    pub title: String,
+    /// 音色名，写入 LRC 的 `[ar:]`
+    pub artist: Option<String>,
+    pub cues: Vec<Cue>,
+}
+
+impl SubtitleTrack {
+    pub fn new(title: impl Into<String>, artist: Option<String>) -> Self {
+        Self { title: title.into(), artist, cues: Vec::new() }
+    }
+
+    /// 添加一条字幕，空白段落跳过
+    pub fn push(&mut self, start_ms: u64, end_ms: u64, text: &str) {
+        let text = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.is_empty() {
+            self.cues.push(Cue { start_ms, end_ms, text });
+        }
+    }
+
+    pub fn render(&self, format: SubtitleFormat) -> String {
+        let mut out = String::new();
+        match format {
+            SubtitleFormat::Srt => {
+                for (i, cue) in self.cues.iter().enumerate() {
+                    let _ = write!(
+                        out,
+                        "{}\n{} --> {}\n{}\n\n",
+                        i + 1,
+                        timestamp(cue.start_ms, ','),
+                        timestamp(cue.end_ms, ','),
+                        cue.text
+                    );
+                }
+            }
+            SubtitleFormat::Vtt => {
+                out.push_str("WEBVTT\n\n");
+                for cue in &self.cues {
+                    let text = cue
+                        .text
+                        .replace('&', "&amp;")
+                        .replace('<', "&lt;")
+                        .replace('>', "&gt;");
+                    let _ = write!(
+                        out,
+                        "{} --> {}\n{}\n\n",
+                        timestamp(cue.start_ms, '.'),
+                        timestamp(cue.end_ms, '.'),
+                        text
+                    );
+                }
+            }
+            SubtitleFormat::Lrc => {
+                let _ = writeln!(out, "[ti:{}]", self.title);
+                if let Some(artist) = &self.artist {
+                    let _ = writeln!(out, "[ar:{}]", artist);
+                }
+                let _ = writeln!(out, "[re:rovel-desk]");
+                for cue in &self.cues {
+                    let _ = writeln!(out, "{}{}", lrc_timestamp(cue.start_ms), cue.text.replace('\n', " "));
+                }
+                // LRC 没有结束时间，最后一句之后补一个空行让歌词在音频结束时消失
+                if let Some(last) = self.cues.last() {
+                    let _ = writeln!(out, "{}", lrc_timestamp(last.end_ms));
+                }
+            }
+        }
+        out
+    }
+
+    /// 在 `base`（不含扩展名）旁边写出每种格式的字幕文件
+    pub fn write(&self, base: &Path, formats: &[SubtitleFormat]) -> io::Result<()> {
+        for format in formats {
+            std::fs::write(append_extension(base, format.extension()), self.render(*format))?;
+        }
+        Ok(())
+    }
+}
+
+/// 在路径后追加扩展名（标题里的 `.` 不会被 `with_extension` 截掉）
+pub fn append_extension(base: &Path, extension: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+/// `HH:MM:SS,mmm`（SRT）或 `HH:MM:SS.mmm`（VTT）
+fn timestamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+/// `[mm:ss.xx]`，分钟数不封顶
+fn lrc_timestamp(ms: u64) -> String {
+    format!("[{:02}:{:02}.{:02}]", ms / 60_000, ms / 1000 % 60, ms % 1000 / 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cumulative_timestamps_in_every_format() {
+        let mut track = SubtitleTrack::new("小说", Some("默认音色".to_string()));
+        track.push(0, 1_250, "第一章 开始");
+        track.push(1_850, 3_723_004, "  他说：<你好> & 再见\n\n第二行 ");
+        track.push(3_723_604, 3_723_900, "   ");
+
+        assert_eq!(
+            track.render(SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:01,250\n第一章 开始\n\n\
+             2\n00:00:01,850 --> 01:02:03,004\n他说：<你好> & 再见\n第二行\n\n"
+        );
+        assert_eq!(
+            track.render(SubtitleFormat::Vtt),
+            "WEBVTT\n\n\
+             00:00:00.000 --> 00:00:01.250\n第一章 开始\n\n\
+             00:00:01.850 --> 01:02:03.004\n他说：&lt;你好&gt; &amp; 再见\n第二行\n\n"
+        );
+        assert_eq!(
+            track.render(SubtitleFormat::Lrc),
+            "[ti:小说]\n[ar:默认音色]\n[re:rovel-desk]\n\
+             [00:00.00]第一章 开始\n\
+             [00:01.85]他说：<你好> & 再见 第二行\n\
+             [62:03.00]\n"
+        );
+    }
+}
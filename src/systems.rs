@@ -12,18 +12,51 @@
 
 use bevy::prelude::*;
 use std::sync::{mpsc, Mutex};
+use std::time::Duration;
 
-use crate::api::ApiClient;
+use crate::api::{ApiClient, ApiError, SegmentAudio};
+use crate::audio::AudioPlayer;
+use crate::audio_cache::AudioCache;
+use crate::backend::Backend;
+use crate::bookmarks::Bookmarks;
+use crate::config::AppConfig;
+use crate::export::export_audiobook;
+use crate::offline::{download_novel, is_offline_session, OfflineLibrary};
+use crate::progress::ReadingProgress;
 use crate::state::{
-    ApiRequest, ApiResponse, AppState, AudioFinishedEvent, CurrentSession,
-    PlayAudioEvent, PlaybackState, WsResponse,
+    ApiRequest, ApiResponse, AppState, AppView, AudioFinishedEvent, CurrentSession, DownloadTask,
+    ExportTask, PlayAudioEvent, PlaybackState, QueueAudioEvent, RepeatMode, SegmentStartedEvent, SleepTimer, StopAudioEvent, SwitchProfileEvent, UploadKind, UploadTask,
+    WsResponse,
 };
+use crate::websocket::spawn_ws_clients;
 
 /// Channel for API responses from worker threads
+///
+/// 每个响应带有发出请求时的服务器代数（`AppState::server_generation`）。
 #[derive(Resource)]
 pub struct ApiResponseChannel {
-    receiver: Mutex<mpsc::Receiver<ApiResponse>>,
-    sender: mpsc::Sender<ApiResponse>,
+    receiver: Mutex<mpsc::Receiver<(u64, ApiResponse)>>,
+    sender: mpsc::Sender<(u64, ApiResponse)>,
+}
+
+impl ApiResponseChannel {
+    /// 为当前服务器代数发出的请求创建发送端
+    fn sender(&self, generation: u64) -> ResponseSender {
+        ResponseSender { generation, sender: self.sender.clone() }
+    }
+}
+
+/// 给响应标记服务器代数的发送端
+#[derive(Clone)]
+struct ResponseSender {
+    generation: u64,
+    sender: mpsc::Sender<(u64, ApiResponse)>,
+}
+
+impl ResponseSender {
+    fn send(&self, response: ApiResponse) -> Result<(), mpsc::SendError<()>> {
+        self.sender.send((self.generation, response)).map_err(|_| mpsc::SendError(()))
+    }
 }
 
 impl Default for ApiResponseChannel {
@@ -42,15 +75,118 @@ pub fn setup_api_channel(mut commands: Commands) {
 }
 
 /// 启动时加载数据
-pub fn startup_load(mut api_events: EventWriter<ApiRequest>) {
+pub fn startup_load(
+    api_client: Res<ApiClient>,
+    mut app_state: ResMut<AppState>,
+    mut api_events: EventWriter<ApiRequest>,
+) {
     api_events.send(ApiRequest::LoadNovels);
     api_events.send(ApiRequest::LoadVoices);
+    resume_pending_uploads(&api_client, &mut app_state, &mut api_events);
+}
+
+/// 继续上次未完成的分块上传（应用重启或切换回原服务器后）
+fn resume_pending_uploads(
+    client: &ApiClient,
+    app_state: &mut AppState,
+    api_events: &mut EventWriter<ApiRequest>,
+) {
+    for pending in client.pending_chunked_uploads() {
+        if !pending.file_path.exists() {
+            tracing::warn!("Pending upload source {:?} no longer exists", pending.file_path);
+            continue;
+        }
+        tracing::info!(
+            "Resuming chunked upload {} ({}/{} bytes)",
+            pending.title,
+            pending.uploaded_bytes(),
+            pending.total_size
+        );
+        let temp_novel = crate::api::NovelResponse::create_temporary(pending.title.clone());
+        let temp_id = temp_novel.id;
+        app_state.novels.insert(0, temp_novel);
+        api_events.send(ApiRequest::UploadNovel {
+            title: pending.title,
+            file_path: pending.file_path,
+            temp_id,
+        });
+    }
+}
+
+/// 切换服务器 profile
+///
+/// 关闭旧服务器上的 session，停止播放，替换 ApiClient / Backend 并重建两个 WebSocket 线程，
+/// 然后从新服务器重新加载小说和音色列表。
+#[allow(clippy::too_many_arguments)]
+pub fn handle_switch_profile(
+    mut commands: Commands,
+    mut events: EventReader<SwitchProfileEvent>,
+    mut config: ResMut<AppConfig>,
+    mut app_state: ResMut<AppState>,
+    backend: Res<Backend>,
+    mut next_view: ResMut<NextState<AppView>>,
+    mut stop_audio: EventWriter<StopAudioEvent>,
+    mut api_events: EventWriter<ApiRequest>,
+) {
+    // 同一帧内多次切换只处理最后一次
+    let Some(event) = events.read().last() else { return };
+    let name = event.profile_name.clone();
+
+    if name == config.active_profile && !event.reconnect {
+        return;
+    }
+    if !config.set_active(&name) {
+        app_state.set_error(format!("未找到服务器配置: {}", name));
+        return;
+    }
+    if let Err(e) = config.save() {
+        tracing::warn!("Failed to save config: {}", e);
+    }
+
+    let profile = config.active().clone();
+    tracing::info!("Switching server profile to '{}' ({})", profile.name, profile.server_url);
+
+    // 在旧服务器上关闭当前 session（不等待结果）
+    if let Some(session) = app_state.current_session.take() {
+        let old_backend = backend.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = old_backend.close_session(&session.session_id) {
+                tracing::warn!("Failed to close session on old server: {}", e);
+            }
+        });
+    }
+    stop_audio.send(StopAudioEvent);
+
+    // 替换 HTTP 客户端、后端和 WebSocket 客户端（旧客户端 Drop 时关闭线程）
+    let client = ApiClient::from_config(&config);
+    let offline = OfflineLibrary::open(OfflineLibrary::default_dir(&profile.server_url));
+    let progress = ReadingProgress::open(ReadingProgress::default_path(&profile.server_url));
+    let bookmarks = Bookmarks::open(Bookmarks::default_path(&profile.server_url));
+    let backend = Backend::from_config(&config, &client, &offline);
+    spawn_ws_clients(&mut commands, profile.ws_base_url(), &client, &backend);
+
+    app_state.reset_server_data();
+    next_view.set(AppView::NovelList);
+
+    api_events.send(ApiRequest::LoadNovels);
+    api_events.send(ApiRequest::LoadVoices);
+    resume_pending_uploads(&client, &mut app_state, &mut api_events);
+    commands.insert_resource(client);
+    commands.insert_resource(backend);
+    commands.insert_resource(offline);
+    commands.insert_resource(progress);
+    commands.insert_resource(bookmarks);
 }
 
 /// 处理 API 请求 - 使用 std::thread 执行阻塞请求（Windows 兼容）
+#[allow(clippy::too_many_arguments)]
 pub fn handle_api_requests(
     mut events: EventReader<ApiRequest>,
     api_client: Res<ApiClient>,
+    backend: Res<Backend>,
+    config: Res<AppConfig>,
+    audio_cache: Res<AudioCache>,
+    offline: Res<OfflineLibrary>,
     mut app_state: ResMut<AppState>,
     channel: Option<Res<ApiResponseChannel>>,
 ) {
@@ -64,16 +200,69 @@ pub fn handle_api_requests(
         
         // 设置加载状态（上传操作除外）
         match event {
-            ApiRequest::UploadNovel { .. } | ApiRequest::UploadVoice { .. } => {}
+            ApiRequest::UploadNovel { temp_id, .. } => {
+                // 重试时恢复临时小说的上传状态
+                if let Some(temp_novel) = app_state.novels.iter_mut().find(|n| n.id == *temp_id) {
+                    temp_novel.status = "uploading".to_string();
+                }
+            }
+            ApiRequest::UploadVoice { .. }
+            | ApiRequest::DownloadNovel { .. }
+            | ApiRequest::ExportAudiobook { .. } => {}
+            // 登录进度显示在登录对话框中
+            ApiRequest::Login { .. } => {
+                app_state.login_dialog.pending = true;
+            }
             _ => {
                 app_state.loading = true;
             }
         }
         
-        let client = (*api_client).clone();
-        let sender = channel.sender.clone();
+        let sender = channel.sender(app_state.server_generation);
+        let request = event.clone();
+        let client = {
+            // 重试通知转发给 UI
+            let sender = sender.clone();
+            let request = request.clone();
+            backend.observed_by(std::sync::Arc::new(move |retry| {
+                let _ = sender.send(ApiResponse::Retrying {
+                    request: request.clone(),
+                    attempt: retry.attempt,
+                    max_attempts: retry.max_attempts,
+                    error: retry.error.clone(),
+                });
+            }))
+        };
 
         match event {
+            // ====== Auth APIs ======
+            ApiRequest::Login { username, password } => {
+                let username = username.clone();
+                let password = password.clone();
+                let auth_client = (*api_client).clone();
+                std::thread::spawn(move || {
+                    tracing::info!("Thread: Login starting, username={}", username);
+                    let response = match auth_client.login(&username, &password) {
+                        Ok(token) => ApiResponse::LoggedIn { username: token.username },
+                        Err(error) => {
+                            tracing::warn!("Thread: Login error: {}", error);
+                            ApiResponse::Error { request, error }
+                        }
+                    };
+                    let _ = sender.send(response);
+                });
+            }
+            ApiRequest::Logout => {
+                let auth_client = (*api_client).clone();
+                std::thread::spawn(move || {
+                    let response = match auth_client.logout() {
+                        Ok(()) => ApiResponse::LoggedOut,
+                        Err(error) => ApiResponse::Error { request, error },
+                    };
+                    let _ = sender.send(response);
+                });
+            }
+
             // ====== Novel APIs ======
             ApiRequest::LoadNovels => {
                 std::thread::spawn(move || {
@@ -83,9 +272,9 @@ pub fn handle_api_requests(
                             tracing::info!("Thread: LoadNovels success, {} novels", novels.len());
                             ApiResponse::NovelsLoaded(novels)
                         }
-                        Err(e) => {
-                            tracing::error!("Thread: LoadNovels error: {}", e);
-                            ApiResponse::Error(e.to_string())
+                        Err(error) => {
+                            tracing::error!("Thread: LoadNovels error: {}", error);
+                            ApiResponse::Error { request, error }
                         }
                     };
                     if let Err(e) = sender.send(response) {
@@ -101,9 +290,9 @@ pub fn handle_api_requests(
                             tracing::info!("Thread: LoadVoices success, {} voices", voices.len());
                             ApiResponse::VoicesLoaded(voices)
                         }
-                        Err(e) => {
-                            tracing::error!("Thread: LoadVoices error: {}", e);
-                            ApiResponse::Error(e.to_string())
+                        Err(error) => {
+                            tracing::error!("Thread: LoadVoices error: {}", error);
+                            ApiResponse::Error { request, error }
                         }
                     };
                     if let Err(e) = sender.send(response) {
@@ -111,19 +300,27 @@ pub fn handle_api_requests(
                     }
                 });
             }
-            ApiRequest::UploadNovel { title, file_path } => {
+            ApiRequest::UploadNovel { title, file_path, temp_id } => {
                 let title = title.clone();
                 let path = file_path.clone();
+                let temp_id = *temp_id;
+                let upload = UploadTask::new(UploadKind::Novel, title.clone());
+                let cancel = upload.cancel.clone();
+                app_state.uploads.insert(temp_id, upload);
                 std::thread::spawn(move || {
                     tracing::info!("Thread: UploadNovel starting, title={}", title);
-                    let response = match client.upload_novel(&title, &path) {
+                    let progress_sender = sender.clone();
+                    let mut on_progress = move |sent, total| {
+                        let _ = progress_sender.send(ApiResponse::UploadProgress { upload_id: temp_id, sent, total });
+                    };
+                    let response = match client.upload_novel(&title, &path, &cancel, &mut on_progress) {
                         Ok(novel) => {
                             tracing::info!("Thread: UploadNovel success");
-                            ApiResponse::NovelUploaded(novel)
+                            ApiResponse::NovelUploaded { temp_id, novel }
                         }
-                        Err(e) => {
-                            tracing::error!("Thread: UploadNovel error: {}", e);
-                            ApiResponse::Error(e.to_string())
+                        Err(error) => {
+                            tracing::error!("Thread: UploadNovel error: {}", error);
+                            ApiResponse::Error { request, error }
                         }
                     };
                     if let Err(e) = sender.send(response) {
@@ -131,20 +328,28 @@ pub fn handle_api_requests(
                     }
                 });
             }
-            ApiRequest::UploadVoice { name, description, file_path } => {
+            ApiRequest::UploadVoice { name, description, file_path, upload_id } => {
                 let name = name.clone();
                 let desc = description.clone();
                 let path = file_path.clone();
+                let upload_id = *upload_id;
+                let upload = UploadTask::new(UploadKind::Voice, name.clone());
+                let cancel = upload.cancel.clone();
+                app_state.uploads.insert(upload_id, upload);
                 std::thread::spawn(move || {
                     tracing::info!("Thread: UploadVoice starting, name={}", name);
-                    let response = match client.upload_voice(&name, desc.as_deref(), &path) {
+                    let progress_sender = sender.clone();
+                    let mut on_progress = move |sent, total| {
+                        let _ = progress_sender.send(ApiResponse::UploadProgress { upload_id, sent, total });
+                    };
+                    let response = match client.upload_voice(&name, desc.as_deref(), &path, &cancel, &mut on_progress) {
                         Ok(voice) => {
                             tracing::info!("Thread: UploadVoice success");
-                            ApiResponse::VoiceUploaded(voice)
+                            ApiResponse::VoiceUploaded { upload_id, voice }
                         }
-                        Err(e) => {
-                            tracing::error!("Thread: UploadVoice error: {}", e);
-                            ApiResponse::Error(e.to_string())
+                        Err(error) => {
+                            tracing::error!("Thread: UploadVoice error: {}", error);
+                            ApiResponse::Error { request, error }
                         }
                     };
                     if let Err(e) = sender.send(response) {
@@ -157,7 +362,7 @@ pub fn handle_api_requests(
                 std::thread::spawn(move || {
                     let response = match client.delete_novel(id) {
                         Ok(_) => ApiResponse::NovelDeleted(id),
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -167,7 +372,7 @@ pub fn handle_api_requests(
                 std::thread::spawn(move || {
                     let response = match client.delete_voice(id) {
                         Ok(_) => ApiResponse::VoiceDeleted(id),
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -177,7 +382,7 @@ pub fn handle_api_requests(
                 std::thread::spawn(move || {
                     let response = match client.get_novel(id) {
                         Ok(novel) => ApiResponse::NovelStatusUpdated(novel),
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -196,22 +401,24 @@ pub fn handle_api_requests(
                             voice_id: resp.voice_id,
                             current_index: resp.current_index,
                         },
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
             }
-            ApiRequest::Seek { session_id, segment_index } => {
+            ApiRequest::Seek { session_id, segment_index, sync_only } => {
                 let session_id = session_id.clone();
                 let segment_index = *segment_index;
+                let sync_only = *sync_only;
                 std::thread::spawn(move || {
                     let response = match client.seek(&session_id, segment_index) {
                         Ok(resp) => ApiResponse::SeekCompleted {
                             session_id: resp.session_id,
                             current_index: resp.current_index,
                             cancelled_tasks: resp.cancelled_tasks,
+                            sync_only,
                         },
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -226,7 +433,7 @@ pub fn handle_api_requests(
                             voice_id: resp.voice_id,
                             cancelled_tasks: resp.cancelled_tasks,
                         },
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -236,7 +443,7 @@ pub fn handle_api_requests(
                 std::thread::spawn(move || {
                     let response = match client.close_session(&session_id) {
                         Ok(resp) => ApiResponse::SessionClosed(resp.session_id),
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -249,7 +456,7 @@ pub fn handle_api_requests(
                 std::thread::spawn(move || {
                     let response = match client.submit_infer(&session_id, segment_indices) {
                         Ok(resp) => ApiResponse::InferSubmitted { tasks: resp.tasks },
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -259,7 +466,7 @@ pub fn handle_api_requests(
                 std::thread::spawn(move || {
                     let response = match client.query_task_status(task_ids) {
                         Ok(resp) => ApiResponse::TaskStatusQueried { tasks: resp.tasks },
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -270,18 +477,142 @@ pub fn handle_api_requests(
                 let novel_id = *novel_id;
                 let segment_index = *segment_index;
                 let voice_id = *voice_id;
+                let formats = config.bandwidth.formats();
+                let cache = audio_cache.clone();
                 std::thread::spawn(move || {
-                    let response = match client.get_audio(novel_id, segment_index, voice_id) {
-                        Ok(Some(data)) => ApiResponse::AudioLoaded {
-                            novel_id,
-                            segment_index,
-                            data,
-                        },
-                        Ok(None) => ApiResponse::AudioNotReady {
+                    // 优先使用磁盘缓存
+                    if let Some(audio) = cache.get(novel_id, voice_id, segment_index) {
+                        tracing::debug!("Audio for segment {} served from cache", segment_index);
+                        let _ = sender.send(ApiResponse::AudioLoaded {
                             novel_id,
                             segment_index,
-                        },
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                            stream: audio.to_stream(),
+                        });
+                        return;
+                    }
+                    match client.open_audio_stream(novel_id, segment_index, voice_id, &formats) {
+                        Ok(stream) => {
+                            let _ = sender.send(ApiResponse::AudioLoaded {
+                                novel_id,
+                                segment_index,
+                                stream: stream.clone(),
+                            });
+                            // 下载完整后写入缓存
+                            if let Some(data) = stream.wait_complete() {
+                                cache.insert(
+                                    novel_id,
+                                    voice_id,
+                                    &SegmentAudio { segment_index, format: stream.format(), data },
+                                );
+                            }
+                        }
+                        Err(ApiError::NotReady) => {
+                            let _ = sender.send(ApiResponse::AudioNotReady { novel_id, segment_index });
+                        }
+                        Err(error) => {
+                            let _ = sender.send(ApiResponse::Error { request, error });
+                        }
+                    }
+                });
+            }
+
+            ApiRequest::PrefetchAudio { novel_id, voice_id, segment_indices } => {
+                let novel_id = *novel_id;
+                let voice_id = *voice_id;
+                let requested = segment_indices.clone();
+                let formats = config.bandwidth.formats();
+                let cache = audio_cache.clone();
+                std::thread::spawn(move || {
+                    // 已缓存的段落不再下载
+                    let mut segments: Vec<SegmentAudio> = requested
+                        .iter()
+                        .filter_map(|&index| cache.get(novel_id, voice_id, index))
+                        .collect();
+                    let missing: Vec<u32> = requested
+                        .iter()
+                        .copied()
+                        .filter(|index| !segments.iter().any(|s| s.segment_index == *index))
+                        .collect();
+                    let fetched = if missing.is_empty() {
+                        Ok(Vec::new())
+                    } else {
+                        client.get_audio_batch(novel_id, voice_id, &missing, &formats)
+                    };
+                    let response = match fetched {
+                        Ok(fetched) => {
+                            for audio in &fetched {
+                                cache.insert(novel_id, voice_id, audio);
+                            }
+                            segments.extend(fetched);
+                            ApiResponse::AudioPrefetched {
+                                novel_id,
+                                voice_id,
+                                requested,
+                                segments,
+                            }
+                        }
+                        Err(error) => ApiResponse::Error { request, error },
+                    };
+                    let _ = sender.send(response);
+                });
+            }
+
+            ApiRequest::DownloadNovel { novel_id, voice_id } => {
+                let novel_id = *novel_id;
+                let voice_id = *voice_id;
+                let formats = config.bandwidth.formats();
+                let library = offline.clone();
+                let download = DownloadTask::default();
+                let cancel = download.cancel.clone();
+                app_state.downloads.insert(novel_id, download);
+                std::thread::spawn(move || {
+                    tracing::info!("Thread: DownloadNovel starting, novel={}", novel_id);
+                    let progress_sender = sender.clone();
+                    let mut on_progress = move |downloaded, total| {
+                        let _ = progress_sender.send(ApiResponse::DownloadProgress { novel_id, downloaded, total });
+                    };
+                    let response =
+                        match download_novel(&*client, &library, novel_id, voice_id, &formats, &cancel, &mut on_progress) {
+                            Ok(()) => ApiResponse::NovelDownloaded { novel_id },
+                            Err(error) => {
+                                tracing::error!("Thread: DownloadNovel error: {}", error);
+                                ApiResponse::Error { request, error }
+                            }
+                        };
+                    let _ = sender.send(response);
+                });
+            }
+
+            ApiRequest::ExportAudiobook { novel_id, voice_id, settings, output_dir } => {
+                let novel_id = *novel_id;
+                let voice_id = *voice_id;
+                let settings = settings.clone();
+                let output_dir = output_dir.clone();
+                let formats = config.bandwidth.formats();
+                let export = ExportTask::default();
+                let cancel = export.cancel.clone();
+                app_state.exports.insert(novel_id, export);
+                std::thread::spawn(move || {
+                    tracing::info!("Thread: ExportAudiobook starting, novel={}, dir={:?}", novel_id, output_dir);
+                    let progress_sender = sender.clone();
+                    let mut on_progress = move |done, total| {
+                        let _ = progress_sender.send(ApiResponse::ExportProgress { novel_id, done, total });
+                    };
+                    let response = match export_audiobook(
+                        &*client,
+                        novel_id,
+                        voice_id,
+                        &formats,
+                        &settings,
+                        &output_dir,
+                        &cancel,
+                        &mut on_progress,
+                    ) {
+                        Ok(summary) => ApiResponse::AudiobookExported { novel_id, summary },
+                        Err(error) => {
+                            tracing::error!("Thread: ExportAudiobook error: {}", error);
+                            ApiResponse::Error { request, error }
+                        }
                     };
                     let _ = sender.send(response);
                 });
@@ -299,7 +630,7 @@ pub fn handle_api_requests(
                             total: resp.total,
                             segments: resp.segments,
                         },
-                        Err(e) => ApiResponse::Error(e.to_string()),
+                        Err(error) => ApiResponse::Error { request, error },
                     };
                     let _ = sender.send(response);
                 });
@@ -308,16 +639,21 @@ pub fn handle_api_requests(
     }
 }
 
-/// 轮询 API 响应通道
+/// 轮询 API 响应通道，丢弃切换服务器之前发出的请求的响应
 pub fn poll_api_tasks(
     channel: Option<Res<ApiResponseChannel>>,
+    app_state: Res<AppState>,
     mut response_events: EventWriter<ApiResponse>,
 ) {
     let Some(channel) = channel else { return };
     
     // Non-blocking receive with mutex
     if let Ok(receiver) = channel.receiver.lock() {
-        while let Ok(response) = receiver.try_recv() {
+        while let Ok((generation, response)) = receiver.try_recv() {
+            if generation != app_state.server_generation {
+                tracing::debug!("Dropping API response from previous server profile");
+                continue;
+            }
             response_events.send(response);
         }
     };
@@ -330,20 +666,60 @@ pub fn handle_api_responses(
     mut play_audio: EventWriter<PlayAudioEvent>,
     mut api_events: EventWriter<ApiRequest>,
     mut ws_events: EventWriter<crate::state::WsRequest>,
+    mut progress: ResMut<ReadingProgress>,
+    mut bookmarks: ResMut<Bookmarks>,
 ) {
     for event in events.read() {
-        // 设置 loading = false（上传响应除外）
+        // 重试中的请求已结束（成功或最终失败），清除重试提示
+        if app_state.retrying_request.as_ref().is_some_and(|request| event.completes(request)) {
+            app_state.retrying_request = None;
+            app_state.retry_status = None;
+        }
+
+        // 设置 loading = false（上传响应和重试通知除外）
         match event {
-            ApiResponse::NovelUploaded(_) | ApiResponse::VoiceUploaded(_) => {}
+            ApiResponse::NovelUploaded { .. }
+            | ApiResponse::VoiceUploaded { .. }
+            | ApiResponse::UploadProgress { .. }
+            | ApiResponse::DownloadProgress { .. }
+            | ApiResponse::NovelDownloaded { .. }
+            | ApiResponse::ExportProgress { .. }
+            | ApiResponse::AudiobookExported { .. }
+            | ApiResponse::Retrying { .. } => {}
+            ApiResponse::Error {
+                request:
+                    ApiRequest::UploadNovel { .. }
+                    | ApiRequest::UploadVoice { .. }
+                    | ApiRequest::DownloadNovel { .. }
+                    | ApiRequest::ExportAudiobook { .. }
+                    | ApiRequest::Login { .. },
+                ..
+            } => {}
             _ => {
                 app_state.loading = false;
             }
         }
 
         match event {
+            // ====== Auth Responses ======
+            ApiResponse::LoggedIn { username } => {
+                tracing::info!("Logged in as {}", username);
+                app_state.login_dialog.reset();
+                app_state.clear_error();
+                // 登录前的请求可能都因 401 失败，重新加载
+                api_events.send(ApiRequest::LoadNovels);
+                api_events.send(ApiRequest::LoadVoices);
+            }
+            ApiResponse::LoggedOut => {
+                tracing::info!("Logged out");
+            }
+
             // ====== Novel Responses ======
             ApiResponse::NovelsLoaded(novels) => {
-                app_state.novels = novels.clone();
+                // 保留仍在上传中的临时小说
+                let mut merged: Vec<_> = app_state.novels.iter().filter(|n| n.is_temporary).cloned().collect();
+                merged.extend(novels.iter().cloned());
+                app_state.novels = merged;
                 app_state.clear_error();
             }
             ApiResponse::VoicesLoaded(voices) => {
@@ -353,18 +729,13 @@ pub fn handle_api_responses(
                 }
                 app_state.clear_error();
             }
-            ApiResponse::NovelUploaded(novel) => {
+            ApiResponse::NovelUploaded { temp_id, novel } => {
                 tracing::info!("NovelUploaded: id={}, title={}, status={}", novel.id, novel.title, novel.status);
-                // 替换临时小说对象
-                let mut temp_found = false;
-                for existing in app_state.novels.iter_mut() {
-                    if existing.is_temporary && existing.status == "uploading" {
-                        *existing = novel.clone();
-                        temp_found = true;
-                        break;
-                    }
-                }
-                if !temp_found {
+                // 替换对应的临时小说对象
+                app_state.uploads.remove(temp_id);
+                if let Some(existing) = app_state.novels.iter_mut().find(|n| n.id == *temp_id) {
+                    *existing = novel.clone();
+                } else {
                     app_state.novels.push(novel.clone());
                 }
                 tracing::info!("NovelUploaded: novels count={}, ids={:?}", 
@@ -372,14 +743,23 @@ pub fn handle_api_responses(
                     app_state.novels.iter().map(|n| n.id.to_string()).collect::<Vec<_>>());
                 app_state.clear_error();
             }
-            ApiResponse::VoiceUploaded(voice) => {
+            ApiResponse::VoiceUploaded { upload_id, voice } => {
+                app_state.uploads.remove(upload_id);
                 app_state.voices.push(voice.clone());
                 if app_state.selected_voice.is_none() {
                     app_state.selected_voice = Some(voice.clone());
                 }
                 app_state.clear_error();
             }
-            ApiResponse::NovelDeleted(_id) => {
+            ApiResponse::UploadProgress { upload_id, sent, total } => {
+                if let Some(upload) = app_state.uploads.get_mut(upload_id) {
+                    upload.sent = *sent;
+                    upload.total = *total;
+                }
+            }
+            ApiResponse::NovelDeleted(id) => {
+                progress.remove(*id);
+                bookmarks.remove_novel(*id);
                 api_events.send(ApiRequest::LoadNovels);
                 app_state.selected_novel = None;
                 app_state.clear_error();
@@ -402,12 +782,18 @@ pub fn handle_api_responses(
 
             // ====== Session Responses (V2) ======
             ApiResponse::PlayStarted { session_id, novel_id, voice_id, current_index } => {
+                // 段落范围和 A-B 循环只对同一本小说有效
+                if app_state.current_session.as_ref().map(|s| s.novel_id) != Some(*novel_id) {
+                    app_state.repeat = app_state.repeat.filter(|repeat| matches!(repeat, RepeatMode::Segment { .. }));
+                }
+
                 // 创建 session 状态
                 app_state.current_session = Some(CurrentSession {
                     session_id: session_id.clone(),
                     novel_id: *novel_id,
                     voice_id: *voice_id,
                     current_index: *current_index,
+                    speed: progress.speed(*novel_id),
                 });
                 app_state.current_segment_index = *current_index as usize;
                 app_state.playback_state = PlaybackState::Loading;
@@ -416,13 +802,16 @@ pub fn handle_api_responses(
                 // 初始化任务管理器
                 app_state.init_task_manager();
                 
-                // 连接 WebSocket
-                ws_events.send(crate::state::WsRequest::Connect(session_id.clone()));
+                // 连接 WebSocket（离线副本的 session 只在本地）
+                if !is_offline_session(session_id) {
+                    ws_events.send(crate::state::WsRequest::Connect(session_id.clone()));
+                }
                 
-                // 加载段落列表（回调中会提交推理任务）- 初始只加载 30 段
+                // 加载段落列表（回调中会提交推理任务）- 初始只加载 30 段，
+                // 从保存的进度继续时从当前段落前几段开始加载
                 api_events.send(ApiRequest::LoadSegments {
                     novel_id: *novel_id,
-                    start: None,
+                    start: (*current_index > 0).then(|| current_index.saturating_sub(5) as usize),
                     limit: Some(30),
                 });
                 
@@ -430,11 +819,30 @@ pub fn handle_api_responses(
                 
                 app_state.clear_error();
             }
-            ApiResponse::SeekCompleted { session_id, current_index, cancelled_tasks: _ } => {
+            ApiResponse::SeekCompleted { session_id, current_index, cancelled_tasks, sync_only: true } => {
+                // 段落范围循环回到开头：本地已在播放，只记录服务端位置，再提交推理窗口
+                let Some(session) = app_state.current_session.as_mut().filter(|s| s.session_id == *session_id) else {
+                    continue;
+                };
+                session.current_index = *current_index;
+                if *cancelled_tasks > 0 {
+                    // 被取消的任务不会再有 WebSocket 通知，需要重新提交
+                    app_state.task_manager.drop_unfinished();
+                }
+                let index = app_state.current_segment_index as u32;
+                advance_inference_window(&mut app_state, &mut api_events, index);
+            }
+            ApiResponse::SeekCompleted { session_id, current_index, .. } => {
                 if let Some(session) = &mut app_state.current_session {
                     if session.session_id == *session_id {
                         session.current_index = *current_index;
                         app_state.current_segment_index = *current_index as usize;
+                        // 跳到段落范围之外时取消范围循环
+                        if let Some(RepeatMode::Range { start, end }) = app_state.repeat {
+                            if !(start..=end).contains(current_index) {
+                                app_state.repeat = None;
+                            }
+                        }
                         
                         // 清除旧任务，提交新任务
                         app_state.task_manager.clear();
@@ -544,14 +952,16 @@ pub fn handle_api_responses(
             }
 
             // ====== Audio Responses (V2) ======
-            ApiResponse::AudioLoaded { novel_id: _, segment_index, data } => {
-                // 播放音频
-                play_audio.send(PlayAudioEvent { data: data.clone() });
+            ApiResponse::AudioLoaded { novel_id: _, segment_index, stream } => {
+                // 播放音频（收到最小缓冲后开始出声），之前排队的段落随之丢弃
+                play_audio.send(PlayAudioEvent { segment_index: *segment_index, stream: stream.clone() });
+                app_state.queued_segment = None;
                 
                 // 如果是当前段，更新播放状态
                 if *segment_index as usize == app_state.current_segment_index {
                     app_state.playback_state = PlaybackState::Playing;
                     app_state.waiting_for_audio = false;
+                    app_state.current_audio_format = stream.format();
                 }
                 
                 app_state.clear_error();
@@ -562,6 +972,35 @@ pub fn handle_api_responses(
                     app_state.waiting_for_audio = true;
                 }
             }
+            ApiResponse::AudioPrefetched { novel_id, voice_id, requested, segments } => {
+                tracing::debug!("AudioPrefetched: {}/{} segments buffered", segments.len(), requested.len());
+                let keep_from = app_state.current_segment_index as u32;
+                app_state.audio_buffer.finish_request(*novel_id, *voice_id, requested);
+                app_state.audio_buffer.insert(*novel_id, *voice_id, segments.clone(), keep_from);
+            }
+            ApiResponse::DownloadProgress { novel_id, downloaded, total } => {
+                if let Some(download) = app_state.downloads.get_mut(novel_id) {
+                    download.downloaded = *downloaded;
+                    download.total = *total;
+                }
+            }
+            ApiResponse::NovelDownloaded { novel_id } => {
+                tracing::info!("Novel {} downloaded for offline playback", novel_id);
+                app_state.downloads.remove(novel_id);
+            }
+            ApiResponse::ExportProgress { novel_id, done, total } => {
+                if let Some(export) = app_state.exports.get_mut(novel_id) {
+                    export.done = *done;
+                    export.total = *total;
+                }
+            }
+            ApiResponse::AudiobookExported { novel_id, summary } => {
+                tracing::info!("Novel {} exported to {:?}", novel_id, summary.output);
+                if let Some(export) = app_state.exports.get_mut(novel_id) {
+                    export.done = export.total;
+                    export.finished = Some(summary.clone());
+                }
+            }
 
             // ====== Segments Response ======
             ApiResponse::SegmentsLoaded { novel_id: _, total: _, segments } => {
@@ -633,22 +1072,121 @@ pub fn handle_api_responses(
                 app_state.clear_error();
             }
 
-            // ====== Error ======
-            ApiResponse::Error(msg) => {
-                // 处理上传错误
-                if msg.contains("upload") || msg.contains("上传") {
-                    if let Some(temp_novel) = app_state.novels.iter_mut()
-                        .find(|n| n.is_temporary && n.status == "uploading") {
-                        temp_novel.status = "error".to_string();
-                        temp_novel.is_temporary = false;
+            // ====== Retry / Error ======
+            ApiResponse::Retrying { request, attempt, max_attempts, error } => {
+                tracing::info!("{} retrying ({}/{}) after: {}", request.label(), attempt, max_attempts, error);
+                app_state.retry_status = Some(format!(
+                    "{}失败，正在重试 ({}/{})",
+                    request.label(),
+                    attempt,
+                    max_attempts
+                ));
+                app_state.retrying_request = Some(request.clone());
+            }
+            ApiResponse::Error { request, error } => {
+                tracing::warn!("{} failed: {}", request.label(), error);
+
+                match request {
+                    // 未就绪不是错误，等待 WebSocket 通知即可
+                    _ if error.is_not_ready() => continue,
+                    // 后台轮询失败不打扰用户，下次轮询会重试
+                    ApiRequest::PollNovelStatus(_) | ApiRequest::QueryTaskStatus { .. } => continue,
+                    // 段落范围循环的位置同步失败不打扰用户，照常提交推理窗口
+                    ApiRequest::Seek { sync_only: true, .. } => {
+                        let index = app_state.current_segment_index as u32;
+                        advance_inference_window(&mut app_state, &mut api_events, index);
+                        continue;
+                    }
+                    // 预取失败不打扰用户，切换到该段时会单独加载
+                    ApiRequest::PrefetchAudio { novel_id, voice_id, segment_indices } => {
+                        app_state.audio_buffer.finish_request(*novel_id, *voice_id, segment_indices);
+                        continue;
+                    }
+                    // 登录失败在登录对话框中提示
+                    ApiRequest::Login { .. } => {
+                        app_state.login_dialog.pending = false;
+                        app_state.login_dialog.message = Some(if error.is_unauthorized() {
+                            "用户名或密码错误".to_string()
+                        } else {
+                            format!("登录失败: {}", error)
+                        });
+                        continue;
+                    }
+                    ApiRequest::UploadNovel { temp_id, .. } => {
+                        app_state.uploads.remove(temp_id);
+                        if *error == ApiError::Cancelled {
+                            // 用户取消，移除临时小说
+                            app_state.novels.retain(|n| n.id != *temp_id);
+                            continue;
+                        }
+                        if let Some(temp_novel) = app_state.novels.iter_mut().find(|n| n.id == *temp_id) {
+                            temp_novel.status = "error".to_string();
+                        }
+                    }
+                    ApiRequest::UploadVoice { upload_id, .. } => {
+                        app_state.uploads.remove(upload_id);
+                        if *error == ApiError::Cancelled {
+                            continue;
+                        }
+                    }
+                    ApiRequest::DownloadNovel { novel_id, .. } => {
+                        app_state.downloads.remove(novel_id);
+                        if *error == ApiError::Cancelled {
+                            continue;
+                        }
+                    }
+                    ApiRequest::ExportAudiobook { novel_id, .. } => {
+                        app_state.exports.remove(novel_id);
+                        if *error == ApiError::Cancelled {
+                            continue;
+                        }
                     }
+                    ApiRequest::SubmitInfer { session_id, segment_indices } => {
+                        // 移除预添加的 pending 任务，让预取系统重新提交
+                        app_state.task_manager.tasks.retain(|idx, task| {
+                            !(task.session_id == *session_id
+                                && task.state == crate::state::TaskState::Pending
+                                && segment_indices.contains(idx))
+                        });
+                    }
+                    _ => {}
+                }
+
+                // token 失效且无法刷新，提示重新登录
+                if error.is_unauthorized() {
+                    app_state.login_dialog.open(Some("登录已过期，请重新登录".to_string()));
+                    continue;
+                }
+
+                app_state.set_error(format!("{}失败: {}", request.label(), error));
+                if error.is_retryable() {
+                    app_state.retry_request = Some(request.clone());
                 }
-                app_state.set_error(msg);
             }
         }
     }
 }
 
+/// 登录 token 变化（登录、刷新、退出、失效）后写回当前 profile 的配置
+pub fn persist_auth_token(api_client: Res<ApiClient>, mut config: ResMut<AppConfig>) {
+    if !api_client.auth().take_changed() {
+        return;
+    }
+    let name = config.active_profile.clone();
+    if config.set_profile_auth(&name, api_client.auth().token()) {
+        if let Err(e) = config.save() {
+            tracing::warn!("Failed to save auth token: {}", e);
+        }
+    }
+}
+
+/// 转发后端本地产生的事件（不使用 WebSocket 的后端）
+pub fn poll_backend_events(backend: Res<Backend>, mut response_events: EventWriter<WsResponse>) {
+    for event in backend.take_events() {
+        response_events.send(WsResponse::from(event));
+    }
+}
+
 /// 处理 WebSocket 响应 - V2
 pub fn handle_ws_responses(
     mut events: EventReader<WsResponse>,
@@ -784,11 +1322,84 @@ pub fn handle_ws_responses(
     }
 }
 
-/// 处理音频播放完成，自动播放下一段 - V2
+/// 音频线程自动衔接到排队的下一段：同步当前段落（高亮和进度）并推进推理预取窗口
+pub fn handle_segment_started(
+    mut events: EventReader<SegmentStartedEvent>,
+    mut app_state: ResMut<AppState>,
+    mut api_events: EventWriter<ApiRequest>,
+    mut stop_audio: EventWriter<StopAudioEvent>,
+) {
+    for event in events.read() {
+        // 只处理本次播放中排队的段落，忽略 seek 之前的旧通知
+        let Some((queued, format)) = app_state.queued_segment else { continue };
+        let index = event.segment_index as usize;
+        if queued != event.segment_index
+            || index == app_state.current_segment_index
+            || app_state.playback_state != PlaybackState::Playing
+        {
+            continue;
+        }
+
+        let previous = app_state.current_segment_index;
+        app_state.current_segment_index = index;
+        app_state.current_audio_format = format;
+        if let Some(session) = &mut app_state.current_session {
+            session.current_index = event.segment_index;
+        }
+
+        // 排队之后才设置的睡眠定时：在新段落开头停止
+        if app_state.sleep_timer_stops_after(previous) {
+            tracing::info!("Sleep timer reached after segment {}", previous);
+            stop_audio.send(StopAudioEvent);
+            app_state.sleep_timer = None;
+            app_state.playback_state = PlaybackState::Stopped;
+            continue;
+        }
+
+        follow_segment_change(&mut app_state, &mut api_events, previous, event.segment_index);
+    }
+}
+
+/// 播放从第 `previous` 段推进到第 `index` 段后推进推理窗口
+///
+/// 段落范围循环回到开头时先把新位置同步给服务端 session。服务端 seek 会取消未完成的任务，
+/// 所以推理窗口等 `SeekCompleted` 到达后再提交。
+fn follow_segment_change(app_state: &mut AppState, api_events: &mut EventWriter<ApiRequest>, previous: usize, index: u32) {
+    let session_id = app_state.current_session.as_ref().map(|s| s.session_id.clone());
+    match session_id {
+        Some(session_id) if index as usize != previous + 1 => {
+            api_events.send(ApiRequest::Seek { session_id, segment_index: index, sync_only: true });
+        }
+        _ => advance_inference_window(app_state, api_events, index),
+    }
+}
+
+/// 滑动窗口：当前段变化后提交新的推理预取任务
+fn advance_inference_window(app_state: &mut AppState, api_events: &mut EventWriter<ApiRequest>, index: u32) {
+    let Some(session_id) = app_state.current_session.as_ref().map(|s| s.session_id.clone()) else { return };
+    // 段落范围循环时窗口在范围末尾接回开头
+    let ahead = app_state.task_manager.prefetch_ahead;
+    let indices: Vec<u32> = std::iter::once(index)
+        .chain(app_state.upcoming_segments(index as usize, ahead))
+        .filter(|idx| !app_state.task_manager.tasks.contains_key(idx))
+        .collect();
+    if !indices.is_empty() {
+        // 预添加 pending 任务
+        app_state.task_manager.add_pending_tasks(&session_id, &indices);
+        api_events.send(ApiRequest::SubmitInfer {
+            session_id,
+            segment_indices: indices,
+        });
+    }
+}
+
+/// 处理音频播放完成（播放队列已空），自动播放下一段 - V2
 pub fn handle_audio_finished(
     mut events: EventReader<AudioFinishedEvent>,
     mut app_state: ResMut<AppState>,
     mut api_events: EventWriter<ApiRequest>,
+    mut play_audio: EventWriter<PlayAudioEvent>,
+    mut progress: ResMut<ReadingProgress>,
 ) {
     for _ in events.read() {
         if app_state.playback_state != PlaybackState::Playing {
@@ -797,30 +1408,50 @@ pub fn handle_audio_finished(
 
         // 先提取需要的值，避免借用冲突
         let session_info = app_state.current_session.as_ref().map(|s| {
-            (s.session_id.clone(), s.novel_id, s.voice_id)
+            (s.novel_id, s.voice_id, s.speed)
         });
         
-        let Some((session_id, novel_id, voice_id)) = session_info else { continue };
+        let Some((novel_id, voice_id, speed)) = session_info else { continue };
         
         let total = app_state.segment_pagination.total_segments;
         let current = app_state.current_segment_index;
         
-        if current + 1 >= total {
+        // 下一段（循环段落范围时可能回到范围开头）
+        let Some(next) = app_state.next_segment_index() else {
             // 已播放完最后一段
             app_state.playback_state = PlaybackState::Stopped;
+            app_state.sleep_timer = None;
+            progress.record(novel_id, voice_id, total as u32, total, speed);
+            continue;
+        };
+
+        // 睡眠定时到达：停止自动播放，位置移到下一段（由 track_reading_progress 保存）
+        if app_state.sleep_timer_stops_after(current) {
+            tracing::info!("Sleep timer reached after segment {}", current);
+            app_state.sleep_timer = None;
+            app_state.playback_state = PlaybackState::Stopped;
+            app_state.current_segment_index = next;
+            if let Some(session) = &mut app_state.current_session {
+                session.current_index = next as u32;
+            }
             continue;
         }
         
         // 移动到下一段
-        let next_index = (current + 1) as u32;
+        let next_index = next as u32;
         app_state.current_segment_index = next_index as usize;
         
         if let Some(session) = &mut app_state.current_session {
             session.current_index = next_index;
         }
         
-        // 检查下一段是否就绪
-        if app_state.task_manager.is_segment_ready(next_index) {
+        // 优先使用预取的音频，无需网络请求
+        app_state.queued_segment = None;
+        if let Some(audio) = app_state.audio_buffer.take(&(novel_id, voice_id, next_index)) {
+            app_state.current_audio_format = audio.format;
+            app_state.waiting_for_audio = false;
+            play_audio.send(PlayAudioEvent { segment_index: next_index, stream: audio.to_stream() });
+        } else if app_state.task_manager.is_segment_ready(next_index) {
             // 直接获取音频
             api_events.send(ApiRequest::LoadAudio {
                 novel_id,
@@ -833,16 +1464,7 @@ pub fn handle_audio_finished(
             app_state.waiting_for_audio = true;
         }
         
-        // 滑动窗口：提交新的预取任务
-        let indices = app_state.task_manager.calculate_prefetch_range(next_index, total as u32);
-        if !indices.is_empty() {
-            // 预添加 pending 任务
-            app_state.task_manager.add_pending_tasks(&session_id, &indices);
-            api_events.send(ApiRequest::SubmitInfer {
-                session_id,
-                segment_indices: indices,
-            });
-        }
+        follow_segment_change(&mut app_state, &mut api_events, current, next_index);
     }
 }
 
@@ -925,6 +1547,140 @@ pub fn prefetch_tasks_system(
     }
 }
 
+/// 批量预取音频（每 0.5 秒）
+///
+/// 把预取窗口内已就绪、尚未缓冲的段落合并为一个 `PrefetchAudio` 请求，
+/// `handle_audio_finished` 切换到下一段时直接从缓冲播放。
+pub fn prefetch_audio_system(
+    mut app_state: ResMut<AppState>,
+    mut api_events: EventWriter<ApiRequest>,
+    time: Res<Time>,
+    mut timer: Local<f32>,
+) {
+    if app_state.playback_state != PlaybackState::Playing
+        && app_state.playback_state != PlaybackState::Loading {
+        *timer = 0.0;
+        return;
+    }
+
+    let Some(session) = &app_state.current_session else {
+        *timer = 0.0;
+        return;
+    };
+
+    *timer += time.delta_secs();
+    if *timer < 0.5 {
+        return;
+    }
+    *timer = 0.0;
+
+    let (novel_id, voice_id) = (session.novel_id, session.voice_id);
+    // 当前段由 LoadAudio 边下载边播放，只预取之后的段落（段落范围循环时包括范围开头）
+    let indices: Vec<u32> = app_state
+        .upcoming_segments(app_state.current_segment_index, app_state.task_manager.prefetch_ahead)
+        .into_iter()
+        .filter(|&idx| app_state.task_manager.is_segment_ready(idx))
+        // 已排进播放队列的段落已从缓冲中取出，不需要再取
+        .filter(|&idx| app_state.queued_segment.is_none_or(|(queued, _)| queued != idx))
+        .filter(|&idx| app_state.audio_buffer.needs_fetch(&(novel_id, voice_id, idx)))
+        .collect();
+    if !indices.is_empty() {
+        // 立即标记，避免请求返回前重复提交
+        app_state.audio_buffer.mark_requested(novel_id, voice_id, &indices);
+        api_events.send(ApiRequest::PrefetchAudio {
+            novel_id,
+            voice_id,
+            segment_indices: indices,
+        });
+    }
+}
+
+/// 睡眠定时结束前淡出的时长
+const SLEEP_FADE: Duration = Duration::from_secs(10);
+
+/// 睡眠定时：按播放时间计时并到时停止，结束前几秒逐渐降低音量
+///
+/// 同时负责把主音量（含静音）同步到音频线程，淡出在主音量的基础上进行。
+///
+/// 按段落 / 章节停止的模式在最后一段解码完成（剩余时长已知）后开始淡出，
+/// 停止由 `handle_audio_finished` 处理。
+pub fn update_sleep_timer(
+    time: Res<Time>,
+    config: Res<AppConfig>,
+    mut app_state: ResMut<AppState>,
+    audio_player: Option<Res<AudioPlayer>>,
+    mut stop_audio: EventWriter<StopAudioEvent>,
+) {
+    let playing = app_state.playback_state == PlaybackState::Playing;
+    let stops_after_current = app_state.sleep_timer_stops_after(app_state.current_segment_index);
+    let remaining = match app_state.sleep_timer.as_mut() {
+        Some(SleepTimer::Time { remaining }) => {
+            if playing {
+                *remaining = remaining.saturating_sub(time.delta());
+            }
+            Some(*remaining)
+        }
+        Some(_) if stops_after_current => audio_player.as_ref().and_then(|player| player.remaining()),
+        _ => None,
+    };
+
+    if matches!(app_state.sleep_timer, Some(SleepTimer::Time { remaining }) if remaining.is_zero()) {
+        tracing::info!("Sleep timer expired, stopping playback");
+        stop_audio.send(StopAudioEvent);
+        app_state.playback_state = PlaybackState::Stopped;
+        app_state.sleep_timer = None;
+    }
+
+    // 定时停止后保持静音，直到再次开始播放
+    let Some(player) = audio_player else { return };
+    if app_state.sleep_timer.is_some() || app_state.playback_state != PlaybackState::Stopped {
+        let fade = remaining.map_or(1.0, |r| (r.as_secs_f32() / SLEEP_FADE.as_secs_f32()).min(1.0));
+        player.set_volume(config.volume.effective() * fade);
+    }
+}
+
+/// 播放的段落或速度变化时保存收听进度
+pub fn track_reading_progress(
+    app_state: Res<AppState>,
+    mut progress: ResMut<ReadingProgress>,
+    mut last_recorded: Local<Option<(uuid::Uuid, uuid::Uuid, u32, f32)>>,
+) {
+    let Some(session) = &app_state.current_session else { return };
+    // 段落总数未知时不记录
+    let total = app_state.segment_pagination.total_segments;
+    if total == 0 {
+        return;
+    }
+    // 调整播放速度时同样写入
+    let position = (session.novel_id, session.voice_id, app_state.current_segment_index as u32, session.speed);
+    if *last_recorded == Some(position) {
+        return;
+    }
+    *last_recorded = Some(position);
+    progress.record(position.0, position.1, position.2, total, position.3);
+}
+
+/// 下一段音频已预取到内存时提前排进播放队列，当前段播完后由音频线程无缝衔接
+///
+/// 睡眠定时会在当前段之后停止时不排队，让播放在当前段结束。
+pub fn queue_next_segment(mut app_state: ResMut<AppState>, mut queue_audio: EventWriter<QueueAudioEvent>) {
+    if app_state.playback_state != PlaybackState::Playing {
+        return;
+    }
+    let Some(session) = &app_state.current_session else { return };
+    let Some(next) = app_state.next_segment_index().map(|next| next as u32) else { return };
+    let key = (session.novel_id, session.voice_id, next);
+    if app_state.queued_segment.is_some_and(|(queued, _)| queued == next)
+        || app_state.sleep_timer_stops_after(app_state.current_segment_index)
+    {
+        return;
+    }
+    if let Some(audio) = app_state.audio_buffer.take(&key) {
+        app_state.queued_segment = Some((next, audio.format));
+        queue_audio.send(QueueAudioEvent { segment_index: next, stream: audio.to_stream() });
+    }
+}
+
 /// 定期清理超时的 pending 任务（每 5 秒检查，清理 30 秒超时的）
 pub fn cleanup_stale_tasks_system(
     mut app_state: ResMut<AppState>,
@@ -937,3 +1693,628 @@ pub fn cleanup_stale_tasks_system(
         app_state.task_manager.cleanup_stale_pending(30);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{RetryPolicy, WsEvent};
+    use crate::audio_stream::AudioStream;
+    use crate::config::ServerProfile;
+    use crate::fs_util::TempDir;
+    use crate::mock_server::MockRovelServer;
+    use crate::state::{WsConnectionState, WsRequest};
+    use crate::openai_speech::{OpenAiSpeechBackend, OpenAiSpeechSettings};
+    use crate::websocket::{handle_ws_requests, poll_global_ws_responses, poll_ws_responses, WsClient};
+    use std::io::Read;
+    use std::time::{Duration, Instant};
+
+    /// 发送给音频系统的播放事件
+    #[derive(Resource, Default)]
+    struct PlayedAudio(Vec<AudioStream>);
+
+    impl PlayedAudio {
+        /// 等待第 `index` 段播放的音频下载完成，返回完整内容
+        fn bytes(&self, index: usize) -> Vec<u8> {
+            let mut data = Vec::new();
+            self.0[index].reader().read_to_end(&mut data).unwrap();
+            data
+        }
+    }
+
+    fn record_played_audio(mut events: EventReader<PlayAudioEvent>, mut played: ResMut<PlayedAudio>) {
+        played.0.extend(events.read().map(|e| e.stream.clone()));
+    }
+
+    /// 排进播放队列的段落
+    #[derive(Resource, Default)]
+    struct QueuedAudio(Vec<(u32, AudioStream)>);
+
+    fn record_queued_audio(mut events: EventReader<QueueAudioEvent>, mut queued: ResMut<QueuedAudio>) {
+        queued.0.extend(events.read().map(|e| (e.segment_index, e.stream.clone())));
+    }
+
+    /// 无窗口、无音频设备的 App：只运行 API / WebSocket 系统，连接 mock 服务器
+    fn headless_app(server: &MockRovelServer) -> App {
+        let client = ApiClient::new(server.base_url())
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..Default::default() });
+        headless_app_with_backend(server, client.clone(), Backend::new(client))
+    }
+
+    fn headless_app_with_backend(server: &MockRovelServer, client: ApiClient, backend: Backend) -> App {
+        let ws_base_url = server.ws_base_url();
+        let (ws_client, ws_backend) = (client.clone(), backend.clone());
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<AppState>()
+            .init_resource::<PlayedAudio>()
+            .init_resource::<QueuedAudio>()
+            .insert_resource(AppConfig::default())
+            .insert_resource(AudioCache::disabled())
+            .init_resource::<OfflineLibrary>()
+            .init_resource::<ReadingProgress>()
+            .init_resource::<Bookmarks>()
+            .insert_resource(backend)
+            .insert_resource(client)
+            .add_event::<ApiRequest>()
+            .add_event::<ApiResponse>()
+            .add_event::<PlayAudioEvent>()
+            .add_event::<QueueAudioEvent>()
+            .add_event::<SegmentStartedEvent>()
+            .add_event::<AudioFinishedEvent>()
+            .add_event::<StopAudioEvent>()
+            .add_event::<WsRequest>()
+            .add_event::<WsResponse>()
+            .add_systems(Startup, setup_api_channel)
+            .add_systems(Startup, move |mut commands: Commands| {
+                spawn_ws_clients(&mut commands, ws_base_url.clone(), &ws_client, &ws_backend);
+            })
+            .add_systems(
+                Update,
+                (
+                    handle_api_requests,
+                    poll_api_tasks,
+                    handle_api_responses,
+                    handle_ws_requests,
+                    poll_ws_responses,
+                    poll_global_ws_responses,
+                    poll_backend_events,
+                    handle_ws_responses,
+                    handle_segment_started,
+                    handle_audio_finished,
+                    queue_next_segment,
+                    track_reading_progress,
+                    update_sleep_timer,
+                    prefetch_audio_system,
+                    record_played_audio,
+                    record_queued_audio,
+                )
+                    .chain(),
+            );
+        app.update();
+        app
+    }
+
+    /// 逐帧运行直到条件满足（超时则测试失败）
+    fn run_until(app: &mut App, what: &str, mut done: impl FnMut(&App) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done(app) {
+            assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+            app.update();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn app_state(app: &App) -> &AppState {
+        app.world().resource::<AppState>()
+    }
+
+    fn wait_for_global_channel(app: &mut App, server: &MockRovelServer) {
+        run_until(app, "global WebSocket", |_| server.global_subscriber_count() > 0);
+    }
+
+    #[test]
+    fn loads_novels_and_voices() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["第一段", "第二段"]);
+        let voice = server.add_voice("默认音色");
+        let mut app = headless_app(&server);
+
+        app.world_mut().send_event(ApiRequest::LoadNovels);
+        app.world_mut().send_event(ApiRequest::LoadVoices);
+        run_until(&mut app, "library", |app| {
+            !app_state(app).novels.is_empty() && !app_state(app).voices.is_empty()
+        });
+
+        let state = app_state(&app);
+        assert_eq!(state.novels[0].id, novel.id);
+        assert_eq!(state.novels[0].total_segments, 2);
+        assert_eq!(state.selected_voice.as_ref().map(|v| v.id), Some(voice.id));
+        assert!(!state.loading);
+        assert!(state.error.is_none());
+    }
+
+    #[test]
+    fn responses_from_previous_server_are_dropped_after_switching_profile() {
+        let old = MockRovelServer::start();
+        old.add_novel("旧服务器小说", &["一"]);
+        old.state.lock().unwrap().novel_list_delay = Duration::from_millis(300);
+        let new = MockRovelServer::start();
+        let novel = new.add_novel("新服务器小说", &["一"]);
+
+        let mut app = headless_app(&old);
+        app.add_plugins(bevy::state::app::StatesPlugin)
+            .init_state::<AppView>()
+            .add_event::<SwitchProfileEvent>()
+            .add_systems(Update, handle_switch_profile.before(handle_api_requests))
+            .insert_resource(AppConfig {
+                active_profile: "旧".to_string(),
+                profiles: vec![
+                    ServerProfile::new("旧", old.server_url()),
+                    ServerProfile::new("新", new.server_url()),
+                ],
+                ..Default::default()
+            });
+
+        // 旧服务器的小说列表请求还未返回时切换服务器
+        app.world_mut().send_event(ApiRequest::LoadNovels);
+        app.update();
+        app.world_mut().send_event(SwitchProfileEvent { profile_name: "新".to_string(), reconnect: false });
+        run_until(&mut app, "new server novels", |app| !app_state(app).novels.is_empty());
+        run_until(&mut app, "old server response", |_| {
+            old.state.lock().unwrap().requests.iter().any(|r| r.ends_with("/api/novel/list"))
+        });
+        std::thread::sleep(Duration::from_millis(400));
+        for _ in 0..10 {
+            app.update();
+        }
+
+        let titles: Vec<&str> = app_state(&app).novels.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, [novel.title.as_str()]);
+    }
+
+    #[test]
+    fn retry_status_clears_only_when_retried_request_finishes() {
+        let server = MockRovelServer::start();
+        let mut app = headless_app(&server);
+
+        app.world_mut().send_event(ApiResponse::Retrying {
+            request: ApiRequest::LoadVoices,
+            attempt: 2,
+            max_attempts: 3,
+            error: ApiError::Timeout,
+        });
+        app.update();
+        assert!(app_state(&app).retry_status.is_some());
+
+        // 其他请求的响应不影响重试提示
+        app.world_mut().send_event(ApiResponse::NovelsLoaded(Vec::new()));
+        app.update();
+        assert!(app_state(&app).retry_status.is_some());
+
+        app.world_mut().send_event(ApiResponse::VoicesLoaded(Vec::new()));
+        app.update();
+        assert!(app_state(&app).retry_status.is_none());
+        assert!(app_state(&app).retrying_request.is_none());
+
+        // 同类请求的失败只有参数一致时才算重试结束
+        let novel_id = uuid::Uuid::new_v4();
+        let voice_id = uuid::Uuid::new_v4();
+        let load = |segment_index| ApiRequest::LoadAudio { novel_id, segment_index, voice_id };
+        app.world_mut().send_event(ApiResponse::Retrying {
+            request: load(1),
+            attempt: 2,
+            max_attempts: 3,
+            error: ApiError::Timeout,
+        });
+        app.update();
+        app.world_mut().send_event(ApiResponse::Error { request: load(2), error: ApiError::Timeout });
+        app.update();
+        assert!(app_state(&app).retry_status.is_some());
+        app.world_mut().send_event(ApiResponse::Error { request: load(1), error: ApiError::Timeout });
+        app.update();
+        assert!(app_state(&app).retry_status.is_none());
+
+        // 段落范围循环的同步 seek 不结束用户 seek 的重试
+        let seek = |sync_only| ApiRequest::Seek { session_id: "s".to_string(), segment_index: 3, sync_only };
+        let synced = ApiResponse::SeekCompleted {
+            session_id: "s".to_string(),
+            current_index: 3,
+            cancelled_tasks: 0,
+            sync_only: true,
+        };
+        assert!(!synced.completes(&seek(false)));
+        assert!(synced.completes(&seek(true)));
+    }
+
+    #[test]
+    fn play_submits_inference_and_plays_ready_audio() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["一", "二", "三", "四", "五"]);
+        let voice = server.add_voice("默认音色");
+        let mut app = headless_app(&server);
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+
+        app.world_mut().send_event(ApiRequest::Play {
+            novel_id: novel.id,
+            voice_id: voice.id,
+            start_index: 0,
+        });
+        run_until(&mut app, "first segment audio", |app| {
+            !app.world().resource::<PlayedAudio>().0.is_empty()
+        });
+
+        let played = app.world().resource::<PlayedAudio>().bytes(0);
+        assert_eq!(&played[..4], b"RIFF");
+        let state = app_state(&app);
+        assert_eq!(state.playback_state, PlaybackState::Playing);
+        assert_eq!(state.ws_state, WsConnectionState::Connected);
+        assert_eq!(state.segments.len(), 5);
+        assert!(state.task_manager.is_segment_ready(0));
+        assert_eq!(state.current_session.as_ref().map(|s| s.novel_id), Some(novel.id));
+    }
+
+    #[test]
+    fn uploaded_novel_becomes_ready_through_global_events() {
+        let server = MockRovelServer::start();
+        let mut app = headless_app(&server);
+        wait_for_global_channel(&mut app, &server);
+
+        let dir = TempDir::new("upload");
+        let path = dir.join("novel.txt");
+        std::fs::write(&path, "第一段\n第二段\n第三段\n").unwrap();
+        let temp_novel = crate::api::NovelResponse::create_temporary("上传".to_string());
+        let temp_id = temp_novel.id;
+        app.world_mut().resource_mut::<AppState>().novels.insert(0, temp_novel);
+        app.world_mut().send_event(ApiRequest::UploadNovel {
+            title: "上传".to_string(),
+            file_path: path.clone(),
+            temp_id,
+        });
+
+        run_until(&mut app, "NovelReady", |app| {
+            app_state(app).novels.iter().any(|n| n.title == "上传" && n.status == "ready")
+        });
+        let state = app_state(&app);
+        assert_eq!(state.novels.len(), 1);
+        assert_eq!(state.novels[0].total_segments, 3);
+        assert!(state.uploads.is_empty());
+    }
+
+    #[test]
+    fn deleted_novel_is_removed_through_global_events() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("删除", &["一"]);
+        let mut app = headless_app(&server);
+        wait_for_global_channel(&mut app, &server);
+
+        app.world_mut().send_event(ApiRequest::LoadNovels);
+        run_until(&mut app, "novel list", |app| !app_state(app).novels.is_empty());
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+
+        app.world_mut().send_event(ApiRequest::DeleteNovel(novel.id));
+        run_until(&mut app, "NovelDeleted", |app| app_state(app).novels.is_empty());
+        assert!(app_state(&app).selected_novel.is_none());
+    }
+
+    #[test]
+    fn novel_failed_event_marks_novel_as_error() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("失败", &["一"]);
+        let mut app = headless_app(&server);
+        wait_for_global_channel(&mut app, &server);
+
+        app.world_mut().send_event(ApiRequest::LoadNovels);
+        run_until(&mut app, "novel list", |app| !app_state(app).novels.is_empty());
+
+        server.push_global_event(WsEvent::NovelFailed { novel_id: novel.id, error: "分段失败".to_string() });
+        run_until(&mut app, "NovelFailed", |app| app_state(app).novels[0].status == "error");
+        assert!(app_state(&app).error.as_deref().is_some_and(|e| e.contains("分段失败")));
+    }
+
+    #[test]
+    fn session_closed_by_server_stops_playback() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["一", "二"]);
+        let voice = server.add_voice("默认音色");
+        let mut app = headless_app(&server);
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+
+        app.world_mut().send_event(ApiRequest::Play {
+            novel_id: novel.id,
+            voice_id: voice.id,
+            start_index: 0,
+        });
+        run_until(&mut app, "session channel", |app| {
+            app_state(app).ws_state == WsConnectionState::Connected
+        });
+        let session_id = app_state(&app).current_session.as_ref().unwrap().session_id.clone();
+        server.push_session_event(
+            &session_id,
+            WsEvent::SessionClosed { session_id: session_id.clone(), reason: "idle timeout".to_string() },
+        );
+        run_until(&mut app, "SessionClosed", |app| app_state(app).current_session.is_none());
+
+        let state = app_state(&app);
+        assert_eq!(state.playback_state, PlaybackState::Stopped);
+        assert!(state.task_manager.tasks.is_empty());
+        assert!(state.error.as_deref().is_some_and(|e| e.contains("idle timeout")));
+    }
+
+    #[test]
+    fn prefetched_next_segment_is_queued_for_gapless_playback() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["一", "二", "三", "四", "五"]);
+        let voice = server.add_voice("默认音色");
+        let mut app = headless_app(&server);
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+
+        app.world_mut().send_event(ApiRequest::Play { novel_id: novel.id, voice_id: voice.id, start_index: 0 });
+        run_until(&mut app, "first segment audio", |app| {
+            !app.world().resource::<PlayedAudio>().0.is_empty()
+        });
+        run_until(&mut app, "second segment queued", |app| {
+            !app.world().resource::<QueuedAudio>().0.is_empty()
+        });
+        let single_requests = |server: &MockRovelServer| {
+            server.state.lock().unwrap().requests.iter().filter(|r| *r == "POST /api/audio").count()
+        };
+        let before = single_requests(&server);
+
+        let (index, stream) = app.world().resource::<QueuedAudio>().0[0].clone();
+        let mut queued = Vec::new();
+        stream.reader().read_to_end(&mut queued).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(queued, crate::mock_server::wav_tone(200, 240.0));
+        assert!(!app_state(&app).audio_buffer.contains(&(novel.id, voice.id, 1)));
+
+        // 排队期间经过几轮预取，已排队的段落不会再次请求
+        let deadline = Instant::now() + Duration::from_millis(1200);
+        while Instant::now() < deadline {
+            app.update();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // 音频线程衔接到排队的段落
+        app.world_mut().send_event(SegmentStartedEvent { segment_index: 1 });
+        run_until(&mut app, "third segment queued", |app| {
+            app.world().resource::<QueuedAudio>().0.iter().any(|(idx, _)| *idx == 2)
+        });
+        let batches = server.state.lock().unwrap().batch_audio_requests.clone();
+        for index in 1..=2 {
+            let requested = batches.iter().filter(|batch| batch.contains(&index)).count();
+            assert_eq!(requested, 1, "segment {} prefetched {} times: {:?}", index, requested, batches);
+        }
+
+        assert_eq!(app_state(&app).current_segment_index, 1);
+        assert_eq!(app.world().resource::<PlayedAudio>().0.len(), 1, "queued segments play without a restart");
+        assert_eq!(single_requests(&server), before, "next segment should not be fetched again");
+        let progress = app.world().resource::<ReadingProgress>().get(novel.id).copied();
+        assert_eq!(progress.map(|p| (p.voice_id, p.segment_index, p.total_segments)), Some((voice.id, 1, 5)));
+
+        // 过时的通知（不是排队中的段落）被忽略
+        app.world_mut().send_event(SegmentStartedEvent { segment_index: 4 });
+        app.update();
+        assert_eq!(app_state(&app).current_segment_index, 1);
+    }
+
+    #[test]
+    fn sleep_timer_stops_at_end_of_chapter_and_saves_next_position() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["第一章 开始", "一", "第二章 继续", "二"]);
+        let voice = server.add_voice("默认音色");
+        let mut app = headless_app(&server);
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+
+        app.world_mut().send_event(ApiRequest::Play { novel_id: novel.id, voice_id: voice.id, start_index: 0 });
+        run_until(&mut app, "first segment audio", |app| {
+            !app.world().resource::<PlayedAudio>().0.is_empty() && app_state(app).segments.len() == 4
+        });
+        app.world_mut().resource_mut::<AppState>().sleep_timer = Some(SleepTimer::EndOfChapter);
+
+        // 下一段不是章节标题，继续播放
+        app.world_mut().send_event(AudioFinishedEvent);
+        run_until(&mut app, "second segment audio", |app| {
+            app.world().resource::<PlayedAudio>().0.len() == 2 && app_state(app).playback_state == PlaybackState::Playing
+        });
+
+        // 下一段是第二章标题，本章结束
+        app.world_mut().send_event(AudioFinishedEvent);
+        app.update();
+        app.update();
+        let state = app_state(&app);
+        assert_eq!(state.playback_state, PlaybackState::Stopped);
+        assert_eq!(state.current_segment_index, 2);
+        assert_eq!(state.sleep_timer, None);
+        assert_eq!(app.world().resource::<PlayedAudio>().0.len(), 2);
+        let progress = app.world().resource::<ReadingProgress>().get(novel.id).copied();
+        assert_eq!(progress.map(|p| p.segment_index), Some(2));
+    }
+
+    #[test]
+    fn range_repeat_returns_to_first_segment_of_range() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["一", "二", "三", "四"]);
+        let voice = server.add_voice("默认音色");
+        let mut app = headless_app(&server);
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+
+        app.world_mut().send_event(ApiRequest::Play { novel_id: novel.id, voice_id: voice.id, start_index: 1 });
+        run_until(&mut app, "first segment audio", |app| {
+            !app.world().resource::<PlayedAudio>().0.is_empty() && app_state(app).segments.len() == 4
+        });
+        app.world_mut().resource_mut::<AppState>().repeat = Some(RepeatMode::Range { start: 1, end: 2 });
+        run_until(&mut app, "second segment queued", |app| {
+            app.world().resource::<QueuedAudio>().0.iter().any(|(idx, _)| *idx == 2)
+        });
+
+        // 播到范围的最后一段后不再排队第 4 段，播完回到范围开头
+        app.world_mut().send_event(SegmentStartedEvent { segment_index: 2 });
+        app.update();
+        assert_eq!(app_state(&app).current_segment_index, 2);
+        assert_eq!(app_state(&app).next_segment_index(), Some(1));
+        assert!(!app.world().resource::<QueuedAudio>().0.iter().any(|(idx, _)| *idx == 3));
+        // 接近范围末尾时预取范围开头
+        run_until(&mut app, "range start prefetched", |_| {
+            server.state.lock().unwrap().batch_audio_requests.iter().any(|batch| batch.contains(&1))
+        });
+
+        app.world_mut().send_event(AudioFinishedEvent);
+        run_until(&mut app, "range start audio", |app| {
+            app.world().resource::<PlayedAudio>().0.len() == 2
+        });
+        assert_eq!(app_state(&app).current_segment_index, 1);
+        assert_eq!(app.world().resource::<PlayedAudio>().bytes(1), crate::mock_server::wav_tone(200, 240.0));
+        // 回到范围开头时同步服务端 session 位置，但不重新加载
+        run_until(&mut app, "server seek to range start", |_| {
+            server.state.lock().unwrap().sessions.values().any(|session| session.current_index == 1)
+        });
+        app.update();
+        assert_eq!(app_state(&app).playback_state, PlaybackState::Playing);
+        assert_eq!(app.world().resource::<PlayedAudio>().0.len(), 2);
+
+        // 跳到范围之外取消范围循环
+        let session_id = app_state(&app).current_session.as_ref().unwrap().session_id.clone();
+        app.world_mut().send_event(ApiRequest::Seek { session_id, segment_index: 3, sync_only: false });
+        run_until(&mut app, "seek outside range", |app| app_state(app).current_segment_index == 3);
+        assert_eq!(app_state(&app).repeat, None);
+    }
+
+    #[test]
+    fn range_loop_back_resubmits_tasks_cancelled_by_seek() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["一", "二", "三", "四"]);
+        let voice = server.add_voice("默认音色");
+        server.state.lock().unwrap().seek_cancels_tasks = true;
+        let mut app = headless_app(&server);
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+
+        app.world_mut().send_event(ApiRequest::Play { novel_id: novel.id, voice_id: voice.id, start_index: 1 });
+        run_until(&mut app, "first segment audio", |app| {
+            !app.world().resource::<PlayedAudio>().0.is_empty() && app_state(app).segments.len() == 4
+        });
+        app.world_mut().resource_mut::<AppState>().repeat = Some(RepeatMode::Range { start: 1, end: 2 });
+        run_until(&mut app, "second segment queued", |app| {
+            app.world().resource::<QueuedAudio>().0.iter().any(|(idx, _)| *idx == 2)
+        });
+
+        // 范围开头的音频需要重新合成：回到开头时它的任务还在推理中
+        {
+            let mut state = server.state.lock().unwrap();
+            state.ready_audio.remove(&(novel.id, voice.id, 1));
+            state.infer_delay = Duration::from_millis(300);
+        }
+        app.world_mut().resource_mut::<AppState>().task_manager.tasks.remove(&1);
+        app.world_mut().send_event(SegmentStartedEvent { segment_index: 2 });
+        app.update();
+        app.world_mut().send_event(AudioFinishedEvent);
+
+        // 同步位置的 seek 取消了该任务，重新提交后继续播放
+        run_until(&mut app, "range start audio", |app| {
+            app.world().resource::<PlayedAudio>().0.len() == 2
+        });
+        assert_eq!(app_state(&app).current_segment_index, 1);
+        assert_eq!(app_state(&app).playback_state, PlaybackState::Playing);
+    }
+
+    #[test]
+    fn load_audio_is_served_from_disk_cache() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("测试小说", &["一", "二"]);
+        let voice = server.add_voice("默认音色");
+        // 先于 App 创建，App 中的缓存退出时写回索引后才删除目录
+        let dir = TempDir::new("cache");
+        let mut app = headless_app(&server);
+
+        let cache = AudioCache::open(dir.to_path_buf(), u64::MAX);
+        let cached = crate::mock_server::wav_tone(100, 880.0);
+        cache.insert(
+            novel.id,
+            voice.id,
+            &SegmentAudio { segment_index: 1, format: None, data: cached.clone() },
+        );
+        app.insert_resource(cache);
+
+        app.world_mut().send_event(ApiRequest::LoadAudio { novel_id: novel.id, segment_index: 1, voice_id: voice.id });
+        run_until(&mut app, "cached audio", |app| {
+            !app.world().resource::<PlayedAudio>().0.is_empty()
+        });
+
+        assert_eq!(app.world().resource::<PlayedAudio>().bytes(0), cached);
+        assert!(!server.state.lock().unwrap().requests.iter().any(|r| r == "POST /api/audio"));
+    }
+
+    #[test]
+    fn downloaded_novel_plays_from_local_session() {
+        let server = MockRovelServer::start();
+        let novel = server.add_novel("离线", &["一", "二", "三"]);
+        let voice = server.add_voice("默认音色");
+        let dir = TempDir::new("offline");
+        let offline = OfflineLibrary::open(Some(dir.to_path_buf()));
+        let client = ApiClient::new(server.base_url());
+        let backend = Backend::new(crate::offline::OfflineBackend::new(Backend::new(client.clone()), offline.clone()));
+        let mut app = headless_app_with_backend(&server, client, backend);
+        app.insert_resource(offline.clone());
+
+        app.world_mut().send_event(ApiRequest::DownloadNovel { novel_id: novel.id, voice_id: voice.id });
+        run_until(&mut app, "download started", |app| !app_state(app).downloads.is_empty());
+        run_until(&mut app, "download finished", |app| app_state(app).downloads.is_empty());
+        assert!(offline.is_complete(novel.id, voice.id));
+        assert!(app_state(&app).error.is_none());
+
+        let submits = |server: &MockRovelServer| {
+            server.state.lock().unwrap().requests.iter().filter(|r| *r == "POST /api/infer/submit").count()
+        };
+        let before = submits(&server);
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+        app.world_mut().send_event(ApiRequest::Play { novel_id: novel.id, voice_id: voice.id, start_index: 0 });
+        run_until(&mut app, "offline audio", |app| !app.world().resource::<PlayedAudio>().0.is_empty());
+
+        let state = app_state(&app);
+        assert!(is_offline_session(&state.current_session.as_ref().unwrap().session_id));
+        assert_eq!(state.segments.len(), 3);
+        assert_eq!(submits(&server), before, "offline playback should not submit inference");
+    }
+
+    #[test]
+    fn plays_through_openai_speech_backend_without_websocket() {
+        let server = MockRovelServer::start();
+        let speech = OpenAiSpeechBackend::new(
+            &server.server_url(),
+            OpenAiSpeechSettings::default(),
+            ureq::Agent::new(),
+            &Default::default(),
+            None,
+        );
+        let client = ApiClient::new(server.base_url());
+        let mut app = headless_app_with_backend(&server, client, Backend::new(speech));
+        assert!(app.world().get_resource::<WsClient>().is_none());
+
+        let dir = TempDir::new("speech");
+        let path = dir.join("novel.txt");
+        std::fs::write(&path, "第一段。\n第二段。\n").unwrap();
+        let temp_novel = crate::api::NovelResponse::create_temporary("本地".to_string());
+        let temp_id = temp_novel.id;
+        app.world_mut().resource_mut::<AppState>().novels.insert(0, temp_novel);
+        app.world_mut().send_event(ApiRequest::UploadNovel {
+            title: "本地".to_string(),
+            file_path: path.clone(),
+            temp_id,
+        });
+        app.world_mut().send_event(ApiRequest::LoadVoices);
+        run_until(&mut app, "local novel", |app| {
+            app_state(app).novels.iter().any(|n| n.status == "ready") && app_state(app).selected_voice.is_some()
+        });
+
+        let novel = app_state(&app).novels[0].clone();
+        let voice_id = app_state(&app).selected_voice.as_ref().unwrap().id;
+        app.world_mut().resource_mut::<AppState>().selected_novel = Some(novel.clone());
+        app.world_mut().send_event(ApiRequest::Play { novel_id: novel.id, voice_id, start_index: 0 });
+        run_until(&mut app, "synthesized audio", |app| {
+            !app.world().resource::<PlayedAudio>().0.is_empty()
+        });
+
+        assert_eq!(app_state(&app).playback_state, PlaybackState::Playing);
+        assert_eq!(server.state.lock().unwrap().speech_requests[0]["input"], "第一段。");
+    }
+}
@@ -0,0 +1,280 @@
+//! 变速播放 - 保持音调的时间伸缩（WSOLA）
+//!
+//! rodio 的 `speed()` 通过重采样变速，声音会随速度变尖或变沉。这里用波形相似
+//! 叠加（WSOLA）伸缩时间：输入切成汉宁窗分段，输出以半窗为步长叠加，每一步在
+//! 输入中前进 `半窗 × 速度`，并在附近寻找与上一段自然延续最相似的位置，
+//! 避免相位错开产生的颤音。速度为 1 时直接取自然延续，输出与输入一致。
+//!
+//! 速度通过原子变量读取，播放中调整在下一个半窗生效。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 3.0;
+
+/// 分段（窗）长度，覆盖人声的几个基音周期
+const WINDOW: Duration = Duration::from_millis(30);
+
+/// 在名义位置前后寻找相似波形的范围
+const TOLERANCE: Duration = Duration::from_millis(10);
+
+/// 计算相似度时的采样间隔约为 12 kHz，高采样率下减少计算量
+const CORRELATION_RATE: u32 = 12_000;
+
+/// 限制到支持的速度范围，非法值按原速处理
+pub fn clamp_speed(speed: f32) -> f32 {
+    if speed.is_finite() {
+        speed.clamp(MIN_SPEED, MAX_SPEED)
+    } else {
+        1.0
+    }
+}
+
+/// 按共享速度伸缩时间的 rodio Source
+pub struct TimeStretch<S> {
+    inner: S,
+    /// 速度（`f32` 的位表示）
+    speed: Arc<AtomicU32>,
+    channels: usize,
+    sample_rate: u32,
+    /// 半窗长度（帧），也是输出步长
+    hop: usize,
+    tolerance: usize,
+    stride: usize,
+    /// 汉宁窗，长度为 `2 * hop`，相隔半窗的两个窗相加恒为 1
+    window: Vec<f32>,
+    /// 交错的输入样本，第一帧在输入中的绝对位置为 `input_start`
+    input: VecDeque<f32>,
+    input_start: usize,
+    inner_done: bool,
+    /// 下一段在输入中的名义位置（帧）
+    position: f64,
+    /// 上一段选中的起点
+    previous: Option<usize>,
+    /// 上一段后半窗加权后的样本，与下一段前半窗叠加
+    tail: Vec<f32>,
+    output: VecDeque<i16>,
+}
+
+impl<S> TimeStretch<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    pub fn new(inner: S, speed: Arc<AtomicU32>) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        let sample_rate = inner.sample_rate().max(1);
+        let hop = ((sample_rate as f64 * WINDOW.as_secs_f64()) as usize / 2).max(1);
+        let window = (0..2 * hop)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::PI * i as f32 / hop as f32).cos())
+            .collect();
+        Self {
+            inner,
+            speed,
+            channels,
+            sample_rate,
+            hop,
+            tolerance: (sample_rate as f64 * TOLERANCE.as_secs_f64()) as usize,
+            stride: (sample_rate / CORRELATION_RATE).max(1) as usize,
+            window,
+            input: VecDeque::new(),
+            input_start: 0,
+            inner_done: false,
+            position: 0.0,
+            previous: None,
+            tail: vec![0.0; hop * channels],
+            output: VecDeque::new(),
+        }
+    }
+
+    fn input_end(&self) -> usize {
+        self.input_start + self.input.len() / self.channels
+    }
+
+    /// 绝对位置 `frame` 的样本，超出已读取范围时为静音
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        frame
+            .checked_sub(self.input_start)
+            .and_then(|offset| self.input.get(offset * self.channels + channel))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// 各声道之和，用于寻找相似位置
+    fn mono(&self, frame: usize) -> f32 {
+        (0..self.channels).map(|c| self.sample(frame, c)).sum()
+    }
+
+    /// 从内部音源读取到 `end` 帧，音源结束时最后不完整的帧补零
+    fn fill(&mut self, end: usize) {
+        while !self.inner_done && self.input_end() < end {
+            for c in 0..self.channels {
+                match self.inner.next() {
+                    Some(sample) => self.input.push_back(sample as f32),
+                    None => {
+                        self.inner_done = true;
+                        if c > 0 {
+                            self.input.extend(std::iter::repeat_n(0.0, self.channels - c));
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 在 `nominal` 附近寻找与 `natural`（上一段的自然延续）最相似的起点
+    fn best_match(&self, natural: usize, nominal: usize) -> usize {
+        let lo = nominal.saturating_sub(self.tolerance);
+        let hi = nominal + self.tolerance;
+        let reference: Vec<f32> = (0..self.hop).step_by(self.stride).map(|i| self.mono(natural + i)).collect();
+        let region: Vec<f32> = (lo..hi + self.hop).map(|frame| self.mono(frame)).collect();
+
+        let mut best = (nominal, f32::MIN);
+        for candidate in lo..=hi {
+            let (mut dot, mut energy) = (0.0f32, 0.0f32);
+            for (k, r) in reference.iter().enumerate() {
+                let x = region[candidate - lo + k * self.stride];
+                dot += r * x;
+                energy += x * x;
+            }
+            let score = dot / (energy + 1.0).sqrt();
+            if score > best.1 {
+                best = (candidate, score);
+            }
+        }
+        best.0
+    }
+
+    /// 输出一个半窗，输入耗尽后返回 `false`
+    fn step(&mut self) -> bool {
+        let speed = clamp_speed(f32::from_bits(self.speed.load(Ordering::Relaxed))) as f64;
+        let nominal = self.position.round() as usize;
+        self.fill(nominal + self.tolerance + 2 * self.hop);
+
+        if self.inner_done && nominal >= self.input_end() {
+            // 输入耗尽：输出最后的半窗后结束
+            if self.previous.take().is_none() {
+                return false;
+            }
+            let tail = std::mem::take(&mut self.tail);
+            self.output.extend(tail.into_iter().map(to_i16));
+            return true;
+        }
+
+        let start = match self.previous {
+            Some(previous) if (speed - 1.0).abs() > 1e-3 => self.best_match(previous + self.hop, nominal),
+            Some(previous) => previous + self.hop,
+            None => nominal,
+        };
+
+        for i in 0..self.hop {
+            // 第一段前面没有可叠加的内容，不做淡入
+            let weight = if self.previous.is_some() { self.window[i] } else { 1.0 };
+            for c in 0..self.channels {
+                let k = i * self.channels + c;
+                let value = self.tail[k] + weight * self.sample(start + i, c);
+                self.output.push_back(to_i16(value));
+                self.tail[k] = self.window[self.hop + i] * self.sample(start + self.hop + i, c);
+            }
+        }
+
+        self.previous = Some(start);
+        self.position += self.hop as f64 * speed;
+
+        // 丢弃之后不会再用到的输入：下一段的自然延续和搜索范围都在 keep_from 之后
+        let keep_from = (start + self.hop).min((self.position as usize).saturating_sub(self.tolerance));
+        let drop_frames = keep_from.saturating_sub(self.input_start).min(self.input.len() / self.channels);
+        self.input.drain(..drop_frames * self.channels);
+        self.input_start += drop_frames;
+        true
+    }
+}
+
+fn to_i16(value: f32) -> i16 {
+    value.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+impl<S> Iterator for TimeStretch<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        loop {
+            if let Some(sample) = self.output.pop_front() {
+                return Some(sample);
+            }
+            if !self.step() {
+                return None;
+            }
+        }
+    }
+}
+
+impl<S> rodio::Source for TimeStretch<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn sine(frequency: f32, frames: usize) -> Vec<i16> {
+        (0..frames)
+            .map(|i| ((i as f32 * frequency * std::f32::consts::TAU / 16_000.0).sin() * 10_000.0) as i16)
+            .collect()
+    }
+
+    fn stretch(samples: &[i16], speed: f32) -> Vec<i16> {
+        let source = SamplesBuffer::new(1, 16_000, samples.to_vec());
+        TimeStretch::new(source, Arc::new(AtomicU32::new(speed.to_bits()))).collect()
+    }
+
+    /// 过零次数估算的频率
+    fn frequency(samples: &[i16]) -> f32 {
+        let crossings = samples.windows(2).filter(|w| (w[0] < 0) != (w[1] < 0)).count();
+        crossings as f32 * 16_000.0 / samples.len() as f32 / 2.0
+    }
+
+    #[test]
+    fn normal_speed_passes_audio_through_unchanged() {
+        let input = sine(440.0, 16_000);
+        let output = stretch(&input, 1.0);
+        // 末尾最多多出一个窗长的静音
+        assert!(output.len() >= input.len() && output.len() <= input.len() + 480, "{}", output.len());
+        assert!(input.iter().zip(&output).all(|(a, b)| (a - b).abs() <= 1));
+    }
+
+    #[test]
+    fn speed_changes_duration_but_keeps_pitch() {
+        let input = sine(440.0, 16_000);
+        for (speed, expected_len) in [(2.0, 8_000), (0.5, 32_000), (1.5, 10_667)] {
+            let output = stretch(&input, speed);
+            assert!(output.len().abs_diff(expected_len) <= 480, "speed {}: {} samples", speed, output.len());
+            let pitch = frequency(&output[240..output.len() - 480]);
+            assert!((pitch - 440.0).abs() < 10.0, "speed {}: {} Hz", speed, pitch);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! TLS 配置 - https / wss
+//!
+//! 默认信任 webpki 内置根证书，每个服务器 profile 还可以配置:
+//! - 自定义 CA 证书（PEM 文件，可包含多个证书），用于私有 CA 签发的证书
+//! - 自签名证书的 SHA-256 指纹，指纹匹配时跳过证书链和主机名校验
+//!
+//! HTTP 客户端和两个 WebSocket 连接共用同一份 `rustls::ClientConfig`。
+
+use anyhow::{anyhow, Context, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// 服务器 profile 的 TLS 设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    /// 额外信任的 CA 证书（PEM 文件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    /// 信任的服务器证书 SHA-256 指纹（十六进制，可带 `:` 分隔）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_sha256: Option<String>,
+}
+
+impl TlsSettings {
+    pub fn is_empty(&self) -> bool {
+        self.ca_bundle.is_none() && self.cert_sha256.is_none()
+    }
+}
+
+/// 根据 profile 设置创建 TLS 客户端配置
+pub fn client_config(settings: &TlsSettings) -> Result<Arc<ClientConfig>> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    if let Some(path) = &settings.ca_bundle {
+        let mut added = 0;
+        for cert in CertificateDer::pem_file_iter(path)
+            .with_context(|| format!("无法读取 CA 证书 {}", path.display()))?
+        {
+            let cert = cert.with_context(|| format!("CA 证书格式错误 {}", path.display()))?;
+            roots
+                .add(cert)
+                .with_context(|| format!("无效的 CA 证书 {}", path.display()))?;
+            added += 1;
+        }
+        if added == 0 {
+            return Err(anyhow!("{} 中没有证书", path.display()));
+        }
+        tracing::info!("Trusting {} CA certificate(s) from {:?}", added, path);
+    }
+
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let config = match &settings.cert_sha256 {
+        Some(fingerprint) => {
+            let verifier = PinnedCertVerifier {
+                fingerprint: parse_fingerprint(fingerprint)?,
+                webpki: WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                    .build()?,
+                provider,
+            };
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(verifier))
+                .with_no_client_auth()
+        }
+        None => builder.with_root_certificates(roots).with_no_client_auth(),
+    };
+    Ok(Arc::new(config))
+}
+
+/// 解析 SHA-256 指纹（64 位十六进制，忽略 `:`、空白和大小写）
+pub fn parse_fingerprint(text: &str) -> Result<[u8; 32]> {
+    let hex: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':')
+        .collect();
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("证书指纹包含非十六进制字符"));
+    }
+    if hex.len() != 64 {
+        return Err(anyhow!("证书指纹应为 64 位十六进制 SHA-256"));
+    }
+    let mut fingerprint = [0u8; 32];
+    for (i, byte) in fingerprint.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(fingerprint)
+}
+
+/// 证书的 SHA-256 指纹
+fn cert_fingerprint(cert: &CertificateDer<'_>) -> [u8; 32] {
+    let digest = ring::digest::digest(&ring::digest::SHA256, cert.as_ref());
+    let mut fingerprint = [0u8; 32];
+    fingerprint.copy_from_slice(digest.as_ref());
+    fingerprint
+}
+
+/// 证书指纹校验：指纹匹配时直接信任，否则按常规证书链校验
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    fingerprint: [u8; 32],
+    webpki: Arc<WebPkiServerVerifier>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if cert_fingerprint(end_entity) == self.fingerprint {
+            return Ok(ServerCertVerified::assertion());
+        }
+        self.webpki
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_accepts_colon_separated_and_mixed_case() {
+        let plain = "ab".repeat(32);
+        let separated = vec!["AB"; 32].join(":");
+        assert_eq!(parse_fingerprint(&plain).unwrap(), [0xab; 32]);
+        assert_eq!(parse_fingerprint(&separated).unwrap(), [0xab; 32]);
+        assert!(parse_fingerprint("abcd").is_err());
+        assert!(parse_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn pinned_config_builds_without_ca_bundle() {
+        let settings = TlsSettings {
+            ca_bundle: None,
+            cert_sha256: Some("00".repeat(32)),
+        };
+        assert!(client_config(&settings).is_ok());
+        assert!(client_config(&TlsSettings::default()).is_ok());
+    }
+}
@@ -2,7 +2,20 @@
 
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
-use crate::state::{ApiRequest, AppState, AppView, FilePickerRequest, FilePickerType, PauseAudioEvent, PlaybackState, ResumeAudioEvent, StopAudioEvent, TaskState};
+use crate::audio::SegmentTransition;
+use crate::audio_cache::AudioCache;
+use crate::bookmarks::Bookmarks;
+use crate::audio_format::BandwidthPreference;
+use crate::config::AppConfig;
+use crate::export::ExportFormat;
+use crate::subtitles::SubtitleFormat;
+use crate::offline::{OfflineLibrary, OfflineStatus};
+use crate::progress::{NovelProgress, ReadingProgress};
+use crate::time_stretch::{MAX_SPEED, MIN_SPEED};
+use crate::state::{ApiRequest, AppState, AppView, CurrentSession, DownloadTask, SleepTimer, ExportTask, FilePickerRequest, FilePickerType, PauseAudioEvent, PlaybackState, RepeatMode, ResumeAudioEvent, SeekAudioEvent, StopAudioEvent, SwitchProfileEvent, TaskState, UploadKind};
+use crate::tls::{self, TlsSettings};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 // 颜色主题
 mod colors {
@@ -35,6 +48,13 @@ pub fn ui_system(
     mut stop_audio_events: EventWriter<StopAudioEvent>,
     mut pause_audio_events: EventWriter<PauseAudioEvent>,
     mut resume_audio_events: EventWriter<ResumeAudioEvent>,
+    mut seek_audio_events: EventWriter<SeekAudioEvent>,
+    mut config: ResMut<AppConfig>,
+    mut switch_profile_events: EventWriter<SwitchProfileEvent>,
+    audio_cache: Res<AudioCache>,
+    offline: Res<OfflineLibrary>,
+    progress: Res<ReadingProgress>,
+    mut bookmarks: ResMut<Bookmarks>,
 ) {
     let ctx = contexts.ctx_mut();
 
@@ -49,16 +69,33 @@ pub fn ui_system(
                 &mut next_view,
                 &mut api_events,
                 &mut file_picker_events,
+                &offline,
+                &progress,
             );
         }
         AppView::Player => {
-            player_ui(ctx, &mut app_state, &mut next_view, &mut api_events, &mut stop_audio_events, &mut pause_audio_events, &mut resume_audio_events);
+            player_ui(
+                ctx,
+                &mut app_state,
+                &mut next_view,
+                &mut api_events,
+                &mut stop_audio_events,
+                &mut pause_audio_events,
+                &mut resume_audio_events,
+                &mut seek_audio_events,
+                &mut file_picker_events,
+                &mut bookmarks,
+                &mut config,
+            );
         }
     }
 
     // 上传对话框
     upload_novel_dialog(ctx, &mut app_state, &mut api_events, &mut file_picker_events);
     upload_voice_dialog(ctx, &mut app_state, &mut api_events, &mut file_picker_events);
+    export_dialog(ctx, &mut app_state, &mut config, &mut api_events, &mut file_picker_events);
+    settings_dialog(ctx, &mut app_state, &mut config, &audio_cache, &mut api_events, &mut switch_profile_events);
+    login_dialog(ctx, &mut app_state, &config, &mut api_events);
 
     // 错误提示
     if let Some(error) = &app_state.error.clone() {
@@ -73,7 +110,14 @@ pub fn ui_system(
                 ui.add_space(12.0);
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     if styled_button(ui, "确定", colors::ACCENT).clicked() {
-                        app_state.error = None;
+                        app_state.clear_error();
+                    }
+                    if let Some(request) = app_state.retry_request.clone() {
+                        ui.add_space(8.0);
+                        if styled_button(ui, "重试", colors::WARNING).clicked() {
+                            app_state.clear_error();
+                            api_events.send(request);
+                        }
                     }
                 });
             });
@@ -98,8 +142,9 @@ pub fn ui_system(
                         ui.horizontal(|ui| {
                             ui.spinner();
                             ui.add_space(12.0);
+                            let text = app_state.retry_status.as_deref().unwrap_or("加载中...");
                             ui.label(
-                                egui::RichText::new("加载中...")
+                                egui::RichText::new(text)
                                     .color(colors::TEXT_PRIMARY)
                                     .size(16.0),
                             );
@@ -181,6 +226,8 @@ fn novel_list_ui(
     next_view: &mut ResMut<NextState<AppView>>,
     api_events: &mut EventWriter<ApiRequest>,
     file_picker_events: &mut EventWriter<FilePickerRequest>,
+    offline: &OfflineLibrary,
+    progress: &ReadingProgress,
 ) {
     // 顶部导航栏
     egui::TopBottomPanel::top("top_panel")
@@ -205,6 +252,10 @@ fn novel_list_ui(
                 );
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if styled_button(ui, "⚙ 设置", colors::BG_CARD).clicked() {
+                        app_state.settings_dialog.show = true;
+                    }
+                    ui.add_space(8.0);
                     if styled_button(ui, "🔄 刷新", colors::BG_CARD).clicked() {
                         api_events.send(ApiRequest::LoadNovels);
                         api_events.send(ApiRequest::LoadVoices);
@@ -248,6 +299,34 @@ fn novel_list_ui(
             ui.add(egui::Separator::default().spacing(1.0));
             ui.add_space(12.0);
 
+            // 正在上传的音色
+            let voice_uploads: Vec<_> = app_state.uploads.iter()
+                .filter(|(_, u)| u.kind == UploadKind::Voice)
+                .map(|(id, u)| (*id, u.label.clone(), u.fraction()))
+                .collect();
+            let mut upload_to_cancel: Option<uuid::Uuid> = None;
+            for (upload_id, label, fraction) in &voice_uploads {
+                ui.label(
+                    egui::RichText::new(format!("📤 {}", label))
+                        .size(13.0)
+                        .color(colors::TEXT_PRIMARY),
+                );
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::ProgressBar::new(*fraction)
+                            .desired_width(ui.available_width() - 48.0)
+                            .show_percentage(),
+                    );
+                    if ui.small_button("取消").clicked() {
+                        upload_to_cancel = Some(*upload_id);
+                    }
+                });
+                ui.add_space(8.0);
+            }
+            if let Some(id) = upload_to_cancel {
+                app_state.cancel_upload(id);
+            }
+
             if app_state.voices.is_empty() {
                 ui.vertical_centered(|ui| {
                     ui.add_space(40.0);
@@ -417,9 +496,83 @@ fn novel_list_ui(
                 let novels_display: Vec<_> = app_state.novels.iter().map(|n| {
                     (n.id, n.title.clone(), n.status.clone(), n.total_segments, n.created_at.clone())
                 }).collect();
+                let upload_progress: HashMap<uuid::Uuid, f32> = app_state.uploads.iter()
+                    .map(|(id, u)| (*id, u.fraction()))
+                    .collect();
+                let downloads: HashMap<uuid::Uuid, DownloadTask> = app_state.downloads.clone();
+                let exports: HashMap<uuid::Uuid, ExportTask> = app_state.exports.clone();
+                let offline_status: HashMap<uuid::Uuid, OfflineStatus> = novels_display.iter()
+                    .filter_map(|(id, ..)| offline.status(*id).map(|status| (*id, status)))
+                    .collect();
+                let saved_progress: HashMap<uuid::Uuid, NovelProgress> = novels_display.iter()
+                    .filter_map(|(id, ..)| progress.get(*id).map(|p| (*id, *p)))
+                    .collect();
                 
                 let mut novel_to_delete: Option<uuid::Uuid> = None;
-                let mut novel_to_play: Option<(uuid::Uuid, uuid::Uuid, usize)> = None; // (novel_id, voice_id, total_segments)
+                let mut upload_to_cancel: Option<uuid::Uuid> = None;
+                let mut novel_to_download: Option<(uuid::Uuid, uuid::Uuid)> = None;
+                let mut download_to_cancel: Option<uuid::Uuid> = None;
+                let mut offline_to_remove: Option<uuid::Uuid> = None;
+                let mut novel_to_export: Option<(uuid::Uuid, uuid::Uuid)> = None;
+                let mut export_to_cancel: Option<uuid::Uuid> = None;
+                let mut export_to_dismiss: Option<uuid::Uuid> = None;
+                let mut novel_to_play: Option<(uuid::Uuid, uuid::Uuid, usize, u32)> = None; // (novel_id, voice_id, total_segments, start_index)
+
+                // 继续收听：最近播放且未听完的小说
+                let ready_novels = novels_display.iter().filter(|n| n.2 == "ready").map(|n| n.0);
+                if let Some((novel_id, saved)) = progress.latest(ready_novels) {
+                    let title = novels_display.iter().find(|n| n.0 == novel_id).map(|n| n.1.clone()).unwrap_or_default();
+                    let saved_voice = app_state.voices.iter().find(|v| v.id == saved.voice_id);
+                    // 保存的音色已删除时使用当前选中的音色
+                    let voice = saved_voice.or(app_state.selected_voice.as_ref()).cloned();
+                    egui::Frame::none()
+                        .fill(colors::BG_HIGHLIGHT)
+                        .rounding(12.0)
+                        .inner_margin(16.0)
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.vertical(|ui| {
+                                    ui.label(
+                                        egui::RichText::new("🔖 继续收听")
+                                            .size(12.0)
+                                            .color(colors::TEXT_SECONDARY),
+                                    );
+                                    ui.label(
+                                        egui::RichText::new(&title)
+                                            .size(18.0)
+                                            .strong()
+                                            .color(colors::TEXT_PRIMARY),
+                                    );
+                                    let mut detail = format!(
+                                        "第 {} / {} 段 · {}",
+                                        saved.segment_index + 1,
+                                        saved.total_segments,
+                                        format_elapsed(saved.updated_at)
+                                    );
+                                    if let Some(voice) = &voice {
+                                        detail = format!("{} · {}", voice.name, detail);
+                                    }
+                                    ui.label(egui::RichText::new(detail).size(12.0).color(colors::TEXT_SECONDARY));
+                                });
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    if let Some(voice) = &voice {
+                                        if styled_button(ui, "▶ 继续收听", colors::SUCCESS).clicked() {
+                                            let total = novels_display.iter().find(|n| n.0 == novel_id).map_or(0, |n| n.3);
+                                            app_state.selected_voice = Some(voice.clone());
+                                            novel_to_play = Some((novel_id, voice.id, total, saved.start_index()));
+                                        }
+                                    } else {
+                                        ui.label(
+                                            egui::RichText::new("← 请先选择音色")
+                                                .size(12.0)
+                                                .color(colors::WARNING),
+                                        );
+                                    }
+                                });
+                            });
+                        });
+                    ui.add_space(16.0);
+                }
                 
                 egui::ScrollArea::vertical().show(ui, |ui| {
                     for (novel_id, novel_title, novel_status, total_segments, created_at) in &novels_display {
@@ -497,13 +650,85 @@ fn novel_list_ui(
                                                 .size(12.0)
                                                 .color(colors::TEXT_MUTED),
                                             );
+                                            // 收听进度
+                                            if let Some(saved) = saved_progress.get(novel_id) {
+                                                ui.add_space(16.0);
+                                                let text = if saved.is_finished() {
+                                                    "✓ 已听完".to_string()
+                                                } else {
+                                                    format!("🔖 听到第 {} 段", saved.segment_index + 1)
+                                                };
+                                                ui.label(egui::RichText::new(text).size(12.0).color(colors::ACCENT));
+                                            }
                                         });
+                                        // 上传进度
+                                        if let Some(fraction) = upload_progress.get(novel_id) {
+                                            ui.add_space(6.0);
+                                            ui.add(
+                                                egui::ProgressBar::new(*fraction)
+                                                    .desired_width(240.0)
+                                                    .show_percentage(),
+                                            );
+                                        }
+                                        // 离线下载进度 / 离线副本
+                                        if let Some(download) = downloads.get(novel_id) {
+                                            ui.add_space(6.0);
+                                            ui.add(
+                                                egui::ProgressBar::new(download.fraction())
+                                                    .desired_width(240.0)
+                                                    .text(format!("离线下载 {}/{} 段", download.downloaded, download.total)),
+                                            );
+                                        } else if let Some(status) = offline_status.get(novel_id) {
+                                            ui.add_space(4.0);
+                                            let (text, color) = if status.is_complete() {
+                                                (format!("📥 已离线 · {}", status.voice_name), colors::SUCCESS)
+                                            } else {
+                                                (
+                                                    format!("📥 已下载 {}/{} 段 · {}", status.downloaded, status.total, status.voice_name),
+                                                    colors::WARNING,
+                                                )
+                                            };
+                                            ui.label(egui::RichText::new(text).size(12.0).color(color));
+                                        }
+                                        // 有声书导出进度 / 结果
+                                        if let Some(export) = exports.get(novel_id) {
+                                            ui.add_space(6.0);
+                                            ui.horizontal(|ui| {
+                                                if let Some(summary) = &export.finished {
+                                                    ui.label(
+                                                        egui::RichText::new(format!("🎧 已导出 {} 章", summary.chapters))
+                                                            .size(12.0)
+                                                            .color(colors::SUCCESS),
+                                                    )
+                                                    .on_hover_text(summary.output.display().to_string());
+                                                    if ui.small_button("✕").on_hover_text("关闭").clicked() {
+                                                        export_to_dismiss = Some(*novel_id);
+                                                    }
+                                                } else {
+                                                    ui.add(
+                                                        egui::ProgressBar::new(export.fraction())
+                                                            .desired_width(240.0)
+                                                            .text(format!("导出有声书 {}/{} 段", export.done, export.total)),
+                                                    );
+                                                    if ui.small_button("取消").on_hover_text("取消导出").clicked() {
+                                                        export_to_cancel = Some(*novel_id);
+                                                    }
+                                                }
+                                            });
+                                        }
                                     });
 
                                     // 右侧按钮
                                     ui.with_layout(
                                         egui::Layout::right_to_left(egui::Align::Center),
                                         |ui| {
+                                            if upload_progress.contains_key(novel_id) {
+                                                if ui.button("取消").on_hover_text("取消上传").clicked() {
+                                                    upload_to_cancel = Some(*novel_id);
+                                                }
+                                                ui.add_space(8.0);
+                                            }
+
                                             // 只有非上传中状态的小说才能删除
                                             let can_delete = novel_status != "uploading";
                                             if ui.add_enabled(
@@ -521,11 +746,18 @@ fn novel_list_ui(
                                             let has_voice = selected_voice_id.is_some();
                                             
                                             if is_ready && has_voice {
-                                                if styled_button(ui, "▶ 播放", colors::SUCCESS)
+                                                let start_index = progress.start_index(*novel_id);
+                                                let label = if start_index > 0 { "▶ 继续" } else { "▶ 播放" };
+                                                if styled_button(ui, label, colors::SUCCESS)
                                                     .clicked()
                                                 {
                                                     if let Some(voice_id) = selected_voice_id {
-                                                        novel_to_play = Some((*novel_id, voice_id, *total_segments));
+                                                        novel_to_play = Some((*novel_id, voice_id, *total_segments, start_index));
+                                                    }
+                                                }
+                                                if start_index > 0 && icon_button(ui, "⏮", "从头播放").clicked() {
+                                                    if let Some(voice_id) = selected_voice_id {
+                                                        novel_to_play = Some((*novel_id, voice_id, *total_segments, 0));
                                                     }
                                                 }
                                             } else if !is_ready {
@@ -546,6 +778,50 @@ fn novel_list_ui(
                                                         .color(colors::WARNING),
                                                 );
                                             }
+
+                                            // 导出有声书
+                                            if is_ready && !exports.contains_key(novel_id) {
+                                                if let Some(voice_id) = selected_voice_id {
+                                                    ui.add_space(8.0);
+                                                    if ui.add(egui::Button::new("🎧 导出").fill(colors::BG_CARD).rounding(6.0))
+                                                        .on_hover_text("用当前音色导出为有声书文件")
+                                                        .clicked()
+                                                    {
+                                                        novel_to_export = Some((*novel_id, voice_id));
+                                                    }
+                                                }
+                                            }
+
+                                            // 离线下载
+                                            ui.add_space(8.0);
+                                            if downloads.contains_key(novel_id) {
+                                                if ui.button("取消").on_hover_text("取消离线下载").clicked() {
+                                                    download_to_cancel = Some(*novel_id);
+                                                }
+                                            } else {
+                                                let status = offline_status.get(novel_id);
+                                                if status.is_some()
+                                                    && ui.add(egui::Button::new("移除离线").fill(colors::BG_CARD).rounding(6.0))
+                                                        .on_hover_text("删除本地保存的文本和音频")
+                                                        .clicked()
+                                                {
+                                                    offline_to_remove = Some(*novel_id);
+                                                }
+                                                let downloaded = status.is_some_and(|s| {
+                                                    s.is_complete() && Some(s.voice_id) == selected_voice_id
+                                                });
+                                                if is_ready && !downloaded {
+                                                    if let Some(voice_id) = selected_voice_id {
+                                                        ui.add_space(8.0);
+                                                        if ui.add(egui::Button::new("⬇ 离线").fill(colors::BG_CARD).rounding(6.0))
+                                                            .on_hover_text("用当前音色下载整本小说，无网络时也可播放")
+                                                            .clicked()
+                                                        {
+                                                            novel_to_download = Some((*novel_id, voice_id));
+                                                        }
+                                                    }
+                                                }
+                                            }
                                         },
                                     );
                                 });
@@ -555,11 +831,46 @@ fn novel_list_ui(
                 });
                 
                 // 在循环外处理操作
+                if let Some(id) = upload_to_cancel {
+                    app_state.cancel_upload(id);
+                }
+
+                if let Some(id) = download_to_cancel {
+                    app_state.cancel_download(id);
+                }
+
+                if let Some(id) = offline_to_remove {
+                    offline.remove(id);
+                }
+
+                if let Some((novel_id, voice_id)) = novel_to_download {
+                    api_events.send(ApiRequest::DownloadNovel { novel_id, voice_id });
+                }
+
+                if let Some(id) = export_to_cancel {
+                    app_state.cancel_export(id);
+                }
+
+                if let Some(id) = export_to_dismiss {
+                    app_state.exports.remove(&id);
+                }
+
+                if let Some((novel_id, voice_id)) = novel_to_export {
+                    if let Some(novel) = app_state.novels.iter().find(|n| n.id == novel_id).cloned() {
+                        app_state.export_dialog.open(novel, voice_id);
+                    }
+                }
+
                 if let Some(id) = novel_to_delete {
-                    api_events.send(ApiRequest::DeleteNovel(id));
+                    // 上传失败的临时小说只存在于本地，直接移除
+                    if app_state.novels.iter().any(|n| n.id == id && n.is_temporary) {
+                        app_state.novels.retain(|n| n.id != id);
+                    } else {
+                        api_events.send(ApiRequest::DeleteNovel(id));
+                    }
                 }
                 
-                if let Some((novel_id, voice_id, total_segments)) = novel_to_play {
+                if let Some((novel_id, voice_id, total_segments, start_index)) = novel_to_play {
                     // 找到对应的 novel 并设置
                     if let Some(novel) = app_state.novels.iter().find(|n| n.id == novel_id).cloned() {
                         app_state.selected_novel = Some(novel);
@@ -570,7 +881,7 @@ fn novel_list_ui(
                     api_events.send(ApiRequest::Play {
                         novel_id,
                         voice_id,
-                        start_index: 0,
+                        start_index,
                     });
                     next_view.set(AppView::Player);
                 }
@@ -708,11 +1019,13 @@ fn upload_novel_dialog(
                             
                             // 立即创建临时小说对象并添加到列表中
                             let temp_novel = crate::api::NovelResponse::create_temporary(title.clone());
+                            let temp_id = temp_novel.id;
                             app_state.novels.insert(0, temp_novel); // 插入到列表顶部
                             
                             api_events.send(ApiRequest::UploadNovel {
                                 title,
                                 file_path: path,
+                                temp_id,
                             });
                             app_state.upload_dialog.reset_novel();
                         }
@@ -871,6 +1184,7 @@ fn upload_voice_dialog(
                                 name,
                                 description,
                                 file_path: path,
+                                upload_id: uuid::Uuid::new_v4(),
                             });
                             app_state.upload_dialog.reset_voice();
                         }
@@ -880,125 +1194,875 @@ fn upload_voice_dialog(
         });
 }
 
-fn player_ui(
+fn export_dialog(
     ctx: &egui::Context,
     app_state: &mut AppState,
-    next_view: &mut ResMut<NextState<AppView>>,
+    config: &mut AppConfig,
     api_events: &mut EventWriter<ApiRequest>,
-    stop_audio_events: &mut EventWriter<StopAudioEvent>,
-    pause_audio_events: &mut EventWriter<PauseAudioEvent>,
-    resume_audio_events: &mut EventWriter<ResumeAudioEvent>,
+    file_picker_events: &mut EventWriter<FilePickerRequest>,
 ) {
-    let session = match &app_state.current_session {
-        Some(s) => s.clone(),
-        None => {
-            egui::CentralPanel::default()
-                .frame(egui::Frame::none().fill(colors::BG_DARK))
-                .show(ctx, |ui| {
-                    ui.vertical_centered(|ui| {
-                        ui.add_space(100.0);
-                        ui.label(egui::RichText::new("没有活动会话").size(18.0).color(colors::TEXT_MUTED));
-                        ui.add_space(24.0);
-                        if styled_button(ui, "← 返回列表", colors::ACCENT).clicked() {
-                            next_view.set(AppView::NovelList);
+    let Some(novel) = app_state.export_dialog.novel.clone() else {
+        return;
+    };
+
+    egui::Window::new("🎧 导出有声书")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .frame(dialog_frame())
+        .min_width(420.0)
+        .show(ctx, |ui| {
+            ui.add_space(8.0);
+            ui.label(
+                egui::RichText::new(&novel.title)
+                    .size(16.0)
+                    .strong()
+                    .color(colors::TEXT_PRIMARY),
+            );
+
+            ui.add_space(12.0);
+
+            // 格式
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("格式")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(24.0);
+                egui::ComboBox::from_id_salt("export_format")
+                    .selected_text(config.export.format.label())
+                    .width(240.0)
+                    .show_ui(ui, |ui| {
+                        for format in ExportFormat::ALL {
+                            ui.selectable_value(&mut config.export.format, format, format.label());
                         }
                     });
-                });
-            return;
-        }
-    };
+            });
 
-    let total = app_state.segment_pagination.total_segments;
-    let current = app_state.current_segment_index;
+            ui.add_space(12.0);
 
-    // 顶部栏 - 简洁设计
-    egui::TopBottomPanel::top("player_top")
-        .exact_height(56.0)
-        .frame(egui::Frame::none().fill(colors::BG_PANEL).inner_margin(egui::Margin::symmetric(20.0, 10.0)))
-        .show(ctx, |ui| {
-            ui.horizontal_centered(|ui| {
-                // 返回按钮
-                if ui.add(egui::Button::new(egui::RichText::new("←").size(18.0).color(colors::TEXT_SECONDARY))
-                    .fill(colors::BG_CARD).rounding(6.0).min_size(egui::vec2(36.0, 36.0))).clicked() {
-                    stop_audio_events.send(StopAudioEvent);
-                    api_events.send(ApiRequest::CloseSession(session.session_id.clone()));
-                    next_view.set(AppView::NovelList);
-                }
+            // 段落间静音
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("段间静音")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(24.0);
+                ui.add(
+                    egui::DragValue::new(&mut config.export.silence_ms)
+                        .range(0..=5000)
+                        .speed(50)
+                        .suffix(" 毫秒"),
+                );
+            });
 
-                ui.add_space(16.0);
+            ui.add_space(12.0);
 
-                // 小说标题
-                if let Some(novel) = &app_state.selected_novel {
-                    ui.label(egui::RichText::new(&novel.title).size(18.0).strong().color(colors::TEXT_PRIMARY));
+            // 字幕
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("字幕")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(24.0);
+                for format in SubtitleFormat::ALL {
+                    let mut checked = config.export.subtitles.contains(&format);
+                    if ui.checkbox(&mut checked, format.label()).changed() {
+                        if checked {
+                            config.export.subtitles.push(format);
+                        } else {
+                            config.export.subtitles.retain(|f| *f != format);
+                        }
+                    }
                 }
+            })
+            .response
+            .on_hover_text("按音频时间轴写出字幕文件，可导入视频编辑器或音乐播放器");
 
-                ui.add_space(16.0);
+            ui.add_space(12.0);
 
-                // 音色选择
-                ui.label(egui::RichText::new("音色:").size(13.0).color(colors::TEXT_MUTED));
-                let current_voice_name = app_state.voices.iter()
-                    .find(|v| v.id == session.voice_id)
-                    .map(|v| v.name.as_str())
-                    .unwrap_or("未知");
-                egui::ComboBox::from_id_salt("voice_selector")
-                    .selected_text(current_voice_name)
-                    .width(120.0)
-                    .show_ui(ui, |ui| {
-                        for voice in &app_state.voices {
-                            let is_selected = voice.id == session.voice_id;
-                            if ui.selectable_label(is_selected, &voice.name).clicked() && !is_selected {
-                                api_events.send(ApiRequest::ChangeVoice {
-                                    session_id: session.session_id.clone(),
-                                    voice_id: voice.id,
-                                });
-                            }
+            // 输出目录
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("目录")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(24.0);
+
+                egui::Frame::none()
+                    .fill(colors::BG_CARD)
+                    .rounding(6.0)
+                    .inner_margin(egui::Margin::symmetric(12.0, 8.0))
+                    .show(ui, |ui| {
+                        if let Some(path) = &app_state.export_dialog.output_dir {
+                            ui.label(
+                                egui::RichText::new(path.display().to_string()).color(colors::TEXT_PRIMARY),
+                            );
+                        } else {
+                            ui.label(egui::RichText::new("未选择目录").color(colors::TEXT_MUTED));
                         }
                     });
 
-                // 右侧状态 - 使用剩余空间
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    let ws_indicator = match app_state.ws_state {
-                        crate::state::WsConnectionState::Connected => ("🟢", "已连接"),
-                        crate::state::WsConnectionState::Connecting => ("🟡", "连接中"),
-                        crate::state::WsConnectionState::Reconnecting => ("🟡", "重连中"),
-                        crate::state::WsConnectionState::Disconnected => ("🔴", "未连接"),
-                    };
-                    ui.label(egui::RichText::new(ws_indicator.0).size(10.0)).on_hover_text(ws_indicator.1);
-                    ui.add_space(8.0);
-                    
-                    let (state_text, state_color) = match app_state.playback_state {
-                        PlaybackState::Stopped => ("已停止", colors::TEXT_MUTED),
-                        PlaybackState::Playing => ("播放中", colors::SUCCESS),
-                        PlaybackState::Paused => ("已暂停", colors::WARNING),
-                        PlaybackState::Loading => ("加载中", colors::ACCENT),
-                    };
-                    ui.label(egui::RichText::new(state_text).size(13.0).color(state_color));
-                });
+                ui.add_space(8.0);
+
+                let picking = app_state.export_dialog.picking_dir;
+                if ui
+                    .add_enabled(
+                        !picking,
+                        egui::Button::new("选择目录...")
+                            .fill(colors::BG_CARD)
+                            .rounding(6.0),
+                    )
+                    .clicked()
+                {
+                    app_state.export_dialog.picking_dir = true;
+                    file_picker_events.send(FilePickerRequest {
+                        picker_type: FilePickerType::ExportDir,
+                    });
+                }
             });
-        });
 
-    // 底部控制栏
-    egui::TopBottomPanel::bottom("player_controls")
-        .exact_height(100.0)
-        .frame(egui::Frame::none().fill(colors::BG_PANEL).inner_margin(egui::Margin::symmetric(24.0, 8.0)))
-        .show(ctx, |ui| {
-            let progress = if total > 0 { current as f32 / total as f32 } else { 0.0 };
+            ui.add_space(8.0);
+            ui.label(
+                egui::RichText::new("未生成的段落会先提交合成，导出在后台进行，可在小说卡片上查看进度。")
+                    .size(12.0)
+                    .color(colors::TEXT_MUTED),
+            );
 
-            // 控制按钮行 - 放在最上面
-            ui.horizontal(|ui| {
-                // 左侧进度文字
-                ui.label(egui::RichText::new(format!("{}/{}", current + 1, total)).size(12.0).color(colors::TEXT_SECONDARY));
-                
-                ui.add_space(20.0);
-                
-                // 居中的控制按钮
-                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
-                    ui.spacing_mut().item_spacing.x = 16.0;
+            ui.add_space(24.0);
 
-                    // 上一段
-                    let prev_enabled = current > 0;
-                    if ui.add_enabled(prev_enabled,
-                        egui::Button::new(egui::RichText::new("⏮").size(18.0)
+            // 按钮
+            ui.horizontal(|ui| {
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .add(
+                            egui::Button::new("取消")
+                                .fill(colors::BG_CARD)
+                                .rounding(8.0),
+                        )
+                        .clicked()
+                    {
+                        app_state.export_dialog.close();
+                    }
+
+                    ui.add_space(12.0);
+
+                    let needs_subtitles = config.export.format == ExportFormat::SubtitlesOnly;
+                    let can_export = app_state.export_dialog.output_dir.is_some()
+                        && app_state.export_dialog.voice_id.is_some()
+                        && !app_state.export_dialog.picking_dir
+                        && !(needs_subtitles && config.export.subtitles.is_empty());
+
+                    if ui
+                        .add_enabled(
+                            can_export,
+                            egui::Button::new(
+                                egui::RichText::new("开始导出").color(egui::Color32::WHITE),
+                            )
+                            .fill(if can_export {
+                                colors::ACCENT
+                            } else {
+                                colors::BG_CARD
+                            })
+                            .rounding(8.0),
+                        )
+                        .clicked()
+                    {
+                        if let (Some(output_dir), Some(voice_id)) = (
+                            app_state.export_dialog.output_dir.clone(),
+                            app_state.export_dialog.voice_id,
+                        ) {
+                            if let Err(e) = config.save() {
+                                app_state.set_error(format!("保存配置失败: {}", e));
+                            }
+                            api_events.send(ApiRequest::ExportAudiobook {
+                                novel_id: novel.id,
+                                voice_id,
+                                settings: config.export.clone(),
+                                output_dir,
+                            });
+                            app_state.export_dialog.close();
+                        }
+                    }
+                });
+            });
+        });
+}
+
+fn settings_dialog(
+    ctx: &egui::Context,
+    app_state: &mut AppState,
+    config: &mut AppConfig,
+    audio_cache: &AudioCache,
+    api_events: &mut EventWriter<ApiRequest>,
+    switch_profile_events: &mut EventWriter<SwitchProfileEvent>,
+) {
+    if !app_state.settings_dialog.show {
+        return;
+    }
+
+    egui::Window::new("⚙ 设置")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .frame(dialog_frame())
+        .min_width(480.0)
+        .show(ctx, |ui| {
+            ui.add_space(8.0);
+            ui.label(
+                egui::RichText::new("服务器")
+                    .size(16.0)
+                    .strong()
+                    .color(colors::TEXT_PRIMARY),
+            );
+            ui.add_space(8.0);
+
+            // 先提取需要的信息，避免在闭包中同时读写 config
+            let active_name = config.active_profile.clone();
+            let profiles_display: Vec<_> = config.profiles.iter().map(|p| {
+                (p.name.clone(), p.server_url.clone(), p.transient)
+            }).collect();
+
+            let mut profile_to_switch: Option<String> = None;
+            let mut profile_to_delete: Option<String> = None;
+
+            for (name, server_url, transient) in &profiles_display {
+                let is_active = *name == active_name;
+                egui::Frame::none()
+                    .fill(if is_active { colors::BG_HIGHLIGHT } else { colors::BG_CARD })
+                    .rounding(8.0)
+                    .inner_margin(10.0)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new(if is_active { "🟢" } else { "⚪" }).size(12.0),
+                            );
+                            ui.vertical(|ui| {
+                                ui.label(
+                                    egui::RichText::new(name)
+                                        .size(14.0)
+                                        .color(colors::TEXT_PRIMARY),
+                                );
+                                ui.label(
+                                    egui::RichText::new(server_url)
+                                        .size(11.0)
+                                        .color(colors::TEXT_MUTED),
+                                );
+                            });
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                let can_delete = !is_active && !transient;
+                                if can_delete && ui.add(
+                                    egui::Button::new("🗑")
+                                        .fill(colors::BG_CARD)
+                                        .rounding(6.0)
+                                ).on_hover_text("删除配置").clicked() {
+                                    profile_to_delete = Some(name.clone());
+                                }
+                                if !is_active && styled_button(ui, "切换", colors::ACCENT).clicked() {
+                                    profile_to_switch = Some(name.clone());
+                                }
+                            });
+                        });
+                    });
+                ui.add_space(6.0);
+            }
+
+            // 在循环外处理操作
+            if let Some(name) = profile_to_delete {
+                if config.remove_profile(&name) {
+                    if let Err(e) = config.save() {
+                        app_state.set_error(format!("保存配置失败: {}", e));
+                    }
+                }
+            }
+            if let Some(name) = profile_to_switch {
+                switch_profile_events.send(SwitchProfileEvent { profile_name: name, reconnect: false });
+            }
+
+            // 当前服务器的登录状态
+            ui.add_space(4.0);
+            ui.horizontal(|ui| {
+                match config.active().auth.as_ref().map(|t| t.username.clone()) {
+                    Some(username) => {
+                        ui.label(
+                            egui::RichText::new(format!("👤 已登录: {}", username))
+                                .size(13.0)
+                                .color(colors::SUCCESS),
+                        );
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.add(
+                                egui::Button::new("退出登录")
+                                    .fill(colors::BG_CARD)
+                                    .rounding(6.0)
+                            ).clicked() {
+                                api_events.send(ApiRequest::Logout);
+                            }
+                        });
+                    }
+                    None => {
+                        ui.label(
+                            egui::RichText::new("👤 未登录")
+                                .size(13.0)
+                                .color(colors::TEXT_MUTED),
+                        );
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if styled_button(ui, "登录", colors::ACCENT).clicked() {
+                                app_state.login_dialog.open(None);
+                            }
+                        });
+                    }
+                }
+            });
+
+            ui.add_space(12.0);
+            ui.add(egui::Separator::default().spacing(1.0));
+            ui.add_space(12.0);
+
+            // 音频传输
+            ui.label(
+                egui::RichText::new("音频")
+                    .size(16.0)
+                    .strong()
+                    .color(colors::TEXT_PRIMARY),
+            );
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("传输格式")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(12.0);
+                let mut bandwidth = config.bandwidth;
+                egui::ComboBox::from_id_salt("bandwidth_preference")
+                    .selected_text(bandwidth.label())
+                    .width(200.0)
+                    .show_ui(ui, |ui| {
+                        for preference in BandwidthPreference::ALL {
+                            ui.selectable_value(&mut bandwidth, preference, preference.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("向服务器声明的音频格式优先级，服务器不支持时使用其默认格式");
+                if bandwidth != config.bandwidth {
+                    config.bandwidth = bandwidth;
+                    if let Err(e) = config.save() {
+                        app_state.set_error(format!("保存配置失败: {}", e));
+                    }
+                }
+            });
+
+            // 段落衔接
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("段落衔接")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(12.0);
+                let mut transition = config.segment_transition;
+                let duration = transition.duration_ms();
+                egui::ComboBox::from_id_salt("segment_transition")
+                    .selected_text(transition.label())
+                    .width(120.0)
+                    .show_ui(ui, |ui| {
+                        for option in [
+                            SegmentTransition::Gapless,
+                            SegmentTransition::Pause { ms: duration.unwrap_or(500) },
+                            SegmentTransition::Crossfade { ms: duration.unwrap_or(300) },
+                        ] {
+                            let selected = std::mem::discriminant(&transition) == std::mem::discriminant(&option);
+                            if ui.selectable_label(selected, option.label()).clicked() {
+                                transition = option;
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text("下一段已预取时提前排队，在上一段结束时立即接上");
+                if let SegmentTransition::Pause { ms } | SegmentTransition::Crossfade { ms } = &mut transition {
+                    ui.add(egui::DragValue::new(ms).range(50..=3000).speed(10.0).suffix(" ms"));
+                }
+                if transition != config.segment_transition {
+                    config.segment_transition = transition;
+                    if let Err(e) = config.save() {
+                        app_state.set_error(format!("保存配置失败: {}", e));
+                    }
+                }
+            });
+
+            // 音频缓存
+            ui.add_space(8.0);
+            let usage = audio_cache.usage();
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("音频缓存")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(12.0);
+                if config.audio_cache.enabled {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "已用 {} / {}",
+                            format_megabytes(usage.total_bytes),
+                            format_megabytes(usage.max_bytes)
+                        ))
+                        .size(13.0)
+                        .color(colors::TEXT_PRIMARY),
+                    );
+                } else {
+                    ui.label(
+                        egui::RichText::new("已禁用")
+                            .size(13.0)
+                            .color(colors::TEXT_MUTED),
+                    );
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if !usage.novels.is_empty()
+                        && ui.add(egui::Button::new("全部清除").fill(colors::BG_CARD).rounding(6.0)).clicked()
+                    {
+                        audio_cache.clear();
+                    }
+                });
+            });
+            if !usage.novels.is_empty() {
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical()
+                    .id_salt("audio_cache_novels")
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for novel in &usage.novels {
+                            let title = app_state
+                                .novels
+                                .iter()
+                                .find(|n| n.id == novel.novel_id)
+                                .map(|n| n.title.clone())
+                                .unwrap_or_else(|| novel.novel_id.to_string()[..8].to_string());
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "📖 {}  ·  {} 段  ·  {}",
+                                        title,
+                                        novel.segments,
+                                        format_megabytes(novel.bytes)
+                                    ))
+                                    .size(13.0)
+                                    .color(colors::TEXT_SECONDARY),
+                                );
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    if ui.add(egui::Button::new("清除").fill(colors::BG_CARD).rounding(6.0)).clicked() {
+                                        audio_cache.clear_novel(novel.novel_id);
+                                    }
+                                });
+                            });
+                        }
+                    });
+            }
+
+            ui.add_space(12.0);
+            ui.add(egui::Separator::default().spacing(1.0));
+            ui.add_space(12.0);
+
+            // 添加新配置
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("名称")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(24.0);
+                ui.add_sized(
+                    [320.0, 28.0],
+                    egui::TextEdit::singleline(&mut app_state.settings_dialog.new_profile_name)
+                        .hint_text("例如: 家里的服务器"),
+                );
+            });
+            if AppConfig::is_reserved_profile_name(app_state.settings_dialog.new_profile_name.trim()) {
+                ui.label(
+                    egui::RichText::new("该名称为命令行覆盖地址保留，请换一个名称")
+                        .size(12.0)
+                        .color(colors::WARNING),
+                );
+            }
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("地址")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(24.0);
+                ui.add_sized(
+                    [320.0, 28.0],
+                    egui::TextEdit::singleline(&mut app_state.settings_dialog.new_profile_url)
+                        .hint_text("http://host:5060"),
+                );
+            });
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("CA")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(32.0);
+                ui.add_sized(
+                    [320.0, 28.0],
+                    egui::TextEdit::singleline(&mut app_state.settings_dialog.new_profile_ca)
+                        .hint_text("可选: 自定义 CA 证书 (PEM) 路径"),
+                );
+            });
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("指纹")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(24.0);
+                ui.add_sized(
+                    [320.0, 28.0],
+                    egui::TextEdit::singleline(&mut app_state.settings_dialog.new_profile_fingerprint)
+                        .hint_text("可选: 自签名证书 SHA-256 指纹"),
+                );
+            });
+
+            ui.add_space(24.0);
+
+            // 按钮
+            ui.horizontal(|ui| {
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .add(
+                            egui::Button::new("关闭")
+                                .fill(colors::BG_CARD)
+                                .rounding(8.0),
+                        )
+                        .clicked()
+                    {
+                        app_state.settings_dialog.reset();
+                    }
+
+                    ui.add_space(12.0);
+
+                    let name = app_state.settings_dialog.new_profile_name.trim().to_string();
+                    let url = app_state.settings_dialog.new_profile_url.trim().to_string();
+                    let reserved = AppConfig::is_reserved_profile_name(&name);
+                    let can_add = !name.is_empty() && !url.is_empty() && !reserved;
+
+                    if ui
+                        .add_enabled(
+                            can_add,
+                            egui::Button::new(
+                                egui::RichText::new("添加配置").color(egui::Color32::WHITE),
+                            )
+                            .fill(if can_add {
+                                colors::ACCENT
+                            } else {
+                                colors::BG_CARD
+                            })
+                            .rounding(8.0),
+                        )
+                        .clicked()
+                    {
+                        let ca = app_state.settings_dialog.new_profile_ca.trim();
+                        let fingerprint = app_state.settings_dialog.new_profile_fingerprint.trim();
+                        let tls_settings = TlsSettings {
+                            ca_bundle: (!ca.is_empty()).then(|| PathBuf::from(ca)),
+                            cert_sha256: (!fingerprint.is_empty()).then(|| fingerprint.to_string()),
+                        };
+                        // 保存前校验证书和指纹，避免连接时才发现配置错误
+                        match tls::client_config(&tls_settings) {
+                            Ok(_) => {
+                                // 同名 profile 只合并地址和 TLS，保留登录 token 和服务器类型
+                                let reconnect = config.edit_profile(&name, &url, tls_settings);
+                                if let Err(e) = config.save() {
+                                    app_state.set_error(format!("保存配置失败: {}", e));
+                                }
+                                if reconnect {
+                                    switch_profile_events.send(SwitchProfileEvent { profile_name: name, reconnect: true });
+                                }
+                                app_state.settings_dialog.new_profile_name.clear();
+                                app_state.settings_dialog.new_profile_url.clear();
+                                app_state.settings_dialog.new_profile_ca.clear();
+                                app_state.settings_dialog.new_profile_fingerprint.clear();
+                            }
+                            Err(e) => app_state.set_error(format!("TLS 配置无效: {:#}", e)),
+                        }
+                    }
+                });
+            });
+        });
+}
+
+fn login_dialog(
+    ctx: &egui::Context,
+    app_state: &mut AppState,
+    config: &AppConfig,
+    api_events: &mut EventWriter<ApiRequest>,
+) {
+    if !app_state.login_dialog.show {
+        return;
+    }
+
+    // 预填上次登录的用户名
+    if app_state.login_dialog.username.is_empty() {
+        if let Some(token) = &config.active().auth {
+            app_state.login_dialog.username = token.username.clone();
+        }
+    }
+
+    egui::Window::new("🔑 登录")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .frame(dialog_frame())
+        .min_width(360.0)
+        .show(ctx, |ui| {
+            ui.add_space(8.0);
+            ui.label(
+                egui::RichText::new(format!("服务器: {}", config.active().server_url))
+                    .size(12.0)
+                    .color(colors::TEXT_MUTED),
+            );
+            if let Some(message) = &app_state.login_dialog.message {
+                ui.add_space(8.0);
+                ui.label(egui::RichText::new(message).size(13.0).color(colors::WARNING));
+            }
+            ui.add_space(12.0);
+
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("用户名")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(12.0);
+                ui.add_sized(
+                    [240.0, 28.0],
+                    egui::TextEdit::singleline(&mut app_state.login_dialog.username),
+                );
+            });
+            ui.add_space(8.0);
+            let password_response = ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new("密码")
+                        .size(14.0)
+                        .color(colors::TEXT_SECONDARY),
+                );
+                ui.add_space(26.0);
+                ui.add_sized(
+                    [240.0, 28.0],
+                    egui::TextEdit::singleline(&mut app_state.login_dialog.password).password(true),
+                )
+            }).inner;
+
+            ui.add_space(20.0);
+
+            let pending = app_state.login_dialog.pending;
+            let can_login = !pending
+                && !app_state.login_dialog.username.trim().is_empty()
+                && !app_state.login_dialog.password.is_empty();
+            let enter_pressed = password_response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+            ui.horizontal(|ui| {
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .add(
+                            egui::Button::new("取消")
+                                .fill(colors::BG_CARD)
+                                .rounding(8.0),
+                        )
+                        .clicked()
+                    {
+                        app_state.login_dialog.reset();
+                    }
+
+                    ui.add_space(12.0);
+
+                    let clicked = ui
+                        .add_enabled(
+                            can_login,
+                            egui::Button::new(
+                                egui::RichText::new("登录").color(egui::Color32::WHITE),
+                            )
+                            .fill(if can_login {
+                                colors::ACCENT
+                            } else {
+                                colors::BG_CARD
+                            })
+                            .rounding(8.0),
+                        )
+                        .clicked();
+                    if pending {
+                        ui.spinner();
+                    }
+
+                    if clicked || (enter_pressed && can_login) {
+                        app_state.login_dialog.message = None;
+                        api_events.send(ApiRequest::Login {
+                            username: app_state.login_dialog.username.trim().to_string(),
+                            password: std::mem::take(&mut app_state.login_dialog.password),
+                        });
+                    }
+                });
+            });
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn player_ui(
+    ctx: &egui::Context,
+    app_state: &mut AppState,
+    next_view: &mut ResMut<NextState<AppView>>,
+    api_events: &mut EventWriter<ApiRequest>,
+    stop_audio_events: &mut EventWriter<StopAudioEvent>,
+    pause_audio_events: &mut EventWriter<PauseAudioEvent>,
+    resume_audio_events: &mut EventWriter<ResumeAudioEvent>,
+    seek_audio_events: &mut EventWriter<SeekAudioEvent>,
+    file_picker_events: &mut EventWriter<FilePickerRequest>,
+    bookmarks: &mut Bookmarks,
+    config: &mut AppConfig,
+) {
+    let session = match &app_state.current_session {
+        Some(s) => s.clone(),
+        None => {
+            egui::CentralPanel::default()
+                .frame(egui::Frame::none().fill(colors::BG_DARK))
+                .show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(100.0);
+                        ui.label(egui::RichText::new("没有活动会话").size(18.0).color(colors::TEXT_MUTED));
+                        ui.add_space(24.0);
+                        if styled_button(ui, "← 返回列表", colors::ACCENT).clicked() {
+                            next_view.set(AppView::NovelList);
+                        }
+                    });
+                });
+            return;
+        }
+    };
+
+    let total = app_state.segment_pagination.total_segments;
+    let current = app_state.current_segment_index;
+
+    // 顶部栏 - 简洁设计
+    egui::TopBottomPanel::top("player_top")
+        .exact_height(56.0)
+        .frame(egui::Frame::none().fill(colors::BG_PANEL).inner_margin(egui::Margin::symmetric(20.0, 10.0)))
+        .show(ctx, |ui| {
+            ui.horizontal_centered(|ui| {
+                // 返回按钮
+                if ui.add(egui::Button::new(egui::RichText::new("←").size(18.0).color(colors::TEXT_SECONDARY))
+                    .fill(colors::BG_CARD).rounding(6.0).min_size(egui::vec2(36.0, 36.0))).clicked() {
+                    stop_audio_events.send(StopAudioEvent);
+                    api_events.send(ApiRequest::CloseSession(session.session_id.clone()));
+                    next_view.set(AppView::NovelList);
+                }
+
+                ui.add_space(16.0);
+
+                // 小说标题
+                if let Some(novel) = &app_state.selected_novel {
+                    ui.label(egui::RichText::new(&novel.title).size(18.0).strong().color(colors::TEXT_PRIMARY));
+                }
+
+                ui.add_space(16.0);
+
+                // 音色选择
+                ui.label(egui::RichText::new("音色:").size(13.0).color(colors::TEXT_MUTED));
+                let current_voice_name = app_state.voices.iter()
+                    .find(|v| v.id == session.voice_id)
+                    .map(|v| v.name.as_str())
+                    .unwrap_or("未知");
+                egui::ComboBox::from_id_salt("voice_selector")
+                    .selected_text(current_voice_name)
+                    .width(120.0)
+                    .show_ui(ui, |ui| {
+                        for voice in &app_state.voices {
+                            let is_selected = voice.id == session.voice_id;
+                            if ui.selectable_label(is_selected, &voice.name).clicked() && !is_selected {
+                                api_events.send(ApiRequest::ChangeVoice {
+                                    session_id: session.session_id.clone(),
+                                    voice_id: voice.id,
+                                });
+                            }
+                        }
+                    });
+
+                ui.add_space(16.0);
+
+                // 书签侧栏开关
+                let bookmark_count = bookmarks.for_novel(session.novel_id).len();
+                let panel_open = app_state.bookmark_panel.show;
+                if ui.add(egui::Button::new(egui::RichText::new(format!("🔖 书签 {}", bookmark_count)).size(13.0))
+                    .fill(if panel_open { colors::BG_HIGHLIGHT } else { colors::BG_CARD }).rounding(6.0))
+                    .on_hover_text(if panel_open { "隐藏书签" } else { "显示书签" })
+                    .clicked() {
+                    app_state.bookmark_panel.show = !panel_open;
+                }
+
+                // 右侧状态 - 使用剩余空间
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let ws_indicator = match app_state.ws_state {
+                        crate::state::WsConnectionState::Connected => ("🟢", "已连接"),
+                        crate::state::WsConnectionState::Connecting => ("🟡", "连接中"),
+                        crate::state::WsConnectionState::Reconnecting => ("🟡", "重连中"),
+                        crate::state::WsConnectionState::Disconnected => ("🔴", "未连接"),
+                    };
+                    ui.label(egui::RichText::new(ws_indicator.0).size(10.0)).on_hover_text(ws_indicator.1);
+                    ui.add_space(8.0);
+
+                    if let Some(retry_status) = &app_state.retry_status {
+                        ui.label(egui::RichText::new("⟳ 重试中").size(13.0).color(colors::WARNING))
+                            .on_hover_text(retry_status);
+                        ui.add_space(8.0);
+                    }
+                    
+                    let (state_text, state_color) = match app_state.playback_state {
+                        PlaybackState::Stopped => ("已停止", colors::TEXT_MUTED),
+                        PlaybackState::Playing => ("播放中", colors::SUCCESS),
+                        PlaybackState::Paused => ("已暂停", colors::WARNING),
+                        PlaybackState::Loading => ("加载中", colors::ACCENT),
+                    };
+                    ui.label(egui::RichText::new(state_text).size(13.0).color(state_color));
+
+                    if let Some(format) = app_state.current_audio_format {
+                        ui.add_space(8.0);
+                        ui.label(egui::RichText::new(format.label()).size(11.0).color(colors::TEXT_MUTED))
+                            .on_hover_text(format!("音频编码: {}", format.codec()));
+                    }
+                });
+            });
+        });
+
+    // 底部控制栏
+    egui::TopBottomPanel::bottom("player_controls")
+        .exact_height(130.0)
+        .frame(egui::Frame::none().fill(colors::BG_PANEL).inner_margin(egui::Margin::symmetric(24.0, 8.0)))
+        .show(ctx, |ui| {
+            let progress = if total > 0 { current as f32 / total as f32 } else { 0.0 };
+
+            // 控制按钮行 - 放在最上面
+            ui.horizontal(|ui| {
+                // 左侧进度文字
+                ui.label(egui::RichText::new(format!("{}/{}", current + 1, total)).size(12.0).color(colors::TEXT_SECONDARY));
+                
+                ui.add_space(20.0);
+                
+                // 居中的控制按钮
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.spacing_mut().item_spacing.x = 16.0;
+
+                    // 上一段
+                    let prev_enabled = current > 0;
+                    if ui.add_enabled(prev_enabled,
+                        egui::Button::new(egui::RichText::new("⏮").size(18.0)
                             .color(if prev_enabled { colors::TEXT_PRIMARY } else { colors::TEXT_MUTED }))
                         .fill(colors::BG_CARD).rounding(8.0).min_size(egui::vec2(50.0, 44.0))
                     ).on_hover_text("上一段").clicked() {
@@ -1006,6 +2070,7 @@ fn player_ui(
                         api_events.send(ApiRequest::Seek { 
                             session_id: session.session_id.clone(), 
                             segment_index: current.saturating_sub(1) as u32,
+                            sync_only: false,
                         });
                     }
 
@@ -1095,17 +2160,31 @@ fn player_ui(
                         api_events.send(ApiRequest::Seek { 
                             session_id: session.session_id.clone(), 
                             segment_index: (current + 1) as u32,
+                            sync_only: false,
                         });
                     }
                 });
                 
-                // 右侧百分比
+                // 右侧百分比、剩余时长、倍速、睡眠定时和音量
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     ui.label(egui::RichText::new(format!("{:.0}%", progress * 100.0)).size(12.0).color(colors::TEXT_MUTED));
+                    if let Some(estimate) = app_state.listening_estimate() {
+                        let text = format!("已听 {} · 剩余约 {}", format_duration(estimate.elapsed), format_duration(estimate.remaining));
+                        ui.label(egui::RichText::new(text).size(12.0).color(colors::TEXT_MUTED))
+                            .on_hover_text("整本小说按当前倍速估算，已合成段落按实际时长计算");
+                    }
+                    ui.add_space(12.0);
+                    sleep_timer_menu(ui, app_state);
+                    repeat_menu(ui, app_state);
+                    speed_menu(ui, app_state);
+                    ui.add_space(12.0);
+                    volume_control(ui, app_state, config);
                 });
             });
 
-            ui.add_space(6.0);
+            ui.add_space(4.0);
+            segment_scrubber(ui, app_state, seek_audio_events);
+            ui.add_space(4.0);
 
             // 进度滑块 - 可拖动（全宽）
             let mut slider_value = current as f64;
@@ -1126,42 +2205,23 @@ fn player_ui(
                     let new_index = slider_value.round() as usize;
                     if new_index != current {
                         stop_audio_events.send(StopAudioEvent);
-                        
-                        // 检查目标位置是否在已加载范围内
-                        let loaded_start = app_state.segment_pagination.loaded_range.start;
-                        let loaded_end = app_state.segment_pagination.loaded_range.end;
-                        
-                        if new_index >= loaded_start && new_index < loaded_end {
-                            // 在已加载范围内，直接跳转并滚动
-                            api_events.send(ApiRequest::Seek { 
-                                session_id: session.session_id.clone(), 
-                                segment_index: new_index as u32,
-                            });
-                            app_state.scroll_to_segment = Some(new_index);
-                        } else {
-                            // 不在已加载范围内，需要先加载段落
-                            // 计算加载范围：目标位置前后各加载一些
-                            let load_start = new_index.saturating_sub(15);
-                            app_state.segments.clear();
-                            app_state.segment_pagination.loaded_range = 0..0;
-                            
-                            // 发送加载请求，加载完成后会设置 scroll_to_segment
-                            app_state.scroll_to_segment = Some(new_index);
-                            api_events.send(ApiRequest::LoadSegments {
-                                novel_id: session.novel_id,
-                                start: Some(load_start),
-                                limit: Some(100),
-                            });
-                            api_events.send(ApiRequest::Seek { 
-                                session_id: session.session_id.clone(), 
-                                segment_index: new_index as u32,
-                            });
-                        }
+                        seek_to_segment(app_state, api_events, &session, new_index);
                     }
                 }
             });
         });
 
+    // 书签侧栏
+    if app_state.bookmark_panel.show {
+        egui::SidePanel::right("bookmarks_panel")
+            .resizable(true)
+            .default_width(320.0)
+            .frame(egui::Frame::none().fill(colors::BG_PANEL).inner_margin(16.0))
+            .show(ctx, |ui| {
+                bookmark_panel_ui(ui, app_state, api_events, stop_audio_events, file_picker_events, bookmarks, &session);
+            });
+    }
+
     // 段落列表
     egui::CentralPanel::default()
         .frame(egui::Frame::none().fill(colors::BG_DARK).inner_margin(20.0))
@@ -1222,7 +2282,11 @@ fn player_ui(
                             Some(TaskState::Cancelled) => "–",
                             None => " ",
                         };
+                        // 已预取到内存的段落
+                        let buffered = app_state.audio_buffer
+                            .contains(&(session.novel_id, session.voice_id, segment.index as u32));
                         let state_color = match task_state {
+                            Some(TaskState::Ready) if buffered => colors::ACCENT,
                             Some(TaskState::Ready) => colors::SUCCESS,
                             Some(TaskState::Inferring) => colors::ACCENT,
                             Some(TaskState::Pending) => colors::TEXT_MUTED,
@@ -1230,6 +2294,9 @@ fn player_ui(
                             _ => colors::TEXT_MUTED,
                         };
 
+                        let bookmarked = bookmarks.contains(novel_id, segment.index as u32);
+                        let mut star_rect = egui::Rect::NOTHING;
+                        let mut star_clicked = false;
                         let response = egui::Frame::none()
                             .fill(bg)
                             .stroke(if is_current { egui::Stroke::new(2.0, colors::ACCENT) } else { egui::Stroke::NONE })
@@ -1237,6 +2304,14 @@ fn player_ui(
                             .inner_margin(14.0)
                             .show(ui, |ui| {
                                 ui.horizontal(|ui| {
+                                    // 书签
+                                    let star = icon_button(
+                                        ui,
+                                        if bookmarked { "★" } else { "☆" },
+                                        if bookmarked { "取消书签" } else { "添加书签" },
+                                    );
+                                    star_rect = star.rect;
+                                    star_clicked = star.clicked();
                                     // 任务状态指示器
                                     ui.label(egui::RichText::new(state_indicator).size(12.0).color(state_color));
                                     ui.add_space(6.0);
@@ -1253,10 +2328,16 @@ fn player_ui(
                                 });
                             }).response;
 
-                        if response.interact(egui::Sense::click()).clicked() {
+                        // 整行可点击跳转，点在书签按钮上时只切换书签
+                        let row_clicked = response.interact(egui::Sense::click()).clicked();
+                        let on_star = ui.ctx().pointer_interact_pos().is_some_and(|pos| star_rect.contains(pos));
+                        if star_clicked || (row_clicked && on_star) {
+                            bookmarks.toggle(novel_id, segment.index as u32, &segment.content);
+                        } else if row_clicked {
                             api_events.send(ApiRequest::Seek { 
                                 session_id: session.session_id.clone(), 
                                 segment_index: segment.index as u32,
+                                sync_only: false,
                             });
                         }
 
@@ -1308,3 +2389,445 @@ fn player_ui(
             }
         });
 }
+
+/// 睡眠定时菜单：按钮上显示剩余时间或段数
+fn sleep_timer_menu(ui: &mut egui::Ui, app_state: &mut AppState) {
+    let current = app_state.current_segment_index as u32;
+    let (label, color) = match app_state.sleep_timer {
+        None => ("🌙 定时".to_string(), colors::TEXT_SECONDARY),
+        Some(SleepTimer::Time { remaining }) => {
+            let secs = remaining.as_secs();
+            (format!("🌙 {:02}:{:02}", secs / 60, secs % 60), colors::ACCENT)
+        }
+        Some(SleepTimer::AfterSegment(last)) => {
+            (format!("🌙 剩 {} 段", (last + 1).saturating_sub(current)), colors::ACCENT)
+        }
+        Some(SleepTimer::EndOfChapter) => ("🌙 本章结束".to_string(), colors::ACCENT),
+    };
+
+    ui.menu_button(egui::RichText::new(label).size(12.0).color(color), |ui| {
+        ui.label(egui::RichText::new("睡眠定时").size(12.0).color(colors::TEXT_MUTED));
+        for minutes in [15, 30, 45, 60, 90] {
+            if ui.button(format!("{} 分钟后", minutes)).clicked() {
+                app_state.sleep_timer = Some(SleepTimer::Time {
+                    remaining: std::time::Duration::from_secs(minutes * 60),
+                });
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        for count in [1, 3, 5, 10] {
+            let text = if count == 1 { "本段结束".to_string() } else { format!("再播 {} 段", count) };
+            if ui.button(text).clicked() {
+                app_state.sleep_timer = Some(SleepTimer::AfterSegment(current + count - 1));
+                ui.close_menu();
+            }
+        }
+        if ui.button("本章结束").clicked() {
+            app_state.sleep_timer = Some(SleepTimer::EndOfChapter);
+            ui.close_menu();
+        }
+        if app_state.sleep_timer.is_some() {
+            ui.separator();
+            if ui.button("关闭定时").clicked() {
+                app_state.sleep_timer = None;
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+/// 循环菜单：每段重复、段落范围循环和段内 A-B 循环，按钮上显示当前的循环状态
+fn repeat_menu(ui: &mut egui::Ui, app_state: &mut AppState) {
+    let current = app_state.current_segment_index as u32;
+    let position = app_state.playback_position;
+    let label = match app_state.repeat {
+        None => "🔁 循环".to_string(),
+        Some(RepeatMode::Segment { times: Some(times) }) => {
+            let pass = position.map_or(0, |p| p.loop_passes) + 1;
+            format!("🔂 第 {}/{} 遍", pass.min(times), times)
+        }
+        Some(RepeatMode::Segment { times: None }) => "🔂 单段循环".to_string(),
+        Some(RepeatMode::Range { start, end }) => format!("🔁 第 {}-{} 段", start + 1, end + 1),
+        Some(RepeatMode::AB { a, b: None, .. }) => format!("🔁 A {} - ?", format_duration(a)),
+        Some(RepeatMode::AB { a, b: Some(b), .. }) => format!("🔁 A-B {}-{}", format_duration(a), format_duration(b)),
+    };
+    let color = if app_state.repeat.is_some() { colors::ACCENT } else { colors::TEXT_SECONDARY };
+
+    ui.menu_button(egui::RichText::new(label).size(12.0).color(color), |ui| {
+        ui.label(egui::RichText::new("每段重复").size(12.0).color(colors::TEXT_MUTED));
+        for times in [2, 3, 5, 10] {
+            let selected = app_state.repeat == Some(RepeatMode::Segment { times: Some(times) });
+            if ui.selectable_label(selected, format!("每段 {} 遍", times)).clicked() {
+                app_state.repeat = Some(RepeatMode::Segment { times: Some(times) });
+                ui.close_menu();
+            }
+        }
+        let selected = app_state.repeat == Some(RepeatMode::Segment { times: None });
+        if ui.selectable_label(selected, "单段循环").clicked() {
+            app_state.repeat = Some(RepeatMode::Segment { times: None });
+            ui.close_menu();
+        }
+
+        ui.separator();
+        ui.label(egui::RichText::new("段落范围").size(12.0).color(colors::TEXT_MUTED));
+        let range = match app_state.repeat {
+            Some(RepeatMode::Range { start, end }) => Some((start, end)),
+            _ => None,
+        };
+        if ui.button(format!("从第 {} 段开始", current + 1)).clicked() {
+            let end = range.map_or(current, |(_, end)| end.max(current));
+            app_state.repeat = Some(RepeatMode::Range { start: current, end });
+            ui.close_menu();
+        }
+        if ui.button(format!("到第 {} 段结束", current + 1)).clicked() {
+            let start = range.map_or(current, |(start, _)| start.min(current));
+            app_state.repeat = Some(RepeatMode::Range { start, end: current });
+            ui.close_menu();
+        }
+
+        ui.separator();
+        ui.label(egui::RichText::new("段内 A-B").size(12.0).color(colors::TEXT_MUTED));
+        let elapsed = position.map(|p| p.elapsed);
+        let a_point = match app_state.repeat {
+            Some(RepeatMode::AB { index, a, .. }) if index == current => Some(a),
+            _ => None,
+        };
+        let a_text = elapsed.map_or_else(|| "设置 A 点".to_string(), |t| format!("设置 A 点（{}）", format_duration(t)));
+        if ui.add_enabled(elapsed.is_some(), egui::Button::new(a_text)).clicked() {
+            if let Some(a) = elapsed {
+                app_state.repeat = Some(RepeatMode::AB { index: current, a, b: None });
+            }
+            ui.close_menu();
+        }
+        let b_text = elapsed.map_or_else(|| "设置 B 点".to_string(), |t| format!("设置 B 点（{}）", format_duration(t)));
+        let b_enabled = a_point.zip(elapsed).is_some_and(|(a, t)| t != a);
+        if ui
+            .add_enabled(b_enabled, egui::Button::new(b_text))
+            .on_disabled_hover_text("先在本段设置 A 点")
+            .clicked()
+        {
+            if let Some((a, t)) = a_point.zip(elapsed) {
+                // B 点在 A 点之前时交换
+                app_state.repeat = Some(RepeatMode::AB { index: current, a: a.min(t), b: Some(a.max(t)) });
+            }
+            ui.close_menu();
+        }
+
+        if app_state.repeat.is_some() {
+            ui.separator();
+            if ui.button("关闭循环").clicked() {
+                app_state.repeat = None;
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+/// 音量控制：静音按钮、主音量滑块和响度均衡开关，修改后写入配置文件
+fn volume_control(ui: &mut egui::Ui, app_state: &mut AppState, config: &mut AppConfig) {
+    let settings = &mut config.volume;
+    let mut changed = false;
+
+    // 从右向左布局，先添加的在最右边
+    let normalize_color = if settings.normalize { colors::ACCENT } else { colors::TEXT_MUTED };
+    if ui
+        .add(egui::Button::new(egui::RichText::new("均衡").size(12.0).color(normalize_color)).frame(false))
+        .on_hover_text(if settings.normalize { "响度均衡：已开启，各段调整到一致的听感音量" } else { "响度均衡：已关闭" })
+        .clicked()
+    {
+        settings.normalize = !settings.normalize;
+        changed = true;
+    }
+
+    ui.spacing_mut().slider_width = 90.0;
+    let slider = ui.add_enabled(
+        !settings.muted,
+        egui::Slider::new(&mut settings.volume, 0.0..=1.0).show_value(false),
+    );
+    // 拖动中只更新音量，松开后再保存
+    if slider.drag_stopped() || (slider.changed() && !slider.dragged()) {
+        changed = true;
+    }
+
+    let volume = settings.effective();
+    let icon = if volume == 0.0 {
+        "🔇"
+    } else if volume < 0.5 {
+        "🔉"
+    } else {
+        "🔊"
+    };
+    if icon_button(ui, icon, if settings.muted { "取消静音" } else { "静音" }).clicked() {
+        settings.muted = !settings.muted;
+        changed = true;
+    }
+
+    if changed {
+        if let Err(e) = config.save() {
+            app_state.set_error(format!("保存配置失败: {}", e));
+        }
+    }
+}
+
+/// 段内时间进度条：拖动跳转，两侧为快退 / 快进按钮
+///
+/// 快进超过段尾时直接进入下一段；快退最多回到段首。
+fn segment_scrubber(ui: &mut egui::Ui, app_state: &AppState, seek_audio_events: &mut EventWriter<SeekAudioEvent>) {
+    let position = app_state
+        .playback_position
+        .filter(|_| matches!(app_state.playback_state, PlaybackState::Playing | PlaybackState::Paused));
+    let elapsed = position.map_or(std::time::Duration::ZERO, |p| p.elapsed);
+    let duration = position.and_then(|p| p.duration);
+
+    // 点击的快退 / 快进按钮对应的目标位置
+    let skip_button = |ui: &mut egui::Ui, seconds: i64| {
+        let (text, tooltip) = if seconds < 0 {
+            (format!("-{}", -seconds), format!("后退 {} 秒", -seconds))
+        } else {
+            (format!("+{}", seconds), format!("前进 {} 秒", seconds))
+        };
+        let button = egui::Button::new(egui::RichText::new(text).size(12.0).color(colors::TEXT_SECONDARY))
+            .fill(colors::BG_CARD)
+            .rounding(6.0)
+            .min_size(egui::vec2(36.0, 22.0));
+        let clicked = ui.add_enabled(position.is_some(), button).on_hover_text(tooltip).clicked();
+        let delta = std::time::Duration::from_secs(seconds.unsigned_abs());
+        clicked.then(|| if seconds < 0 { elapsed.saturating_sub(delta) } else { elapsed + delta })
+    };
+
+    let mut seek_to = None;
+    ui.horizontal(|ui| {
+        seek_to = seek_to.or(skip_button(ui, -30));
+        seek_to = seek_to.or(skip_button(ui, -10));
+        ui.label(egui::RichText::new(format_duration(elapsed)).size(12.0).color(colors::TEXT_SECONDARY).monospace());
+
+        // 右侧的时长标签和按钮约占 130px
+        ui.spacing_mut().slider_width = (ui.available_width() - 130.0).max(60.0);
+        let max = duration.map_or(1.0, |d| d.as_secs_f64().max(0.001));
+        let mut value = elapsed.as_secs_f64().min(max);
+        let slider = egui::Slider::new(&mut value, 0.0..=max).show_value(false).trailing_fill(true);
+        let response = ui.add_enabled(duration.is_some(), slider);
+        if response.drag_stopped() {
+            seek_to = Some(std::time::Duration::from_secs_f64(value));
+        }
+
+        let total = duration.map_or_else(|| "--:--".to_string(), format_duration);
+        ui.label(egui::RichText::new(total).size(12.0).color(colors::TEXT_MUTED).monospace())
+            .on_hover_text(if duration.is_some() { "本段时长" } else { "本段下载完成后显示时长" });
+        seek_to = seek_to.or(skip_button(ui, 10));
+        seek_to = seek_to.or(skip_button(ui, 30));
+    });
+
+    if let Some(position) = seek_to {
+        seek_audio_events.send(SeekAudioEvent { position });
+    }
+}
+
+/// 倍速菜单：保持音调变速，按小说保存
+fn speed_menu(ui: &mut egui::Ui, app_state: &mut AppState) {
+    let Some(session) = app_state.current_session.as_mut() else { return };
+    let color = if session.speed == 1.0 { colors::TEXT_SECONDARY } else { colors::ACCENT };
+
+    ui.menu_button(egui::RichText::new(format!("⏩ {}×", format_speed(session.speed))).size(12.0).color(color), |ui| {
+        ui.label(egui::RichText::new("播放速度").size(12.0).color(colors::TEXT_MUTED));
+        for speed in [0.75, 1.0, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0] {
+            if ui.selectable_label(session.speed == speed, format!("{}×", format_speed(speed))).clicked() {
+                session.speed = speed;
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        ui.add(
+            egui::Slider::new(&mut session.speed, MIN_SPEED..=MAX_SPEED)
+                .step_by(0.05)
+                .custom_formatter(|speed, _| format!("{}×", format_speed(speed as f32))),
+        );
+    });
+}
+
+/// 倍速显示，去掉多余的零，例如 `1.25`、`1.5`、`2`
+fn format_speed(speed: f32) -> String {
+    let text = format!("{:.2}", speed);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// 跳转到指定段落；目标不在已加载范围内时先重新加载附近的段落
+fn seek_to_segment(
+    app_state: &mut AppState,
+    api_events: &mut EventWriter<ApiRequest>,
+    session: &CurrentSession,
+    index: usize,
+) {
+    if !app_state.segment_pagination.loaded_range.contains(&index) {
+        // 目标位置前后各加载一些，加载完成后滚动到目标段落
+        app_state.segments.clear();
+        app_state.segment_pagination.loaded_range = 0..0;
+        api_events.send(ApiRequest::LoadSegments {
+            novel_id: session.novel_id,
+            start: Some(index.saturating_sub(15)),
+            limit: Some(100),
+        });
+    }
+    app_state.scroll_to_segment = Some(index);
+    api_events.send(ApiRequest::Seek {
+        session_id: session.session_id.clone(),
+        segment_index: index as u32,
+        sync_only: false,
+    });
+}
+
+fn bookmark_panel_ui(
+    ui: &mut egui::Ui,
+    app_state: &mut AppState,
+    api_events: &mut EventWriter<ApiRequest>,
+    stop_audio_events: &mut EventWriter<StopAudioEvent>,
+    file_picker_events: &mut EventWriter<FilePickerRequest>,
+    bookmarks: &mut Bookmarks,
+    session: &CurrentSession,
+) {
+    let novel_id = session.novel_id;
+    let list = bookmarks.for_novel(novel_id).to_vec();
+
+    ui.horizontal(|ui| {
+        ui.label(egui::RichText::new("🔖 书签").size(16.0).strong().color(colors::TEXT_PRIMARY));
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let can_export = !list.is_empty() && !app_state.bookmark_panel.picking_file;
+            if ui
+                .add_enabled(can_export, egui::Button::new("导出 Markdown").fill(colors::BG_CARD).rounding(6.0))
+                .on_hover_text("导出书签、段落原文和笔记")
+                .clicked()
+            {
+                app_state.bookmark_panel.picking_file = true;
+                file_picker_events.send(FilePickerRequest {
+                    picker_type: FilePickerType::BookmarksFile,
+                });
+            }
+        });
+    });
+    ui.add_space(8.0);
+
+    if list.is_empty() {
+        ui.label(
+            egui::RichText::new("点击段落左侧的 ☆ 添加书签")
+                .size(13.0)
+                .color(colors::TEXT_MUTED),
+        );
+        return;
+    }
+
+    let mut jump_to: Option<u32> = None;
+    let mut to_remove: Option<u32> = None;
+    let mut note_to_save: Option<(u32, String)> = None;
+
+    egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+        for bookmark in &list {
+            let index = bookmark.segment_index;
+            egui::Frame::none()
+                .fill(colors::BG_CARD)
+                .rounding(8.0)
+                .inner_margin(10.0)
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .link(egui::RichText::new(format!("第 {} 段", index + 1)).size(13.0).color(colors::ACCENT))
+                            .on_hover_text("跳转到此段")
+                            .clicked()
+                        {
+                            jump_to = Some(index);
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if icon_button(ui, "🗑", "删除书签").clicked() {
+                                to_remove = Some(index);
+                            }
+                            if app_state.bookmark_panel.editing != Some(index) && icon_button(ui, "✏", "编辑笔记").clicked() {
+                                app_state.bookmark_panel.editing = Some(index);
+                                app_state.bookmark_panel.note_draft = bookmark.note.clone();
+                            }
+                        });
+                    });
+                    let preview: String = bookmark.text.chars().take(80).collect();
+                    let ellipsis = if bookmark.text.chars().count() > 80 { "…" } else { "" };
+                    ui.add(
+                        egui::Label::new(
+                            egui::RichText::new(format!("{}{}", preview, ellipsis))
+                                .size(13.0)
+                                .color(colors::TEXT_SECONDARY),
+                        )
+                        .wrap(),
+                    );
+
+                    if app_state.bookmark_panel.editing == Some(index) {
+                        ui.add_space(6.0);
+                        ui.add(
+                            egui::TextEdit::multiline(&mut app_state.bookmark_panel.note_draft)
+                                .hint_text("写点笔记...")
+                                .desired_rows(3)
+                                .desired_width(f32::INFINITY),
+                        );
+                        ui.horizontal(|ui| {
+                            if ui.small_button("保存").clicked() {
+                                note_to_save = Some((index, std::mem::take(&mut app_state.bookmark_panel.note_draft)));
+                            }
+                            if ui.small_button("取消").clicked() {
+                                app_state.bookmark_panel.editing = None;
+                            }
+                        });
+                    } else if !bookmark.note.is_empty() {
+                        ui.add_space(4.0);
+                        ui.add(
+                            egui::Label::new(
+                                egui::RichText::new(format!("📝 {}", bookmark.note))
+                                    .size(13.0)
+                                    .color(colors::TEXT_PRIMARY),
+                            )
+                            .wrap(),
+                        );
+                    }
+                });
+            ui.add_space(6.0);
+        }
+    });
+
+    if let Some(index) = jump_to {
+        stop_audio_events.send(StopAudioEvent);
+        seek_to_segment(app_state, api_events, session, index as usize);
+    }
+    if let Some(index) = to_remove {
+        bookmarks.remove(novel_id, index);
+    }
+    if let Some((index, note)) = note_to_save {
+        bookmarks.set_note(novel_id, index, &note);
+        app_state.bookmark_panel.editing = None;
+    }
+}
+
+/// 以 MB 显示字节数
+fn format_megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// 时长显示为 `H:MM:SS`，不足一小时为 `MM:SS`
+fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// 距离某个 Unix 时间（秒）过去了多久，例如「3 小时前」
+fn format_elapsed(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let elapsed = now.saturating_sub(timestamp);
+    match elapsed {
+        0..60 => "刚刚".to_string(),
+        60..3600 => format!("{} 分钟前", elapsed / 60),
+        3600..86400 => format!("{} 小时前", elapsed / 3600),
+        _ => format!("{} 天前", elapsed / 86400),
+    }
+}
@@ -0,0 +1,135 @@
+//! 分块上传状态持久化
+//!
+//! 大文件按分块上传，每完成一个分块就把进度写入磁盘
+//! （`<配置目录>/rovel-desk/uploads/<key>.json`），
+//! 网络中断或应用重启后可以从上次的位置继续上传。
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::config::app_config_dir;
+use crate::fs_util::write_atomic;
+
+/// 一次分块上传的本地状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkedUploadState {
+    /// 上传目标服务器（API base URL），不同服务器的上传互不干扰
+    pub server_url: String,
+    /// 服务端分配的上传 ID
+    pub upload_id: String,
+    pub title: String,
+    pub file_path: PathBuf,
+    /// 转换为 UTF-8 后的内容大小
+    pub total_size: u64,
+    pub chunk_size: u64,
+    /// 内容指纹，文件被修改后不能继续旧的上传
+    pub fingerprint: u64,
+    /// 已确认上传完成的分块序号
+    #[serde(default)]
+    pub completed_parts: BTreeSet<u32>,
+}
+
+impl ChunkedUploadState {
+    /// 分块总数
+    pub fn part_count(&self) -> u32 {
+        if self.chunk_size == 0 {
+            return 0;
+        }
+        self.total_size.div_ceil(self.chunk_size) as u32
+    }
+
+    /// 第 `index` 个分块在内容中的字节范围
+    pub fn part_range(&self, index: u32) -> std::ops::Range<usize> {
+        let start = (index as u64 * self.chunk_size).min(self.total_size);
+        let end = (start + self.chunk_size).min(self.total_size);
+        start as usize..end as usize
+    }
+
+    /// 已上传字节数
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.completed_parts
+            .iter()
+            .map(|&index| self.part_range(index).len() as u64)
+            .sum()
+    }
+}
+
+/// 分块上传状态存储（每个上传一个 JSON 文件）
+#[derive(Debug, Clone)]
+pub struct UploadStateStore {
+    dir: PathBuf,
+}
+
+impl UploadStateStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// 默认存储目录：`<配置目录>/rovel-desk/uploads`
+    pub fn default_dir() -> Option<PathBuf> {
+        app_config_dir().map(|dir| dir.join("uploads"))
+    }
+
+    /// 查找同一服务器、同一文件的未完成上传
+    pub fn find(&self, server_url: &str, file_path: &Path) -> Option<ChunkedUploadState> {
+        let path = self.state_path(server_url, file_path);
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str::<ChunkedUploadState>(&content) {
+            Ok(state) if state.server_url == server_url && state.file_path == file_path => Some(state),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Ignoring corrupt upload state {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// 保存上传状态（原子替换，避免写入一半时崩溃导致文件损坏）
+    pub fn save(&self, state: &ChunkedUploadState) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.state_path(&state.server_url, &state.file_path);
+        let content = serde_json::to_string_pretty(state)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        write_atomic(&path, content.as_bytes())
+    }
+
+    pub fn remove(&self, state: &ChunkedUploadState) {
+        let path = self.state_path(&state.server_url, &state.file_path);
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Failed to remove upload state {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// 指定服务器上所有未完成的上传（用于应用重启后继续上传）
+    pub fn pending(&self, server_url: &str) -> Vec<ChunkedUploadState> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut states: Vec<ChunkedUploadState> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str::<ChunkedUploadState>(&content).ok())
+            .filter(|state| state.server_url == server_url)
+            .collect();
+        states.sort_by(|a, b| a.title.cmp(&b.title));
+        states
+    }
+
+    /// 以服务器地址 + 文件路径为键，同一文件同时只保留一个上传
+    fn state_path(&self, server_url: &str, file_path: &Path) -> PathBuf {
+        let key = format!("{}\n{}", server_url, file_path.display());
+        self.dir.join(format!("{:016x}.json", fingerprint(key.as_bytes())))
+    }
+}
+
+/// 内容指纹（FNV-1a 64 位，跨版本稳定，写入磁盘后仍可比较）
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &b| (hash ^ b as u64).wrapping_mul(PRIME))
+}
@@ -3,6 +3,9 @@
 //! 支持两种连接类型:
 //! - /ws/session/{session_id}: 接收任务状态推送 (TaskStateChanged, SessionClosed)
 //! - /ws/events: 全局事件通道 (NovelReady, NovelFailed)
+//!
+//! 两种连接的握手都携带 `Authorization: Bearer <token>`，握手返回 401 时刷新 token 后重试一次。
+//! `wss://` 连接使用与 HTTP 客户端相同的 TLS 配置（自定义 CA / 证书指纹）。
 
 use bevy::prelude::*;
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
@@ -10,9 +13,13 @@ use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
 
-use crate::api::{WsEvent, WS_BASE_URL};
+use crate::api::{ApiClient, WsEvent};
+use crate::backend::Backend;
+use crate::config::AppConfig;
 use crate::state::{WsConnectionState, WsRequest, WsResponse};
 
+type WsSocket = tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>;
+
 /// WebSocket 内部命令
 enum WsCommand {
     Connect { session_id: String },
@@ -42,13 +49,13 @@ pub struct GlobalWsClient {
 }
 
 impl WsClient {
-    pub fn new() -> Self {
+    pub fn new(ws_base_url: String, api_client: ApiClient) -> Self {
         let (command_tx, command_rx) = mpsc::channel::<WsCommand>();
         let (response_tx, response_rx) = mpsc::channel::<WsResponse>();
 
         // 启动 WebSocket 线程
         thread::spawn(move || {
-            ws_thread(command_rx, response_tx);
+            ws_thread(ws_base_url, api_client, command_rx, response_tx);
         });
 
         Self {
@@ -85,13 +92,13 @@ impl Drop for WsClient {
 }
 
 impl GlobalWsClient {
-    pub fn new() -> Self {
+    pub fn new(ws_base_url: String, api_client: ApiClient) -> Self {
         let (command_tx, command_rx) = mpsc::channel::<GlobalWsCommand>();
         let (response_tx, response_rx) = mpsc::channel::<WsResponse>();
 
         // 启动全局事件 WebSocket 线程
         thread::spawn(move || {
-            global_ws_thread(command_rx, response_tx);
+            global_ws_thread(ws_base_url, api_client, command_rx, response_tx);
         });
 
         Self {
@@ -126,12 +133,96 @@ impl Drop for GlobalWsClient {
     }
 }
 
+/// 建立 WebSocket 连接，握手携带 Bearer token
+///
+/// 握手返回 401 时通过 `ApiClient` 刷新 token 后重试一次。
+#[allow(clippy::result_large_err)]
+fn connect_authorized(url: &str, api_client: &ApiClient) -> Result<WsSocket, tungstenite::Error> {
+    let sent_token = api_client.auth().access_token();
+    match open_socket(handshake_request(url, api_client)?, api_client) {
+        Err(tungstenite::Error::Http(resp))
+            if resp.status() == tungstenite::http::StatusCode::UNAUTHORIZED
+                && api_client.refresh_after_unauthorized(sent_token.as_deref()) =>
+        {
+            tracing::info!("WebSocket handshake unauthorized, retrying with refreshed token");
+            open_socket(handshake_request(url, api_client)?, api_client)
+        }
+        result => result,
+    }
+}
+
+/// 建立 TCP 连接并完成 WebSocket 握手（`wss://` 时先完成 TLS 握手）
+#[allow(clippy::result_large_err)]
+fn open_socket(
+    request: tungstenite::handshake::client::Request,
+    api_client: &ApiClient,
+) -> Result<WsSocket, tungstenite::Error> {
+    use tungstenite::error::UrlError;
+    use tungstenite::handshake::HandshakeError;
+
+    let uri = request.uri();
+    let host = uri
+        .host()
+        .ok_or(tungstenite::Error::Url(UrlError::NoHostName))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("wss") { 443 } else { 80 });
+    let stream = std::net::TcpStream::connect((host.as_str(), port))?;
+    let _ = stream.set_nodelay(true);
+
+    let connector = tungstenite::Connector::Rustls(api_client.tls_config());
+    match tungstenite::client_tls_with_config(request, stream, None, Some(connector)) {
+        Ok((socket, _)) => Ok(socket),
+        Err(HandshakeError::Failure(e)) => Err(e),
+        // 阻塞模式下握手不会被中断
+        Err(HandshakeError::Interrupted(_)) => Err(tungstenite::Error::Io(std::io::ErrorKind::WouldBlock.into())),
+    }
+}
+
+/// 将底层 TCP 连接设为非阻塞（明文和 TLS 连接都需要，否则读取会阻塞命令处理）
+fn set_nonblocking(socket: &WsSocket) {
+    let result = match socket.get_ref() {
+        tungstenite::stream::MaybeTlsStream::Plain(stream) => stream.set_nonblocking(true),
+        tungstenite::stream::MaybeTlsStream::Rustls(stream) => stream.get_ref().set_nonblocking(true),
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        tracing::warn!("Failed to set WebSocket non-blocking: {}", e);
+    }
+}
+
+/// WebSocket 握手请求（已登录时添加 `Authorization` 头）
+#[allow(clippy::result_large_err)]
+fn handshake_request(
+    url: &str,
+    api_client: &ApiClient,
+) -> Result<tungstenite::handshake::client::Request, tungstenite::Error> {
+    use tungstenite::client::IntoClientRequest;
+
+    let mut request = url.into_client_request()?;
+    if let Some(bearer) = api_client.auth().bearer() {
+        let value = bearer
+            .parse()
+            .map_err(|e: tungstenite::http::header::InvalidHeaderValue| tungstenite::Error::HttpFormat(e.into()))?;
+        request.headers_mut().insert(tungstenite::http::header::AUTHORIZATION, value);
+    }
+    Ok(request)
+}
+
 /// WebSocket 线程主循环 (Session channel)
-fn ws_thread(command_rx: Receiver<WsCommand>, response_tx: Sender<WsResponse>) {
-    use tungstenite::{connect, Message};
+fn ws_thread(
+    ws_base_url: String,
+    api_client: ApiClient,
+    command_rx: Receiver<WsCommand>,
+    response_tx: Sender<WsResponse>,
+) {
+    use tungstenite::Message;
     use url::Url;
 
-    let mut current_socket: Option<tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>> = None;
+    let mut current_socket: Option<WsSocket> = None;
 
     loop {
         // 检查命令
@@ -143,15 +234,13 @@ fn ws_thread(command_rx: Receiver<WsCommand>, response_tx: Sender<WsResponse>) {
                 }
 
                 // 建立新连接
-                let url = format!("{}/session/{}", WS_BASE_URL, session_id);
+                let url = format!("{}/session/{}", ws_base_url, session_id);
                 match Url::parse(&url) {
                     Ok(url) => {
-                        match connect(url.as_str()) {
-                            Ok((socket, _)) => {
+                        match connect_authorized(url.as_str(), &api_client) {
+                            Ok(socket) => {
                                 // 设置非阻塞模式
-                                if let tungstenite::stream::MaybeTlsStream::Plain(ref stream) = socket.get_ref() {
-                                    let _ = stream.set_nonblocking(true);
-                                }
+                                set_nonblocking(&socket);
                                 current_socket = Some(socket);
                                 let _ = response_tx.send(WsResponse::Connected);
                             }
@@ -239,11 +328,16 @@ fn ws_thread(command_rx: Receiver<WsCommand>, response_tx: Sender<WsResponse>) {
 }
 
 /// 全局事件 WebSocket 线程主循环
-fn global_ws_thread(command_rx: Receiver<GlobalWsCommand>, response_tx: Sender<WsResponse>) {
-    use tungstenite::{connect, Message};
+fn global_ws_thread(
+    ws_base_url: String,
+    api_client: ApiClient,
+    command_rx: Receiver<GlobalWsCommand>,
+    response_tx: Sender<WsResponse>,
+) {
+    use tungstenite::Message;
     use url::Url;
 
-    let mut current_socket: Option<tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>> = None;
+    let mut current_socket: Option<WsSocket> = None;
     let mut should_reconnect = false;
     let mut reconnect_delay = Duration::from_secs(1);
     let mut consecutive_failures = 0u32;
@@ -287,20 +381,18 @@ fn global_ws_thread(command_rx: Receiver<GlobalWsCommand>, response_tx: Sender<W
                 consecutive_failures = 5; // 重置一部分，允许继续尝试
             }
             
-            let url = format!("{}/events", WS_BASE_URL);
+            let url = format!("{}/events", ws_base_url);
             match Url::parse(&url) {
                 Ok(url) => {
-                    match connect(url.as_str()) {
-                        Ok((socket, _)) => {
+                    match connect_authorized(url.as_str(), &api_client) {
+                        Ok(socket) => {
                             // 设置非阻塞模式
-                            if let tungstenite::stream::MaybeTlsStream::Plain(ref stream) = socket.get_ref() {
-                                let _ = stream.set_nonblocking(true);
-                            }
+                            set_nonblocking(&socket);
                             current_socket = Some(socket);
                             reconnect_delay = Duration::from_secs(1);
                             consecutive_failures = 0;
                             let _ = response_tx.send(WsResponse::GlobalConnected);
-                            tracing::info!("Global WebSocket connected to {}", WS_BASE_URL);
+                            tracing::info!("Global WebSocket connected to {}", ws_base_url);
                         }
                         Err(e) => {
                             consecutive_failures += 1;
@@ -458,13 +550,32 @@ pub fn poll_global_ws_responses(
 }
 
 /// 设置 WebSocket 客户端 (Session + Global)
-pub fn setup_ws_client(mut commands: Commands) {
+pub fn setup_ws_client(
+    mut commands: Commands,
+    config: Res<AppConfig>,
+    api_client: Res<ApiClient>,
+    backend: Res<Backend>,
+) {
+    spawn_ws_clients(&mut commands, config.active().ws_base_url(), &api_client, &backend);
+}
+
+/// 创建 Session + Global WebSocket 客户端
+///
+/// 已存在的客户端资源会被替换，旧客户端 Drop 时发送 Shutdown 关闭其线程和连接。
+/// 两个线程与 `api_client` 共享登录 token。后端不使用 WebSocket 时只移除旧客户端。
+pub fn spawn_ws_clients(commands: &mut Commands, ws_base_url: String, api_client: &ApiClient, backend: &Backend) {
+    if !backend.uses_websocket() {
+        commands.remove_resource::<WsClient>();
+        commands.remove_resource::<GlobalWsClient>();
+        return;
+    }
+
     // Session channel client
-    let client = WsClient::new();
+    let client = WsClient::new(ws_base_url.clone(), api_client.clone());
     commands.insert_resource(client);
     
     // Global events channel client - 启动时自动连接
-    let global_client = GlobalWsClient::new();
+    let global_client = GlobalWsClient::new(ws_base_url, api_client.clone());
     global_client.connect(); // 自动连接全局事件通道
     commands.insert_resource(global_client);
 }